@@ -1,10 +1,12 @@
 #![feature(impl_trait_in_assoc_type)]
-#![feature(slice_pattern)]
-use core::panic;
+use neural_network as nn;
 use rand::Rng;
 use rand::{seq::SliceRandom, RngCore};
 use std::ops::Index;
 
+pub use self::neat::*;
+mod neat;
+
 //Statistics::
 //
 #[derive(Clone, Debug)]
@@ -19,11 +21,14 @@ pub struct GeneticAlgorithm<S> {
     crossover_method: Box<dyn CrossoverMethod>,
     mutation_method: Box<dyn MutationMethod>,
 }
+#[derive(Default)]
 pub struct RouletteWheelSelection;
 
+/// Genes of an individual, `f32`s by default; other scalar types, e.g.
+/// `nn::Fixed`, can hold the weights of networks cast to them.
 #[derive(Clone, Debug)]
-pub struct Chromosome {
-    genes: Vec<f32>,
+pub struct Chromosome<T = f32> {
+    genes: Vec<T>,
 }
 
 pub trait Individual {
@@ -58,7 +63,7 @@ pub trait CrossoverMethod {
     ) -> Chromosome;
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct UniformCrossover;
 
 impl UniformCrossover {
@@ -105,20 +110,24 @@ pub struct GaussianMutation {
 
 impl GaussianMutation {
     pub fn new(chance: f32, coeff: f32) -> Self {
-        assert!(chance >= 0.0 && chance <= 1.0);
+        assert!((0.0..=1.0).contains(&chance));
 
         Self { chance, coeff }
     }
+
+    fn mutate_gene(&self, rng: &mut dyn RngCore, gene: &mut f32) {
+        let sign = if rng.gen_bool(0.5) { -1.0 } else { 1.0 };
+
+        if rng.gen_bool(self.chance as _) {
+            *gene += sign * self.coeff * rng.gen::<f32>();
+        }
+    }
 }
 
 impl MutationMethod for GaussianMutation {
     fn mutate(&self, rng: &mut dyn RngCore, child: &mut Chromosome) {
         for gene in child.iter_mut() {
-            let sign = if rng.gen_bool(0.5) { -1.0 } else { 1.0 };
-
-            if rng.gen_bool(self.chance as _) {
-                *gene += sign * self.coeff * rng.gen::<f32>();
-            }
+            self.mutate_gene(rng, gene);
         }
     }
 }
@@ -171,15 +180,18 @@ impl Statistics {
     where
         I: Individual,
     {
-        assert!(!population.is_empty());
+        Self::from_fitness(population.iter().map(I::fitness))
+    }
 
-        let mut min_fitness = population[0].fitness();
-        let mut max_fitness = min_fitness;
-        let mut sum_fitness = 0.0;
+    fn from_fitness(fitness: impl ExactSizeIterator<Item = f32>) -> Self {
+        let len = fitness.len();
+        assert!(len > 0);
 
-        for individual in population {
-            let fitness = individual.fitness();
+        let mut min_fitness = f32::INFINITY;
+        let mut max_fitness = f32::NEG_INFINITY;
+        let mut sum_fitness = 0.0;
 
+        for fitness in fitness {
             min_fitness = min_fitness.min(fitness);
             max_fitness = max_fitness.max(fitness);
             sum_fitness += fitness;
@@ -188,7 +200,7 @@ impl Statistics {
         Self {
             min_fitness,
             max_fitness,
-            avg_fitness: sum_fitness / (population.len() as f32),
+            avg_fitness: sum_fitness / (len as f32),
         }
     }
 
@@ -211,43 +223,47 @@ impl RouletteWheelSelection {
     }
 }
 
-impl Chromosome {
-    pub fn new(genes: Vec<f32>) -> Self {
+impl<T> Chromosome<T> {
+    pub fn new(genes: Vec<T>) -> Self {
         Self { genes }
     }
     pub fn len(&self) -> usize {
         self.genes.len()
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = &f32> {
+    pub fn is_empty(&self) -> bool {
+        self.genes.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
         self.genes.iter()
     }
 
-    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut f32> {
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
         self.genes.iter_mut()
     }
 }
 
 //Impl of std library functions for Chromosome:
-impl Index<usize> for Chromosome {
-    type Output = f32;
+impl<T> Index<usize> for Chromosome<T> {
+    type Output = T;
 
     fn index(&self, index: usize) -> &Self::Output {
         &self.genes[index]
     }
 }
 
-impl FromIterator<f32> for Chromosome {
-    fn from_iter<T: IntoIterator<Item = f32>>(iter: T) -> Self {
+impl<T> FromIterator<T> for Chromosome<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
         Self {
             genes: iter.into_iter().collect(),
         }
     }
 }
 
-impl IntoIterator for Chromosome {
-    type Item = f32;
-    type IntoIter = impl Iterator<Item = f32>;
+impl<T> IntoIterator for Chromosome<T> {
+    type Item = T;
+    type IntoIter = impl Iterator<Item = T>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.genes.into_iter()
@@ -312,7 +328,7 @@ mod tests {
         fn test() {
             use rand::SeedableRng;
             use rand_chacha::ChaCha8Rng;
-            use std::{collections::BTreeMap, iter::FromIterator};
+            use std::collections::BTreeMap;
             let method = RouletteWheelSelection::new();
             let mut rng = ChaCha8Rng::from_seed(Default::default());
 
@@ -460,8 +476,6 @@ mod tests {
             }
 
             mod and_zero_coefficient {
-                use core::slice::SlicePattern;
-
                 use super::*;
                 #[test]
                 fn does_not_change_the_original_chromosome() {
@@ -0,0 +1,948 @@
+use crate::*;
+use std::collections::HashMap;
+
+//Share of every species (by fitness) allowed to reproduce
+const SURVIVAL: f32 = 0.5;
+//Species at least this big keep their champion unchanged
+const ELITISM_MIN_SPECIES: usize = 5;
+//Chance that a gene disabled in either parent stays disabled in the child
+const DISABLED_CHANCE: f64 = 0.75;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    Input,
+    Output,
+    Hidden,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NodeGene {
+    pub id: usize,
+    pub kind: NodeKind,
+    /// Unused for input nodes, like the activation.
+    pub bias: f32,
+    pub activation: nn::Activation,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConnectionGene {
+    /// Historical marking, shared by every genome that got this connection
+    /// from the same structural mutation.
+    pub innovation: usize,
+    pub from: usize,
+    pub to: usize,
+    pub weight: f32,
+    pub enabled: bool,
+}
+
+/// Hands out node ids and innovation numbers, so that the same structural
+/// mutation gets the same numbers in every genome of a run.
+///
+/// Nodes `0..inputs` are the inputs and the following `outputs` nodes are
+/// the outputs; hidden nodes come after that. New hidden nodes use tanh and
+/// outputs the identity, unless `with_activations` says otherwise.
+#[derive(Clone, Debug)]
+pub struct Innovations {
+    inputs: usize,
+    outputs: usize,
+    hidden_activation: nn::Activation,
+    output_activation: nn::Activation,
+    next_node: usize,
+    connections: HashMap<(usize, usize), usize>,
+    //Innovation of the split connection -> the node put in its place
+    splits: HashMap<usize, usize>,
+}
+
+impl Innovations {
+    pub fn new(inputs: usize, outputs: usize) -> Self {
+        Self {
+            inputs,
+            outputs,
+            hidden_activation: nn::Activation::Tanh,
+            output_activation: nn::Activation::Identity,
+            next_node: inputs + outputs,
+            connections: HashMap::new(),
+            splits: HashMap::new(),
+        }
+    }
+
+    pub fn with_activations(mut self, hidden: nn::Activation, output: nn::Activation) -> Self {
+        self.hidden_activation = hidden;
+        self.output_activation = output;
+        self
+    }
+
+    pub fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    pub fn outputs(&self) -> usize {
+        self.outputs
+    }
+
+    fn connection(&mut self, from: usize, to: usize) -> usize {
+        let next = self.connections.len();
+        *self.connections.entry((from, to)).or_insert(next)
+    }
+
+    fn split(&mut self, innovation: usize) -> usize {
+        if let Some(&node) = self.splits.get(&innovation) {
+            return node;
+        }
+
+        let node = self.next_node;
+        self.next_node += 1;
+        self.splits.insert(innovation, node);
+        node
+    }
+}
+
+/// NEAT genome (Stanley & Miikkulainen, 2002): a feed-forward network
+/// described gene by gene, which grows new nodes and connections as it
+/// mutates.
+///
+/// Nodes are kept sorted by id and connections by innovation number.
+#[derive(Clone, Debug)]
+pub struct Genome {
+    nodes: Vec<NodeGene>,
+    connections: Vec<ConnectionGene>,
+}
+
+impl Genome {
+    /// Every input connected straight to every output, with weights and
+    /// biases in [-1, 1].
+    pub fn minimal(rng: &mut dyn RngCore, innovations: &mut Innovations) -> Self {
+        let (inputs, outputs) = (innovations.inputs, innovations.outputs);
+
+        let nodes = (0..inputs)
+            .map(|id| NodeGene {
+                id,
+                kind: NodeKind::Input,
+                bias: 0.0,
+                activation: nn::Activation::Identity,
+            })
+            .chain((inputs..inputs + outputs).map(|id| NodeGene {
+                id,
+                kind: NodeKind::Output,
+                bias: rng.gen_range(-1.0..=1.0),
+                activation: innovations.output_activation,
+            }))
+            .collect();
+
+        let mut connections: Vec<_> = (0..inputs)
+            .flat_map(|from| (inputs..inputs + outputs).map(move |to| (from, to)))
+            .map(|(from, to)| ConnectionGene {
+                innovation: innovations.connection(from, to),
+                from,
+                to,
+                weight: rng.gen_range(-1.0..=1.0),
+                enabled: true,
+            })
+            .collect();
+
+        connections.sort_by_key(|connection| connection.innovation);
+
+        Self { nodes, connections }
+    }
+
+    pub fn nodes(&self) -> &[NodeGene] {
+        &self.nodes
+    }
+
+    pub fn connections(&self) -> &[ConnectionGene] {
+        &self.connections
+    }
+
+    /// Compiles the genome's enabled connections into a network that takes
+    /// one input per input node and returns one output per output node, both
+    /// in order of their ids.
+    ///
+    /// Fails if the genome doesn't describe a valid network, e.g. if its
+    /// enabled connections form a cycle; mutations and crossover never lead
+    /// to such genomes, but genomes put together by hand can.
+    pub fn to_network(&self) -> Result<nn::SparseNetwork, nn::NetworkError> {
+        let ids = |kind| -> Vec<_> {
+            self.nodes
+                .iter()
+                .filter(|node| node.kind == kind)
+                .map(|node| node.id)
+                .collect()
+        };
+
+        let neurons: Vec<_> = self
+            .nodes
+            .iter()
+            .filter(|node| node.kind != NodeKind::Input)
+            .map(|node| nn::SparseNeuron {
+                id: node.id,
+                bias: node.bias,
+                activation: node.activation,
+            })
+            .collect();
+
+        let connections: Vec<_> = self
+            .connections
+            .iter()
+            .filter(|connection| connection.enabled)
+            .map(|connection| nn::Connection {
+                from: connection.from,
+                to: connection.to,
+                weight: connection.weight,
+            })
+            .collect();
+
+        nn::SparseNetwork::try_from_connections(
+            &ids(NodeKind::Input),
+            &ids(NodeKind::Output),
+            &neurons,
+            &connections,
+        )
+    }
+
+    fn node(&self, id: usize) -> Option<&NodeGene> {
+        self.nodes
+            .binary_search_by_key(&id, |node| node.id)
+            .ok()
+            .map(|index| &self.nodes[index])
+    }
+
+    fn connection(&self, innovation: usize) -> Option<&ConnectionGene> {
+        self.connections
+            .binary_search_by_key(&innovation, |connection| connection.innovation)
+            .ok()
+            .map(|index| &self.connections[index])
+    }
+
+    fn insert_node(&mut self, node: NodeGene) {
+        let index = self.nodes.partition_point(|other| other.id < node.id);
+        self.nodes.insert(index, node);
+    }
+
+    fn insert_connection(&mut self, connection: ConnectionGene) {
+        let index = self
+            .connections
+            .partition_point(|other| other.innovation < connection.innovation);
+
+        self.connections.insert(index, connection);
+    }
+
+    /// Whether `to` can be reached from `from`, following connections whether
+    /// they are enabled or not - crossover may enable them again later.
+    fn reaches(&self, from: usize, to: usize) -> bool {
+        let mut pending = vec![from];
+        let mut visited = vec![from];
+
+        while let Some(node) = pending.pop() {
+            if node == to {
+                return true;
+            }
+
+            for connection in self.connections.iter().filter(|c| c.from == node) {
+                if !visited.contains(&connection.to) {
+                    visited.push(connection.to);
+                    pending.push(connection.to);
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Perturbs weights and biases the same way `GaussianMutation` perturbs
+    /// genes.
+    pub fn mutate_weights(&mut self, rng: &mut dyn RngCore, mutation: &GaussianMutation) {
+        for node in &mut self.nodes {
+            if node.kind != NodeKind::Input {
+                mutation.mutate_gene(rng, &mut node.bias);
+            }
+        }
+
+        for connection in &mut self.connections {
+            mutation.mutate_gene(rng, &mut connection.weight);
+        }
+    }
+
+    /// Connects two nodes that weren't connected yet, without creating a
+    /// cycle. Returns `false` when no such pair is left.
+    pub fn add_connection(&mut self, rng: &mut dyn RngCore, innovations: &mut Innovations) -> bool {
+        let candidates: Vec<_> = self
+            .nodes
+            .iter()
+            .filter(|from| from.kind != NodeKind::Output)
+            .flat_map(|from| {
+                self.nodes
+                    .iter()
+                    .filter(|to| to.kind != NodeKind::Input)
+                    .map(move |to| (from.id, to.id))
+            })
+            .filter(|&(from, to)| {
+                from != to
+                    && !self
+                        .connections
+                        .iter()
+                        .any(|connection| connection.from == from && connection.to == to)
+                    && !self.reaches(to, from)
+            })
+            .collect();
+
+        let Some(&(from, to)) = candidates.choose(rng) else {
+            return false;
+        };
+
+        self.insert_connection(ConnectionGene {
+            innovation: innovations.connection(from, to),
+            from,
+            to,
+            weight: rng.gen_range(-1.0..=1.0),
+            enabled: true,
+        });
+
+        true
+    }
+
+    /// Splits an enabled connection in two with a new hidden node in between.
+    /// The incoming half gets a weight of 1 and the outgoing half the old
+    /// weight, so the network's behaviour barely changes. Returns `false` when
+    /// there is nothing left to split.
+    pub fn add_node(&mut self, rng: &mut dyn RngCore, innovations: &mut Innovations) -> bool {
+        let candidates: Vec<_> = self
+            .connections
+            .iter()
+            .enumerate()
+            .filter(|(_, connection)| connection.enabled)
+            //Re-enabled by crossover after this genome had split it already
+            .filter(|(_, connection)| {
+                innovations
+                    .splits
+                    .get(&connection.innovation)
+                    .is_none_or(|&node| self.node(node).is_none())
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        let Some(&index) = candidates.choose(rng) else {
+            return false;
+        };
+
+        let split = &mut self.connections[index];
+        split.enabled = false;
+
+        let ConnectionGene {
+            innovation,
+            from,
+            to,
+            weight,
+            ..
+        } = *split;
+
+        let node = innovations.split(innovation);
+
+        self.insert_node(NodeGene {
+            id: node,
+            kind: NodeKind::Hidden,
+            bias: 0.0,
+            activation: innovations.hidden_activation,
+        });
+
+        for (from, to, weight) in [(from, node, 1.0), (node, to, weight)] {
+            self.insert_connection(ConnectionGene {
+                innovation: innovations.connection(from, to),
+                from,
+                to,
+                weight,
+                enabled: true,
+            });
+        }
+
+        true
+    }
+
+    /// Child of two genomes, where `fitter` is the parent with the higher
+    /// fitness: matching genes come from either parent at random, while
+    /// disjoint and excess genes come from `fitter` only.
+    pub fn crossover(rng: &mut dyn RngCore, fitter: &Self, other: &Self) -> Self {
+        let nodes = fitter
+            .nodes
+            .iter()
+            .map(|&node| match other.node(node.id) {
+                Some(&other) if rng.gen_bool(0.5) => other,
+                _ => node,
+            })
+            .collect();
+
+        let connections = fitter
+            .connections
+            .iter()
+            .map(
+                |&connection| match other.connection(connection.innovation) {
+                    Some(&matching) => {
+                        let mut child = if rng.gen_bool(0.5) {
+                            connection
+                        } else {
+                            matching
+                        };
+
+                        child.enabled = (connection.enabled && matching.enabled)
+                            || !rng.gen_bool(DISABLED_CHANCE);
+
+                        child
+                    }
+                    None => connection,
+                },
+            )
+            .collect();
+
+        Self { nodes, connections }
+    }
+
+    /// Compatibility distance: a weighted sum of the share of excess genes,
+    /// the share of disjoint genes and the average weight difference of
+    /// matching genes.
+    pub fn distance(&self, other: &Self, compatibility: &Compatibility) -> f32 {
+        let (mut a, mut b) = (self.connections.iter(), other.connections.iter());
+        let (mut next_a, mut next_b) = (a.next(), b.next());
+
+        let mut disjoint = 0;
+        let mut excess = 0;
+        let mut matching = 0;
+        let mut weight_difference = 0.0;
+
+        loop {
+            match (next_a, next_b) {
+                (Some(gene_a), Some(gene_b)) if gene_a.innovation == gene_b.innovation => {
+                    matching += 1;
+                    weight_difference += (gene_a.weight - gene_b.weight).abs();
+                    (next_a, next_b) = (a.next(), b.next());
+                }
+                (Some(gene_a), Some(gene_b)) => {
+                    disjoint += 1;
+
+                    if gene_a.innovation < gene_b.innovation {
+                        next_a = a.next();
+                    } else {
+                        next_b = b.next();
+                    }
+                }
+                (Some(_), None) => {
+                    excess += 1;
+                    next_a = a.next();
+                }
+                (None, Some(_)) => {
+                    excess += 1;
+                    next_b = b.next();
+                }
+                (None, None) => break,
+            }
+        }
+
+        let genes = self.connections.len().max(other.connections.len()).max(1) as f32;
+
+        let weight_difference = if matching > 0 {
+            weight_difference / matching as f32
+        } else {
+            0.0
+        };
+
+        compatibility.excess * excess as f32 / genes
+            + compatibility.disjoint * disjoint as f32 / genes
+            + compatibility.weight * weight_difference
+    }
+}
+
+/// Coefficients of `Genome::distance`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Compatibility {
+    pub excess: f32,
+    pub disjoint: f32,
+    pub weight: f32,
+}
+
+impl Default for Compatibility {
+    //Values from the original paper
+    fn default() -> Self {
+        Self {
+            excess: 1.0,
+            disjoint: 1.0,
+            weight: 0.4,
+        }
+    }
+}
+
+pub struct NeatMutation {
+    weights: GaussianMutation,
+    //Probabilities of the structural mutations, applied at most once per child
+    add_connection: f32,
+    add_node: f32,
+}
+
+impl NeatMutation {
+    pub fn new(weights: GaussianMutation, add_connection: f32, add_node: f32) -> Self {
+        assert!((0.0..=1.0).contains(&add_connection));
+        assert!((0.0..=1.0).contains(&add_node));
+
+        Self {
+            weights,
+            add_connection,
+            add_node,
+        }
+    }
+
+    pub fn mutate(
+        &self,
+        rng: &mut dyn RngCore,
+        genome: &mut Genome,
+        innovations: &mut Innovations,
+    ) {
+        genome.mutate_weights(rng, &self.weights);
+
+        if rng.gen_bool(self.add_connection as _) {
+            genome.add_connection(rng, innovations);
+        }
+
+        if rng.gen_bool(self.add_node as _) {
+            genome.add_node(rng, innovations);
+        }
+    }
+}
+
+pub trait NeatIndividual {
+    fn fitness(&self) -> f32;
+    fn genome(&self) -> &Genome;
+    fn create(genome: Genome) -> Self;
+}
+
+/// Evolves `Genome`s, protecting new structure by speciation: individuals
+/// only compete for offspring within their species, and every species gets
+/// offspring in proportion to its average fitness.
+pub struct Neat {
+    innovations: Innovations,
+    mutation: NeatMutation,
+    compatibility: Compatibility,
+    //Genomes closer than this to a species' representative belong to it
+    threshold: f32,
+    //One per species, picked from the previous generation
+    representatives: Vec<Genome>,
+}
+
+impl Neat {
+    pub fn new(inputs: usize, outputs: usize, mutation: NeatMutation) -> Self {
+        Self {
+            innovations: Innovations::new(inputs, outputs),
+            mutation,
+            compatibility: Compatibility::default(),
+            threshold: 3.0,
+            representatives: Vec::new(),
+        }
+    }
+
+    /// See `Innovations::with_activations`.
+    pub fn with_activations(mut self, hidden: nn::Activation, output: nn::Activation) -> Self {
+        self.innovations = self.innovations.with_activations(hidden, output);
+        self
+    }
+
+    pub fn with_compatibility(mut self, compatibility: Compatibility, threshold: f32) -> Self {
+        self.compatibility = compatibility;
+        self.threshold = threshold;
+        self
+    }
+
+    /// Genome for the initial population.
+    pub fn random_genome(&mut self, rng: &mut dyn RngCore) -> Genome {
+        Genome::minimal(rng, &mut self.innovations)
+    }
+
+    /// Number of species found by the last `evolve`.
+    pub fn species(&self) -> usize {
+        self.representatives.len()
+    }
+
+    pub fn evolve<T>(&mut self, rng: &mut dyn RngCore, population: &[T]) -> (Vec<T>, Statistics)
+    where
+        T: NeatIndividual,
+    {
+        assert!(!population.is_empty());
+
+        let species = self.speciate(rng, population);
+
+        //Explicit fitness sharing: dividing every fitness by the size of its
+        //species makes each species' share its average fitness
+        let shares: Vec<_> = species
+            .iter()
+            .map(|members| {
+                members
+                    .iter()
+                    .map(|&member| population[member].fitness().max(0.0))
+                    .sum::<f32>()
+                    / members.len() as f32
+            })
+            .collect();
+
+        let mut new_population = Vec::with_capacity(population.len());
+
+        for (mut members, offspring) in species.into_iter().zip(allot(&shares, population.len())) {
+            members.sort_by(|&a, &b| population[b].fitness().total_cmp(&population[a].fitness()));
+
+            let survivors = &members[..((members.len() as f32 * SURVIVAL).ceil() as usize).max(1)];
+            let mut offspring = offspring;
+
+            if offspring > 0 && members.len() >= ELITISM_MIN_SPECIES {
+                new_population.push(T::create(population[members[0]].genome().clone()));
+                offspring -= 1;
+            }
+
+            for _ in 0..offspring {
+                let a = *survivors.choose(rng).unwrap();
+                let b = *survivors.choose(rng).unwrap();
+
+                let (fitter, other) = if population[a].fitness() >= population[b].fitness() {
+                    (a, b)
+                } else {
+                    (b, a)
+                };
+
+                let mut child =
+                    Genome::crossover(rng, population[fitter].genome(), population[other].genome());
+
+                self.mutation.mutate(rng, &mut child, &mut self.innovations);
+
+                new_population.push(T::create(child));
+            }
+        }
+
+        let stats = Statistics::from_fitness(population.iter().map(T::fitness));
+
+        (new_population, stats)
+    }
+
+    /// Groups the population into species, returning the members' indices.
+    fn speciate<T>(&mut self, rng: &mut dyn RngCore, population: &[T]) -> Vec<Vec<usize>>
+    where
+        T: NeatIndividual,
+    {
+        let mut species = vec![Vec::new(); self.representatives.len()];
+
+        for (index, individual) in population.iter().enumerate() {
+            let genome = individual.genome();
+
+            let found = self.representatives.iter().position(|representative| {
+                representative.distance(genome, &self.compatibility) < self.threshold
+            });
+
+            match found {
+                Some(found) => species[found].push(index),
+                None => {
+                    self.representatives.push(genome.clone());
+                    species.push(vec![index]);
+                }
+            }
+        }
+
+        //Species that died out are gone, and the rest are represented by a
+        //random member of this generation from now on
+        species.retain(|members| !members.is_empty());
+
+        self.representatives = species
+            .iter()
+            .map(|members| population[*members.choose(rng).unwrap()].genome().clone())
+            .collect();
+
+        species
+    }
+}
+
+/// Splits `total` offspring in proportion to `shares`, handing the leftovers
+/// from rounding down to the largest remainders.
+fn allot(shares: &[f32], total: usize) -> Vec<usize> {
+    let sum: f32 = shares.iter().sum();
+
+    let exact: Vec<_> = if sum > 0.0 {
+        shares
+            .iter()
+            .map(|share| share / sum * total as f32)
+            .collect()
+    } else {
+        vec![total as f32 / shares.len() as f32; shares.len()]
+    };
+
+    let mut allotted: Vec<_> = exact.iter().map(|exact| *exact as usize).collect();
+    let mut by_remainder: Vec<_> = (0..shares.len()).collect();
+    by_remainder
+        .sort_by(|&a, &b| (exact[b] - exact[b].floor()).total_cmp(&(exact[a] - exact[a].floor())));
+
+    let floors: usize = allotted.iter().sum();
+
+    if floors <= total {
+        for &index in by_remainder.iter().cycle().take(total - floors) {
+            allotted[index] += 1;
+        }
+    } else {
+        //Rounding in `exact` can push the floors above `total`; the excess is
+        //taken back from the smallest remainders
+        let mut excess = floors - total;
+
+        for &index in by_remainder.iter().rev().cycle() {
+            if excess == 0 {
+                break;
+            }
+
+            if allotted[index] > 0 {
+                allotted[index] -= 1;
+                excess -= 1;
+            }
+        }
+    }
+
+    allotted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    struct TestGenome {
+        genome: Genome,
+    }
+
+    impl NeatIndividual for TestGenome {
+        //Rewards bigger networks, so that structure sticks around
+        fn fitness(&self) -> f32 {
+            self.genome.connections().len() as f32
+        }
+
+        fn genome(&self) -> &Genome {
+            &self.genome
+        }
+
+        fn create(genome: Genome) -> Self {
+            Self { genome }
+        }
+    }
+
+    #[test]
+    fn test_minimal() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut innovations = Innovations::new(3, 2);
+
+        let a = Genome::minimal(&mut rng, &mut innovations);
+        let b = Genome::minimal(&mut rng, &mut innovations);
+
+        assert_eq!(a.nodes().len(), 5);
+        assert_eq!(a.connections().len(), 6);
+
+        //Same connections, same innovation numbers
+        for (a, b) in a.connections().iter().zip(b.connections()) {
+            assert_eq!((a.innovation, a.from, a.to), (b.innovation, b.from, b.to));
+        }
+    }
+
+    #[test]
+    fn test_add_node() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut innovations = Innovations::new(1, 1);
+        let mut a = Genome::minimal(&mut rng, &mut innovations);
+        let mut b = a.clone();
+        let weight = a.connections()[0].weight;
+
+        assert!(a.add_node(&mut rng, &mut innovations));
+        assert!(b.add_node(&mut rng, &mut innovations));
+
+        assert_eq!(a.nodes()[2].id, 2);
+        assert_eq!(a.nodes()[2].kind, NodeKind::Hidden);
+
+        let connections: Vec<_> = a
+            .connections()
+            .iter()
+            .map(|c| (c.from, c.to, c.weight, c.enabled))
+            .collect();
+
+        assert_eq!(
+            connections,
+            [
+                (0, 1, weight, false),
+                (0, 2, 1.0, true),
+                (2, 1, weight, true)
+            ]
+        );
+
+        //The same split in another genome ends up with the same genes
+        assert_eq!(a.distance(&b, &Compatibility::default()), 0.0);
+
+        //Only enabled connections get split, so this adds yet another node
+        assert!(a.add_node(&mut rng, &mut innovations));
+        assert_eq!(a.nodes().len(), 4);
+    }
+
+    #[test]
+    fn test_add_connection_stays_acyclic() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut innovations = Innovations::new(2, 2);
+        let mut genome = Genome::minimal(&mut rng, &mut innovations);
+
+        for _ in 0..30 {
+            genome.add_node(&mut rng, &mut innovations);
+            genome.add_connection(&mut rng, &mut innovations);
+        }
+
+        for connection in genome.connections() {
+            assert!(!genome.reaches(connection.to, connection.from));
+        }
+
+        assert!(genome
+            .connections()
+            .windows(2)
+            .all(|w| w[0].innovation < w[1].innovation));
+    }
+
+    #[test]
+    fn test_distance() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut innovations = Innovations::new(2, 1);
+        let a = Genome::minimal(&mut rng, &mut innovations);
+        let mut b = a.clone();
+
+        assert_eq!(a.distance(&b, &Compatibility::default()), 0.0);
+
+        b.connections[0].weight += 1.0;
+        b.add_node(&mut rng, &mut innovations);
+
+        let compatibility = Compatibility {
+            excess: 1.0,
+            disjoint: 0.0,
+            weight: 0.0,
+        };
+
+        //Two excess genes out of four
+        assert_eq!(a.distance(&b, &compatibility), 0.5);
+        assert_eq!(b.distance(&a, &compatibility), 0.5);
+    }
+
+    #[test]
+    fn test_crossover_takes_structure_from_fitter() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut innovations = Innovations::new(2, 2);
+        let mut fitter = Genome::minimal(&mut rng, &mut innovations);
+        let mut other = fitter.clone();
+
+        fitter.add_node(&mut rng, &mut innovations);
+        other.add_connection(&mut rng, &mut innovations);
+        other.add_node(&mut rng, &mut innovations);
+
+        let child = Genome::crossover(&mut rng, &fitter, &other);
+
+        let innovations = |genome: &Genome| -> Vec<_> {
+            genome.connections().iter().map(|c| c.innovation).collect()
+        };
+
+        assert_eq!(innovations(&child), innovations(&fitter));
+        assert_eq!(child.nodes().len(), fitter.nodes().len());
+    }
+
+    #[test]
+    fn test_to_network() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut innovations = Innovations::new(1, 1);
+        let mut genome = Genome::minimal(&mut rng, &mut innovations);
+
+        genome.add_node(&mut rng, &mut innovations);
+
+        let weight = genome.connections()[0].weight;
+        let bias = genome.nodes()[1].bias;
+
+        //The split connection is disabled, so the input only goes through
+        //the new tanh node, with a weight of 1
+        approx::assert_relative_eq!(
+            genome.to_network().unwrap().propagate(vec![0.5]).as_slice(),
+            [bias + weight * 0.5f32.tanh()].as_slice()
+        );
+
+        //Looping the output back into the hidden node closes a cycle
+        let hidden = genome.nodes()[2].id;
+        let output = genome.nodes()[1].id;
+
+        genome.connections.push(ConnectionGene {
+            innovation: 99,
+            from: output,
+            to: hidden,
+            weight: 1.0,
+            enabled: true,
+        });
+
+        assert!(matches!(
+            genome.to_network(),
+            Err(nn::NetworkError::Cycle { .. })
+        ));
+    }
+
+    #[test]
+    fn test_evolved_networks_propagate() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut neat = Neat::new(
+            3,
+            2,
+            NeatMutation::new(GaussianMutation::new(0.5, 0.5), 0.5, 0.5),
+        )
+        .with_activations(nn::Activation::Sigmoid, nn::Activation::Sigmoid);
+
+        let mut population: Vec<_> = (0..20)
+            .map(|_| TestGenome::create(neat.random_genome(&mut rng)))
+            .collect();
+
+        for _ in 0..10 {
+            population = neat.evolve(&mut rng, &population).0;
+        }
+
+        for individual in &population {
+            let mut network = individual.genome().to_network().unwrap();
+
+            assert_eq!((network.inputs(), network.outputs()), (3, 2));
+
+            let outputs = network.propagate(vec![0.5, -1.0, 2.0]);
+            assert!(outputs.iter().all(|output| (0.0..=1.0).contains(output)));
+        }
+
+        assert!(population
+            .iter()
+            .any(|individual| individual.genome().nodes().len() > 5));
+    }
+
+    #[test]
+    fn test_allot() {
+        assert_eq!(allot(&[1.0, 1.0, 2.0], 10), [3, 2, 5]);
+        assert_eq!(allot(&[0.0, 0.0], 3), [2, 1]);
+
+        //The sum rounds down to 2^25, so the floors come out as 20000001
+        assert_eq!(
+            allot(&[33554432.0, 1.9], 20_000_000).iter().sum::<usize>(),
+            20_000_000
+        );
+    }
+
+    #[test]
+    fn test_evolve() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut neat = Neat::new(
+            3,
+            2,
+            NeatMutation::new(GaussianMutation::new(0.5, 0.5), 0.3, 0.2),
+        );
+
+        let mut population: Vec<_> = (0..20)
+            .map(|_| TestGenome::create(neat.random_genome(&mut rng)))
+            .collect();
+
+        let (_, first) = neat.evolve(&mut rng, &population);
+
+        for _ in 0..20 {
+            population = neat.evolve(&mut rng, &population).0;
+        }
+
+        let (_, last) = neat.evolve(&mut rng, &population);
+
+        assert_eq!(population.len(), 20);
+        assert!(neat.species() >= 1);
+        assert!(last.max_fitness() > first.max_fitness());
+    }
+}
@@ -0,0 +1,151 @@
+use crate::*;
+
+/// Non-linearity applied to the weighted sum of every neuron in a layer.
+///
+/// The default is `Relu`, which is what every layer used before activations
+/// became configurable.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Activation {
+    #[default]
+    Relu,
+    /// ReLU that lets negative values through, scaled by the given slope.
+    LeakyRelu(f32),
+    /// Exponential linear unit with the given alpha.
+    Elu(f32),
+    Sigmoid,
+    Tanh,
+    Identity,
+    Softsign,
+    /// Heaviside step: 1.0 for non-negative inputs, 0.0 otherwise.
+    Step,
+}
+
+impl Activation {
+    pub fn apply<T: Scalar>(&self, x: T) -> T {
+        match *self {
+            Self::Relu => x.max(T::ZERO),
+            Self::LeakyRelu(slope) => {
+                if x >= T::ZERO {
+                    x
+                } else {
+                    T::from_f32(slope) * x
+                }
+            }
+            Self::Elu(alpha) => {
+                if x >= T::ZERO {
+                    x
+                } else {
+                    T::from_f32(alpha) * (x.exp() - T::ONE)
+                }
+            }
+            Self::Sigmoid => T::ONE / (T::ONE + (-x).exp()),
+            Self::Tanh => x.tanh(),
+            Self::Identity => x,
+            Self::Softsign => x / (T::ONE + x.abs()),
+            Self::Step => {
+                if x >= T::ZERO {
+                    T::ONE
+                } else {
+                    T::ZERO
+                }
+            }
+        }
+    }
+
+    /// Whether the activation's parameter, if it has one, is finite.
+    pub(crate) fn is_valid(&self) -> bool {
+        match *self {
+            Self::LeakyRelu(param) | Self::Elu(param) => param.is_finite(),
+            _ => true,
+        }
+    }
+
+    /// Derivative of `apply` with respect to its input.
+    ///
+    /// The step function is treated as flat everywhere, so gradients never
+    /// flow through it.
+    pub fn derivative(&self, x: f32) -> f32 {
+        match *self {
+            Self::Relu => {
+                if x > 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Self::LeakyRelu(slope) => {
+                if x >= 0.0 {
+                    1.0
+                } else {
+                    slope
+                }
+            }
+            Self::Elu(alpha) => {
+                if x >= 0.0 {
+                    1.0
+                } else {
+                    alpha * x.exp()
+                }
+            }
+            Self::Sigmoid => {
+                let y = self.apply(x);
+                y * (1.0 - y)
+            }
+            Self::Tanh => 1.0 - x.tanh().powi(2),
+            Self::Identity => 1.0,
+            Self::Softsign => 1.0 / (1.0 + x.abs()).powi(2),
+            Self::Step => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_relu() {
+        assert_relative_eq!(Activation::Relu.apply(-2.0), 0.0);
+        assert_relative_eq!(Activation::Relu.apply(2.0), 2.0);
+    }
+
+    #[test]
+    fn test_negative_outputs() {
+        //Everything but ReLU and step should be able to go below zero
+        assert_relative_eq!(Activation::LeakyRelu(0.1).apply(-2.0f32), -0.2);
+        assert_relative_eq!(Activation::Elu(1.0).apply(-1.0), (-1.0f32).exp() - 1.0);
+        assert_relative_eq!(Activation::Tanh.apply(-1.0), (-1.0f32).tanh());
+        assert_relative_eq!(Activation::Identity.apply(-3.0), -3.0);
+        assert_relative_eq!(Activation::Softsign.apply(-3.0), -0.75);
+    }
+
+    #[test]
+    fn test_bounded() {
+        assert_relative_eq!(Activation::Sigmoid.apply(0.0), 0.5);
+        assert_relative_eq!(Activation::Step.apply(-0.1), 0.0);
+        assert_relative_eq!(Activation::Step.apply(0.0), 1.0);
+    }
+
+    #[test]
+    fn test_derivative() {
+        let activations = [
+            Activation::LeakyRelu(0.1),
+            Activation::Elu(1.0),
+            Activation::Sigmoid,
+            Activation::Tanh,
+            Activation::Identity,
+            Activation::Softsign,
+        ];
+
+        //Compare against central differences on both sides of zero
+        for activation in activations {
+            for x in [-1.5, -0.3, 0.4, 2.0] {
+                let h = 1e-3;
+                let numeric = (activation.apply(x + h) - activation.apply(x - h)) / (2.0 * h);
+
+                assert_relative_eq!(activation.derivative(x), numeric, epsilon = 1e-3);
+            }
+        }
+    }
+}
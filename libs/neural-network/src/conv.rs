@@ -0,0 +1,375 @@
+use crate::*;
+
+/// Shape of a 1-D convolutional layer (`LayerKind::Conv1d`).
+///
+/// The layer's inputs are a row of positions with `channels` values each,
+/// laid out position by position. Every one of the `filters` slides along
+/// the row looking at `kernel` neighbouring positions at a time, with the
+/// same weights everywhere, so it detects the same pattern wherever it is.
+/// Outputs are laid out the same way as inputs, with one channel per filter,
+/// so convolutions can be stacked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Convolution {
+    pub channels: usize,
+    pub filters: usize,
+    pub kernel: usize,
+    pub padding: Padding,
+}
+
+/// What a `Convolution` does at the ends of its row of inputs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Padding {
+    /// Kernels only go where they fit entirely, so there are `kernel - 1`
+    /// fewer output positions than input ones.
+    #[default]
+    Valid,
+    /// The row wraps around, like the cells of an `Eye` do, and there are as
+    /// many output positions as input ones. Kernels are centered on their
+    /// output position.
+    Circular,
+}
+
+impl Convolution {
+    /// Single-channel convolution with `Padding::Valid`.
+    pub fn new(filters: usize, kernel: usize) -> Self {
+        Self {
+            channels: 1,
+            filters,
+            kernel,
+            padding: Padding::default(),
+        }
+    }
+
+    pub fn with_channels(mut self, channels: usize) -> Self {
+        self.channels = channels;
+        self
+    }
+
+    pub fn with_padding(mut self, padding: Padding) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// How many neurons a layer with this shape has after `inputs` inputs,
+    /// or `None` if the inputs don't fit it.
+    pub fn neurons(&self, inputs: usize) -> Option<usize> {
+        if self.channels == 0 || self.filters == 0 || self.kernel == 0 {
+            return None;
+        }
+
+        if !inputs.is_multiple_of(self.channels) {
+            return None;
+        }
+
+        let positions = inputs / self.channels;
+
+        if self.kernel > positions {
+            return None;
+        }
+
+        self.filters.checked_mul(self.positions(positions))
+    }
+
+    //Output positions for the given number of input positions
+    fn positions(&self, positions: usize) -> usize {
+        match self.padding {
+            Padding::Valid => positions - self.kernel + 1,
+            Padding::Circular => positions,
+        }
+    }
+
+    fn row_len(&self) -> usize {
+        1 + self.kernel * self.channels
+    }
+
+    /// `None` if the count doesn't fit in a `usize`.
+    pub(crate) fn weights_len(&self) -> Option<usize> {
+        self.kernel
+            .checked_mul(self.channels)?
+            .checked_add(1)?
+            .checked_mul(self.filters)
+    }
+}
+
+impl LayerTopology {
+    /// Convolutional layer after a layer of `inputs` neurons.
+    ///
+    /// # Panics
+    ///
+    /// If the inputs don't fit the convolution, see `Convolution::neurons`.
+    pub fn conv1d(inputs: usize, convolution: Convolution) -> Self {
+        let neurons = convolution
+            .neurons(inputs)
+            .unwrap_or_else(|| panic!("{convolution:?} doesn't fit {inputs} inputs"));
+
+        Self::new(neurons).with_kind(LayerKind::Conv1d(convolution))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Conv1d<T = f32> {
+    inputs: usize,
+    convolution: Convolution,
+    /// Row-major `filters x (1 + kernel * channels)` matrix: every row holds
+    /// a filter's bias followed by its weights, position by position.
+    weights: Vec<T>,
+    activation: Activation,
+    //Inputs under the kernel, reused between positions
+    window: Vec<T>,
+}
+
+impl<T: Scalar> Conv1d<T> {
+    pub(crate) fn new(
+        inputs: usize,
+        convolution: Convolution,
+        weights: Vec<T>,
+        activation: Activation,
+    ) -> Self {
+        assert!(convolution.neurons(inputs).is_some());
+        assert_eq!(Some(weights.len()), convolution.weights_len());
+
+        Self {
+            inputs,
+            convolution,
+            weights,
+            activation,
+            window: Vec::new(),
+        }
+    }
+
+    pub(crate) fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    pub(crate) fn neurons(&self) -> usize {
+        self.convolution.filters * self.output_positions()
+    }
+
+    pub(crate) fn convolution(&self) -> Convolution {
+        self.convolution
+    }
+
+    pub(crate) fn activation(&self) -> Activation {
+        self.activation
+    }
+
+    pub(crate) fn weights(&self) -> &[T] {
+        &self.weights
+    }
+
+    pub(crate) fn weights_mut(&mut self) -> &mut [T] {
+        &mut self.weights
+    }
+
+    pub(crate) fn bias(&self, neuron: usize) -> T {
+        self.row(neuron % self.convolution.filters)[0]
+    }
+
+    /// Weight between `neuron` and `input` as if this was a dense layer: the
+    /// shared weight if the input is under the neuron's kernel, zero if not.
+    pub(crate) fn weight(&self, neuron: usize, input: usize) -> T {
+        let Convolution {
+            channels, filters, ..
+        } = self.convolution;
+
+        let (position, filter) = (neuron / filters, neuron % filters);
+        let (source, channel) = (input / channels, input % channels);
+
+        (0..self.convolution.kernel)
+            .filter(|&tap| self.source(position, tap) == source)
+            .map(|tap| self.row(filter)[1 + tap * channels + channel])
+            .sum()
+    }
+
+    pub(crate) fn propagate_into(&mut self, inputs: &[T], outputs: &mut Vec<T>) {
+        self.step(inputs, None, outputs);
+    }
+
+    pub(crate) fn trace_into(
+        &mut self,
+        inputs: &[T],
+        pre_activations: &mut Vec<T>,
+        outputs: &mut Vec<T>,
+    ) {
+        pre_activations.clear();
+        self.step(inputs, Some(pre_activations), outputs);
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[T],
+        mut pre_activations: Option<&mut Vec<T>>,
+        outputs: &mut Vec<T>,
+    ) {
+        assert_eq!(inputs.len() % self.inputs, 0);
+
+        let channels = self.convolution.channels;
+
+        outputs.clear();
+
+        for inputs in inputs.chunks_exact(self.inputs) {
+            for position in 0..self.output_positions() {
+                self.window.clear();
+
+                for tap in 0..self.convolution.kernel {
+                    let source = self.source(position, tap);
+                    self.window
+                        .extend_from_slice(&inputs[source * channels..][..channels]);
+                }
+
+                for filter in 0..self.convolution.filters {
+                    let sum = weighted_sum(self.row(filter), &self.window);
+
+                    if let Some(pre_activations) = pre_activations.as_deref_mut() {
+                        pre_activations.push(sum);
+                    }
+
+                    outputs.push(self.activation.apply(sum));
+                }
+            }
+        }
+    }
+
+    fn row(&self, filter: usize) -> &[T] {
+        let len = self.convolution.row_len();
+
+        &self.weights[filter * len..][..len]
+    }
+
+    fn input_positions(&self) -> usize {
+        self.inputs / self.convolution.channels
+    }
+
+    fn output_positions(&self) -> usize {
+        self.convolution.positions(self.input_positions())
+    }
+
+    //Input position that the kernel's `tap` reads for the output `position`
+    fn source(&self, position: usize, tap: usize) -> usize {
+        match self.convolution.padding {
+            Padding::Valid => position + tap,
+            Padding::Circular => {
+                let positions = self.input_positions();
+
+                (position + tap + positions - self.convolution.kernel / 2) % positions
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn layer(inputs: usize, convolution: Convolution, weights: Vec<f32>) -> Conv1d {
+        Conv1d::new(inputs, convolution, weights, Activation::Identity)
+    }
+
+    fn propagate(layer: &mut Conv1d, inputs: &[f32]) -> Vec<f32> {
+        let mut outputs = Vec::new();
+        layer.propagate_into(inputs, &mut outputs);
+        outputs
+    }
+
+    #[test]
+    fn test_valid() {
+        //Difference between each position and the next
+        let mut layer = layer(4, Convolution::new(1, 2), vec![0.0, -1.0, 1.0]);
+
+        assert_eq!(layer.neurons(), 3);
+        assert_relative_eq!(
+            propagate(&mut layer, &[1.0, 3.0, 2.0, 2.0]).as_slice(),
+            [2.0, -1.0, 0.0].as_slice()
+        );
+    }
+
+    #[test]
+    fn test_circular() {
+        //Left neighbour minus right neighbour, wrapping around
+        let convolution = Convolution::new(2, 3).with_padding(Padding::Circular);
+
+        #[rustfmt::skip]
+        let weights = vec![
+            0.0, 1.0, 0.0, -1.0,
+            0.5, 0.0, 1.0, 0.0,
+        ];
+
+        let mut layer = layer(4, convolution, weights);
+
+        assert_eq!(layer.neurons(), 8);
+
+        //Filters interleave: position 0 gives [x3 - x1, x0 + 0.5], ...
+        assert_relative_eq!(
+            propagate(&mut layer, &[1.0, 2.0, 3.0, 4.0]).as_slice(),
+            [2.0, 1.5, -2.0, 2.5, -2.0, 3.5, 2.0, 4.5].as_slice()
+        );
+    }
+
+    #[test]
+    fn test_channels() {
+        let convolution = Convolution::new(1, 2).with_channels(2);
+        //Adds up the first channel plus one, ignoring the second
+        let mut layer = layer(6, convolution, vec![1.0, 1.0, 0.0, 1.0, 0.0]);
+
+        assert_relative_eq!(
+            propagate(&mut layer, &[1.0, 10.0, 2.0, 20.0, 3.0, 30.0]).as_slice(),
+            [4.0, 6.0].as_slice()
+        );
+    }
+
+    #[test]
+    fn test_dense_view() {
+        let convolution = Convolution::new(1, 3).with_padding(Padding::Circular);
+        let layer = layer(5, convolution, vec![0.5, 1.0, 2.0, 3.0]);
+
+        //Position 0's kernel covers inputs 4, 0 and 1
+        let weights: Vec<_> = (0..5).map(|input| layer.weight(0, input)).collect();
+
+        assert_eq!(weights, [2.0, 3.0, 0.0, 0.0, 1.0]);
+        assert_eq!(layer.bias(3), 0.5);
+    }
+
+    #[test]
+    fn test_in_network() {
+        let convolution = Convolution::new(2, 3).with_padding(Padding::Circular);
+
+        let topology = [
+            LayerTopology::new(6),
+            LayerTopology::conv1d(6, convolution),
+            LayerTopology::new(2).with_activation(Activation::Tanh),
+        ];
+
+        let mut network = Network::random(&topology);
+        assert_eq!(network.topology(), topology);
+        assert_eq!(network.weights().len(), 2 * 4 + 2 * 13);
+
+        let mut copy = Network::from_weights(&topology, network.weights());
+        let inputs = vec![0.1, 0.9, 0.0, 0.0, 0.3, 0.2];
+
+        assert_eq!(copy.propagate(inputs.clone()), network.propagate(inputs));
+
+        let mismatched = [
+            LayerTopology::new(6),
+            LayerTopology::new(12).with_kind(LayerKind::Conv1d(Convolution::new(2, 3))),
+        ];
+
+        assert_eq!(
+            Network::try_random(&mismatched).unwrap_err(),
+            NetworkError::InvalidConvolution { layer: 1 }
+        );
+    }
+
+    #[test]
+    fn test_neurons() {
+        assert_eq!(Convolution::new(2, 3).neurons(5), Some(6));
+        assert_eq!(
+            Convolution::new(2, 3)
+                .with_padding(Padding::Circular)
+                .neurons(5),
+            Some(10)
+        );
+        assert_eq!(Convolution::new(1, 6).neurons(5), None);
+        assert_eq!(Convolution::new(1, 2).with_channels(2).neurons(5), None);
+    }
+}
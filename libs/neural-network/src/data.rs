@@ -0,0 +1,634 @@
+use crate::*;
+use core::fmt;
+#[cfg(feature = "rand")]
+use rand::seq::SliceRandom;
+#[cfg(feature = "std")]
+use std::{fs, io, path::Path};
+
+/// Examples for supervised learning: input vectors paired with the outputs
+/// a network should give for them.
+///
+/// Inputs and targets are kept apart, the way `Network::train_batch` takes
+/// them. Every input has the same length, and so does every target.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dataset {
+    inputs: Vec<Vec<f32>>,
+    targets: Vec<Vec<f32>>,
+}
+
+/// A `Dataset` shuffled and cut into three, see `Dataset::split`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Split {
+    pub train: Dataset,
+    pub validation: Dataset,
+    pub test: Dataset,
+}
+
+/// Consecutive examples of a `Dataset`, see `Dataset::batches`.
+#[derive(Clone, Copy, Debug)]
+pub struct Batch<'a> {
+    pub inputs: &'a [Vec<f32>],
+    pub targets: &'a [Vec<f32>],
+}
+
+#[derive(Debug)]
+pub enum DataError {
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    /// A JSON line isn't an object with `inputs` and `targets` arrays.
+    #[cfg(feature = "std")]
+    Json { line: usize, err: serde_json::Error },
+    /// A CSV field isn't a number.
+    InvalidNumber { line: usize, column: usize },
+    /// A CSV line has a different number of values than the lines before it.
+    InconsistentLength {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A JSON line has a different number of inputs than the lines before
+    /// it.
+    InconsistentInputs {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A JSON line has a different number of targets than the lines before
+    /// it.
+    InconsistentTargets {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(err) => write!(f, "couldn't access the dataset file: {err}"),
+            #[cfg(feature = "std")]
+            Self::Json { line, err } => write!(f, "couldn't parse line {line}: {err}"),
+            Self::InvalidNumber { line, column } => {
+                write!(f, "value in line {line}, column {column} isn't a number")
+            }
+            Self::InconsistentLength {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {line} has {found} values, but the lines before it have {expected}"
+            ),
+            Self::InconsistentInputs {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {line} has {found} inputs, but the lines before it have {expected}"
+            ),
+            Self::InconsistentTargets {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {line} has {found} targets, but the lines before it have {expected}"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for DataError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(err) => Some(err),
+            #[cfg(feature = "std")]
+            Self::Json { err, .. } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for DataError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+//One line of a JSON-lines dataset
+#[cfg(feature = "std")]
+#[derive(Deserialize)]
+struct Example {
+    inputs: Vec<f32>,
+    targets: Vec<f32>,
+}
+
+impl Dataset {
+    pub fn new(inputs: Vec<Vec<f32>>, targets: Vec<Vec<f32>>) -> Self {
+        assert_eq!(inputs.len(), targets.len());
+
+        let mut dataset = Self::default();
+
+        for (inputs, targets) in inputs.into_iter().zip(targets) {
+            dataset.push(inputs, targets);
+        }
+
+        dataset
+    }
+
+    pub fn push(&mut self, inputs: Vec<f32>, targets: Vec<f32>) {
+        if let (Some(first_inputs), Some(first_targets)) =
+            (self.inputs.first(), self.targets.first())
+        {
+            assert_eq!(inputs.len(), first_inputs.len(), "inputs differ in length");
+            assert_eq!(
+                targets.len(),
+                first_targets.len(),
+                "targets differ in length"
+            );
+        }
+
+        self.inputs.push(inputs);
+        self.targets.push(targets);
+    }
+
+    /// Parses comma-separated numbers, one example per line: the first
+    /// `inputs` columns are the example's inputs, the rest its targets.
+    ///
+    /// A first line that isn't all numbers is taken for a header and
+    /// skipped, and so are empty lines. Class labels have to be turned into
+    /// numbers (e.g. one-hot) beforehand.
+    pub fn from_csv(csv: &str, inputs: usize) -> Result<Self, DataError> {
+        let mut dataset = Self::default();
+        let mut columns = None;
+
+        for (index, text) in csv.lines().enumerate() {
+            let line = index + 1;
+
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            let values: Result<Vec<f32>, _> = text
+                .split(',')
+                .enumerate()
+                .map(|(column, value)| {
+                    value.trim().parse().map_err(|_| DataError::InvalidNumber {
+                        line,
+                        column: column + 1,
+                    })
+                })
+                .collect();
+
+            let values = match values {
+                Err(_) if index == 0 => continue,
+                values => values?,
+            };
+
+            let expected = *columns.get_or_insert(values.len().max(inputs));
+
+            if values.len() != expected {
+                return Err(DataError::InconsistentLength {
+                    line,
+                    expected,
+                    found: values.len(),
+                });
+            }
+
+            let (inputs, targets) = values.split_at(inputs);
+            dataset.push(inputs.to_vec(), targets.to_vec());
+        }
+
+        Ok(dataset)
+    }
+
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    pub fn inputs(&self) -> &[Vec<f32>] {
+        &self.inputs
+    }
+
+    pub fn targets(&self) -> &[Vec<f32>] {
+        &self.targets
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&[f32], &[f32])> + '_ {
+        self.inputs
+            .iter()
+            .zip(&self.targets)
+            .map(|(inputs, targets)| (inputs.as_slice(), targets.as_slice()))
+    }
+
+    /// Splits the examples into batches of `size`, in order; the last batch
+    /// holds whatever is left over.
+    pub fn batches(&self, size: usize) -> impl Iterator<Item = Batch<'_>> + '_ {
+        assert!(size > 0, "batches have to hold at least one example");
+
+        self.inputs
+            .chunks(size)
+            .zip(self.targets.chunks(size))
+            .map(|(inputs, targets)| Batch { inputs, targets })
+    }
+}
+
+#[cfg(feature = "std")]
+impl Dataset {
+    /// Parses JSON lines, one `{"inputs": [...], "targets": [...]}` object
+    /// per example. Empty lines are skipped.
+    pub fn from_jsonl(jsonl: &str) -> Result<Self, DataError> {
+        let mut dataset = Self::default();
+        let mut lengths = None;
+
+        for (index, text) in jsonl.lines().enumerate() {
+            let line = index + 1;
+
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            let example: Example =
+                serde_json::from_str(text).map_err(|err| DataError::Json { line, err })?;
+
+            let found = (example.inputs.len(), example.targets.len());
+            let expected = *lengths.get_or_insert(found);
+
+            if found.0 != expected.0 {
+                return Err(DataError::InconsistentInputs {
+                    line,
+                    expected: expected.0,
+                    found: found.0,
+                });
+            }
+
+            if found.1 != expected.1 {
+                return Err(DataError::InconsistentTargets {
+                    line,
+                    expected: expected.1,
+                    found: found.1,
+                });
+            }
+
+            dataset.push(example.inputs, example.targets);
+        }
+
+        Ok(dataset)
+    }
+
+    /// Reads a CSV file, see `from_csv`.
+    pub fn load_csv(path: impl AsRef<Path>, inputs: usize) -> Result<Self, DataError> {
+        Self::from_csv(&fs::read_to_string(path)?, inputs)
+    }
+
+    /// Reads a JSON-lines file, see `from_jsonl`.
+    pub fn load_jsonl(path: impl AsRef<Path>) -> Result<Self, DataError> {
+        Self::from_jsonl(&fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(feature = "rand")]
+impl Dataset {
+    /// Shuffles the examples, e.g. between epochs.
+    pub fn shuffle(&mut self, rng: &mut dyn RngCore) {
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        order.shuffle(rng);
+
+        *self = self.select(&order);
+    }
+
+    /// Shuffles the examples and cuts them into a training, a validation and
+    /// a test set, the last two getting the given fractions of the examples
+    /// (rounded down).
+    ///
+    /// The same `rng` state always gives the same split.
+    pub fn split(&self, rng: &mut dyn RngCore, validation: f32, test: f32) -> Split {
+        assert!(
+            validation >= 0.0 && test >= 0.0 && validation + test <= 1.0,
+            "validation and test fractions have to add up to at most 1"
+        );
+
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        order.shuffle(rng);
+
+        let validation = (self.len() as f32 * validation) as usize;
+        let test = (self.len() as f32 * test) as usize;
+
+        let (test_order, rest) = order.split_at(test);
+        let (validation_order, train_order) = rest.split_at(validation);
+
+        Split {
+            train: self.select(train_order),
+            validation: self.select(validation_order),
+            test: self.select(test_order),
+        }
+    }
+
+    fn select(&self, order: &[usize]) -> Self {
+        Self {
+            inputs: order.iter().map(|&i| self.inputs[i].clone()).collect(),
+            targets: order.iter().map(|&i| self.targets[i].clone()).collect(),
+        }
+    }
+}
+
+/// How often each class was predicted for each actual class, as computed by
+/// `Network::confusion_matrix`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfusionMatrix {
+    classes: usize,
+    //Row-major `classes x classes` matrix, actual classes being rows
+    counts: Vec<usize>,
+}
+
+impl ConfusionMatrix {
+    pub fn new(classes: usize) -> Self {
+        Self {
+            classes,
+            counts: vec![0; classes * classes],
+        }
+    }
+
+    pub fn classes(&self) -> usize {
+        self.classes
+    }
+
+    /// How many examples of class `actual` were predicted to be `predicted`.
+    pub fn get(&self, actual: usize, predicted: usize) -> usize {
+        self.counts[actual * self.classes + predicted]
+    }
+
+    pub fn record(&mut self, actual: usize, predicted: usize) {
+        self.counts[actual * self.classes + predicted] += 1;
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Fraction of the examples whose class was predicted correctly.
+    pub fn accuracy(&self) -> f32 {
+        let correct: usize = (0..self.classes).map(|class| self.get(class, class)).sum();
+
+        correct as f32 / self.total() as f32
+    }
+}
+
+impl Network {
+    /// Mean squared error of the network's outputs over `dataset`, averaged
+    /// over every output of every example.
+    ///
+    /// Every example is propagated from a fresh state, see `reset_state`.
+    pub fn mean_squared_error(&mut self, dataset: &Dataset) -> f32 {
+        assert!(!dataset.is_empty());
+
+        let mut scratch = Scratch::new();
+        let mut total = 0.0;
+
+        for (inputs, targets) in dataset.iter() {
+            self.reset_state();
+
+            let outputs = self.propagate_into(inputs, &mut scratch);
+            total += Loss::MeanSquaredError.loss(outputs, targets);
+        }
+
+        total / dataset.len() as f32
+    }
+
+    /// Treats the network as a classifier and counts its predictions over
+    /// `dataset`.
+    ///
+    /// With several outputs, the class is the index of the largest one, in
+    /// both outputs and (one-hot) targets. With a single output there are
+    /// two classes: 1 for values of at least 0.5, 0 for anything else.
+    pub fn confusion_matrix(&mut self, dataset: &Dataset) -> ConfusionMatrix {
+        assert!(!dataset.is_empty());
+
+        let classes = dataset.targets[0].len().max(2);
+        let mut matrix = ConfusionMatrix::new(classes);
+        let mut scratch = Scratch::new();
+
+        for (inputs, targets) in dataset.iter() {
+            self.reset_state();
+
+            let outputs = self.propagate_into(inputs, &mut scratch);
+            matrix.record(class(targets), class(outputs));
+        }
+
+        matrix
+    }
+
+    /// Fraction of `dataset` classified correctly, see `confusion_matrix`.
+    pub fn accuracy(&mut self, dataset: &Dataset) -> f32 {
+        self.confusion_matrix(dataset).accuracy()
+    }
+}
+
+fn class(values: &[f32]) -> usize {
+    match values {
+        [value] => (*value >= 0.5) as usize,
+        values => values
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+            .unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn xor() -> Dataset {
+        Dataset::from_csv("a,b,xor\n0,0,0\n0,1,1\n1,0,1\n1,1,0\n", 2).unwrap()
+    }
+
+    //Solves XOR with two ReLUs: (a + b) - 2 * relu(a + b - 1)
+    fn xor_network() -> Network {
+        let topology = [
+            LayerTopology::new(2),
+            LayerTopology::new(2),
+            LayerTopology::new(1).with_activation(Activation::Identity),
+        ];
+
+        #[rustfmt::skip]
+        let weights = [
+            0.0, 1.0, 1.0,
+            -1.0, 1.0, 1.0,
+            0.0, 1.0, -2.0,
+        ];
+
+        Network::from_weights(&topology, weights)
+    }
+
+    #[test]
+    fn test_csv() {
+        let dataset = xor();
+
+        assert_eq!(dataset.len(), 4);
+        assert_eq!(dataset.inputs()[1], [0.0, 1.0]);
+        assert_eq!(dataset.targets()[1], [1.0]);
+
+        //No header, blank lines and one-hot targets
+        let dataset = Dataset::from_csv("5.1, 3.5, 1, 0\n\n6.2, 2.9, 0, 1\n", 2).unwrap();
+
+        assert_eq!(dataset.len(), 2);
+        assert_eq!(dataset.targets()[1], [0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_csv_errors() {
+        assert!(matches!(
+            Dataset::from_csv("1,2,3\n4,x,6\n", 2).unwrap_err(),
+            DataError::InvalidNumber { line: 2, column: 2 }
+        ));
+
+        assert!(matches!(
+            Dataset::from_csv("1,2,3\n4,5\n", 2).unwrap_err(),
+            DataError::InconsistentLength {
+                line: 2,
+                expected: 3,
+                found: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn test_jsonl() {
+        let jsonl = r#"{"inputs": [0, 1], "targets": [1]}
+
+{"inputs": [1, 1], "targets": [0]}
+"#;
+
+        let dataset = Dataset::from_jsonl(jsonl).unwrap();
+
+        assert_eq!(dataset.inputs(), [vec![0.0, 1.0], vec![1.0, 1.0]]);
+        assert_eq!(dataset.targets(), [vec![1.0], vec![0.0]]);
+
+        assert!(matches!(
+            Dataset::from_jsonl("{\"inputs\": [0]}").unwrap_err(),
+            DataError::Json { line: 1, .. }
+        ));
+
+        assert!(matches!(
+            Dataset::from_jsonl(
+                "{\"inputs\": [0], \"targets\": [1]}\n{\"inputs\": [0, 1], \"targets\": [1]}"
+            )
+            .unwrap_err(),
+            DataError::InconsistentInputs {
+                line: 2,
+                expected: 1,
+                found: 2
+            }
+        ));
+
+        //One input fewer and one target more add up to the same length
+        assert!(matches!(
+            Dataset::from_jsonl(
+                "{\"inputs\": [0, 1], \"targets\": [1]}\n{\"inputs\": [0], \"targets\": [1, 0]}"
+            )
+            .unwrap_err(),
+            DataError::InconsistentInputs {
+                line: 2,
+                expected: 2,
+                found: 1
+            }
+        ));
+
+        assert!(matches!(
+            Dataset::from_jsonl(
+                "{\"inputs\": [0], \"targets\": [1]}\n{\"inputs\": [1], \"targets\": [0, 1]}"
+            )
+            .unwrap_err(),
+            DataError::InconsistentTargets {
+                line: 2,
+                expected: 1,
+                found: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn test_split() {
+        let dataset = Dataset::new(
+            (0..10).map(|n| vec![n as f32]).collect(),
+            (0..10).map(|n| vec![n as f32 * 2.0]).collect(),
+        );
+
+        let split = dataset.split(&mut ChaCha8Rng::from_seed(Default::default()), 0.2, 0.3);
+
+        assert_eq!(
+            (split.train.len(), split.validation.len(), split.test.len()),
+            (5, 2, 3)
+        );
+
+        //Every example ends up in exactly one set, still paired up
+        let mut seen: Vec<_> = [&split.train, &split.validation, &split.test]
+            .into_iter()
+            .flat_map(|set| set.iter())
+            .map(|(inputs, targets)| {
+                assert_eq!(targets[0], inputs[0] * 2.0);
+                inputs[0] as usize
+            })
+            .collect();
+
+        seen.sort();
+        assert_eq!(seen, (0..10).collect::<Vec<_>>());
+
+        //And the same seed gives the same split
+        let again = dataset.split(&mut ChaCha8Rng::from_seed(Default::default()), 0.2, 0.3);
+        assert_eq!(again, split);
+    }
+
+    #[test]
+    fn test_batches() {
+        let dataset = xor();
+        let batches: Vec<_> = dataset.batches(3).collect();
+
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].inputs.len(), 3);
+        assert_eq!(batches[1].targets, [vec![0.0]]);
+    }
+
+    #[test]
+    fn test_metrics() {
+        let dataset = xor();
+        let mut network = xor_network();
+
+        assert_relative_eq!(network.mean_squared_error(&dataset), 0.0);
+        assert_eq!(network.accuracy(&dataset), 1.0);
+
+        //Dropping the second neuron makes it compute a OR b, plus one for 1, 1
+        let mut weights = network.weights();
+        weights[8] = 0.0;
+        let mut network = Network::from_weights(&network.topology(), weights);
+
+        let matrix = network.confusion_matrix(&dataset);
+
+        assert_eq!(matrix.classes(), 2);
+        assert_eq!(
+            [
+                matrix.get(0, 0),
+                matrix.get(0, 1),
+                matrix.get(1, 0),
+                matrix.get(1, 1)
+            ],
+            [1, 1, 0, 2]
+        );
+        assert_eq!(network.accuracy(&dataset), 0.75);
+        //Errors of 0 and 2 for 1, 1
+        assert_relative_eq!(network.mean_squared_error(&dataset), 1.0);
+    }
+}
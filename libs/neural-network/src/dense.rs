@@ -0,0 +1,228 @@
+use crate::*;
+
+#[derive(Clone, Debug)]
+pub(crate) struct Dense<T = f32> {
+    pub(crate) inputs: usize,
+    /// Row-major `neurons x (inputs + 1)` matrix: every row holds a neuron's
+    /// bias followed by its weights, which is also the layout that
+    /// `Network::weights()` exposes.
+    pub(crate) weights: Vec<T>,
+    pub(crate) activation: Activation,
+}
+
+impl<T: Scalar> Dense<T> {
+    #[cfg(test)]
+    pub(crate) fn new(inputs: usize, weights: Vec<T>, activation: Activation) -> Self {
+        assert_eq!(weights.len() % (inputs + 1), 0);
+
+        Self {
+            inputs,
+            weights,
+            activation,
+        }
+    }
+
+    pub(crate) fn from_weights(
+        input_size: usize,
+        output_size: usize,
+        activation: Activation,
+        weights: &mut dyn Iterator<Item = T>,
+    ) -> Self {
+        let weights: Vec<_> = weights.take(output_size * (input_size + 1)).collect();
+        assert_eq!(
+            weights.len(),
+            output_size * (input_size + 1),
+            "got not enough weights"
+        );
+
+        Self {
+            inputs: input_size,
+            weights,
+            activation,
+        }
+    }
+
+    pub(crate) fn neurons(&self) -> usize {
+        self.weights.len() / (self.inputs + 1)
+    }
+
+    /// The given neuron's bias followed by its weights.
+    pub(crate) fn row(&self, neuron: usize) -> &[T] {
+        &self.weights[neuron * (self.inputs + 1)..][..self.inputs + 1]
+    }
+
+    //The neuron's output before the activation is applied
+    pub(crate) fn weighted_sum(&self, neuron: usize, inputs: &[T]) -> T {
+        assert_eq!(inputs.len(), self.inputs);
+
+        weighted_sum(self.row(neuron), inputs)
+    }
+
+    /// Propagates a batch of inputs laid out one after another, replacing
+    /// `outputs` with the batch's outputs laid out the same way.
+    pub(crate) fn propagate_into(&self, inputs: &[T], outputs: &mut Vec<T>) {
+        self.step(inputs, None, outputs);
+    }
+
+    /// Like `propagate_into`, but also replaces `pre_activations` with the
+    /// weighted sums.
+    pub(crate) fn trace_into(
+        &self,
+        inputs: &[T],
+        pre_activations: &mut Vec<T>,
+        outputs: &mut Vec<T>,
+    ) {
+        pre_activations.clear();
+        self.step(inputs, Some(pre_activations), outputs);
+    }
+
+    //The whole batch is one matrix product: every row of weights is applied
+    //to every input vector before moving on to the next row, so that it's
+    //read from memory once per batch rather than once per input vector
+    fn step(&self, inputs: &[T], pre_activations: Option<&mut Vec<T>>, outputs: &mut Vec<T>) {
+        assert_eq!(inputs.len() % self.inputs, 0);
+
+        let neurons = self.neurons();
+
+        outputs.clear();
+        outputs.resize(inputs.len() / self.inputs * neurons, T::ZERO);
+
+        for neuron in 0..neurons {
+            let row = self.row(neuron);
+
+            for (sums, inputs) in outputs
+                .chunks_exact_mut(neurons)
+                .zip(inputs.chunks_exact(self.inputs))
+            {
+                sums[neuron] = weighted_sum(row, inputs);
+            }
+        }
+
+        if let Some(pre_activations) = pre_activations {
+            pre_activations.extend_from_slice(outputs);
+        }
+
+        for output in outputs.iter_mut() {
+            *output = self.activation.apply(*output);
+        }
+    }
+}
+
+//Random weights are always drawn as f32, see `Network::cast`
+impl Dense {
+    #[cfg(feature = "rand")]
+    pub(crate) fn random(
+        rng: &mut dyn RngCore,
+        input_size: usize,
+        output_size: usize,
+        activation: Activation,
+        init: Init,
+    ) -> Self {
+        let weights = init.weights(rng, input_size, output_size);
+
+        Self {
+            inputs: input_size,
+            weights,
+            activation,
+        }
+    }
+}
+
+/// Bias plus dot product, for a row laid out as the bias followed by one
+/// weight per input.
+pub(crate) fn weighted_sum<T: Scalar>(row: &[T], inputs: &[T]) -> T {
+    let output = inputs
+        .iter()
+        .zip(&row[1..])
+        .map(|(&input, &weight)| input * weight)
+        .sum::<T>();
+
+    output + row[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn propagate(layer: &Dense, inputs: &[f32]) -> Vec<f32> {
+        let mut outputs = Vec::new();
+        layer.propagate_into(inputs, &mut outputs);
+        outputs
+    }
+
+    #[test]
+    fn test_random() {
+        use approx::assert_relative_eq;
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha8Rng;
+        // Because we always use the same seed, our `rng` in here will
+        // always return the same set of values
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let layer = Dense::random(&mut rng, 4, 1, Activation::Relu, Init::default());
+
+        assert_relative_eq!(
+            layer.weights.as_slice(),
+            [-0.6255188, 0.67383957, 0.8181262, 0.26284897, 0.5238807].as_ref()
+        );
+    }
+
+    #[test]
+    fn test_propagate_neuron() {
+        let layer = Dense::new(2, vec![0.5, -0.3, 0.8], Activation::Relu);
+
+        //Ensure that our .max() works as intended:
+        approx::assert_relative_eq!(propagate(&layer, &[-10.0, -10.0])[0], 0.0);
+
+        // 0.5 and 1.0 test it
+        approx::assert_relative_eq!(
+            propagate(&layer, &[0.5, 1.0])[0],
+            (-0.3 * 0.5) + (0.8 * 1.0) + 0.5
+        );
+    }
+
+    #[test]
+    fn test_propagate_layer_with_activation() {
+        #[rustfmt::skip]
+        let layer = Dense::new(
+            2,
+            vec![
+                -0.5, -0.3, 1.0,
+                0.2, -0.3, 0.8,
+            ],
+            Activation::Identity,
+        );
+
+        //Identity keeps the negative output that ReLU would have cut off
+        approx::assert_relative_eq!(
+            propagate(&layer, &[-0.3, 0.5]).as_slice(),
+            [0.09, 0.69].as_ref()
+        );
+    }
+
+    #[test]
+    fn test_propagate_layer() {
+        #[rustfmt::skip]
+        let layer = Dense::new(
+            2,
+            vec![
+                0.5, -0.3, 1.0,
+                0.2, -0.3, 0.8,
+                0.1, 0.3, 0.2,
+            ],
+            Activation::Relu,
+        );
+
+        //Ensure that the .max() works (ReLu)
+        assert_eq!(propagate(&layer, &[-0.3, 0.5]), vec![1.09, 0.69, 0.11]);
+    }
+
+    #[test]
+    fn test_propagate_batch() {
+        let layer = Dense::new(2, vec![0.5, -0.3, 1.0], Activation::Relu);
+
+        approx::assert_relative_eq!(
+            propagate(&layer, &[-0.3, 0.5, 1.0, -1.0]).as_slice(),
+            [1.09, 0.0].as_ref()
+        );
+    }
+}
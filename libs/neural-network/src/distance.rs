@@ -0,0 +1,200 @@
+use crate::*;
+
+impl Network {
+    /// Euclidean (L2) distance between the two networks' `weights()`.
+    ///
+    /// Both networks need the same topology; for two of them bred by a
+    /// genetic algorithm, zero means they're clones.
+    pub fn distance(&self, other: &Self) -> f32 {
+        self.assert_same_topology(other);
+
+        self.layers
+            .iter()
+            .zip(&other.layers)
+            .map(|(a, b)| squared_distance(a.weights(), b.weights()))
+            .sum::<f32>()
+            .sqrt()
+    }
+
+    /// Cosine similarity between the weights of every layer (not counting
+    /// the input one), from -1 for opposite weights to 1 for weights pointing
+    /// the same way, whatever their magnitude.
+    ///
+    /// Layers without weights, like dropout ones, count as identical, and so
+    /// do layers with all their weights at zero in both networks; if only one
+    /// of them is all zeros, the similarity is zero.
+    pub fn cosine_similarity(&self, other: &Self) -> Vec<f32> {
+        self.assert_same_topology(other);
+
+        self.layers
+            .iter()
+            .zip(&other.layers)
+            .map(|(a, b)| cosine_similarity(a.weights(), b.weights()))
+            .collect()
+    }
+
+    /// How differently the two networks behave: the Euclidean distance
+    /// between their outputs, averaged over `probes`.
+    ///
+    /// Unlike `distance`, this only needs the networks to have as many
+    /// inputs and outputs as each other, and ignores differences that don't
+    /// change what they compute - e.g. two hidden neurons swapping places.
+    /// Every probe is propagated from a fresh state, see `reset_state`.
+    pub fn behavioral_distance(&mut self, other: &mut Self, probes: &[Vec<f32>]) -> f32 {
+        assert!(!probes.is_empty());
+
+        let mut scratch = Scratch::new();
+        let mut other_scratch = Scratch::new();
+        let mut total = 0.0;
+
+        for probe in probes {
+            self.reset_state();
+            other.reset_state();
+
+            let outputs = self.propagate_into(probe, &mut scratch);
+            let other_outputs = other.propagate_into(probe, &mut other_scratch);
+
+            assert_eq!(
+                outputs.len(),
+                other_outputs.len(),
+                "networks have different numbers of outputs"
+            );
+
+            total += squared_distance(outputs, other_outputs).sqrt();
+        }
+
+        total / probes.len() as f32
+    }
+
+    /// Mean `distance` between every pair of `networks`, or zero when
+    /// there's less than two of them.
+    ///
+    /// Meant for keeping an eye on a population: once it drops close to
+    /// zero, evolution has converged on (near) clones of a single network.
+    pub fn diversity<'a>(networks: impl IntoIterator<Item = &'a Self>) -> f32 {
+        let networks: Vec<_> = networks.into_iter().collect();
+        let mut total = 0.0;
+        let mut pairs = 0;
+
+        for (idx, network) in networks.iter().enumerate() {
+            for other in &networks[idx + 1..] {
+                total += network.distance(other);
+                pairs += 1;
+            }
+        }
+
+        if pairs == 0 {
+            0.0
+        } else {
+            total / pairs as f32
+        }
+    }
+
+    fn assert_same_topology(&self, other: &Self) {
+        assert!(
+            self.topology() == other.topology(),
+            "networks have different topologies"
+        );
+    }
+}
+
+fn squared_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let norm_a = a.iter().map(|a| a * a).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|b| b * b).sum::<f32>().sqrt();
+
+    match (norm_a == 0.0, norm_b == 0.0) {
+        (true, true) => 1.0,
+        (true, false) | (false, true) => 0.0,
+        (false, false) => dot / (norm_a * norm_b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn topology() -> [LayerTopology; 3] {
+        [
+            LayerTopology::new(1),
+            LayerTopology::new(1).with_activation(Activation::Identity),
+            LayerTopology::new(1)
+                .with_activation(Activation::Identity)
+                .with_kind(LayerKind::Dropout(0.5)),
+        ]
+    }
+
+    #[test]
+    fn test_distance() {
+        let a = Network::from_weights(&topology(), vec![0.0, 1.0]);
+        let b = Network::from_weights(&topology(), vec![3.0, 5.0]);
+        let c = Network::from_weights(&topology(), vec![0.0, -2.0]);
+
+        assert_eq!(a.distance(&a), 0.0);
+        assert_relative_eq!(a.distance(&b), 5.0);
+        assert_relative_eq!(b.distance(&a), 5.0);
+
+        assert_relative_eq!(a.cosine_similarity(&a).as_slice(), [1.0, 1.0].as_slice());
+        assert_relative_eq!(a.cosine_similarity(&c).as_slice(), [-1.0, 1.0].as_slice());
+        assert_relative_eq!(
+            a.cosine_similarity(&b).as_slice(),
+            [5.0 / 34f32.sqrt(), 1.0].as_slice()
+        );
+
+        //Pairs are 5, 3 and √58 apart
+        assert_relative_eq!(Network::diversity([&a, &b, &c]), (8.0 + 58f32.sqrt()) / 3.0);
+        assert_eq!(Network::diversity([&a]), 0.0);
+    }
+
+    #[test]
+    fn test_distance_ignores_running_statistics() {
+        let topology = [
+            LayerTopology::new(2),
+            LayerTopology::new(2).with_kind(LayerKind::BatchNorm),
+        ];
+
+        let a = Network::random(&topology);
+        let mut b = a.clone();
+
+        b.set_mode(Mode::Training);
+        b.propagate_batch(&[1.0, 2.0, 3.0, 5.0]);
+
+        assert_ne!(a.statistics(), b.statistics());
+        assert_eq!(a.distance(&b), 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "networks have different topologies")]
+    fn test_distance_needs_same_topology() {
+        let a = Network::random(&[LayerTopology::new(2), LayerTopology::new(1)]);
+        let b = Network::random(&[LayerTopology::new(2), LayerTopology::new(2)]);
+
+        a.distance(&b);
+    }
+
+    #[test]
+    fn test_behavioral_distance() {
+        let probes = [vec![1.0], vec![-1.0]];
+
+        //Outputs x + 1 and 2x, so 0 apart for x = 1 and 2 apart for x = -1
+        let mut a = Network::from_weights(&topology(), vec![1.0, 1.0]);
+        let mut b = Network::from_weights(&topology(), vec![0.0, 2.0]);
+
+        assert_relative_eq!(a.behavioral_distance(&mut b, &probes), 1.0);
+
+        //A wider hidden layer with a silent neuron behaves the same
+        let wide = [
+            LayerTopology::new(1),
+            LayerTopology::new(2).with_activation(Activation::Identity),
+            LayerTopology::new(1).with_activation(Activation::Identity),
+        ];
+
+        let mut c = Network::from_weights(&wide, vec![0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0]);
+        assert_relative_eq!(a.behavioral_distance(&mut c, &probes), 0.0);
+    }
+}
@@ -0,0 +1,262 @@
+use crate::*;
+use core::marker::PhantomData;
+
+/// Whether a network is being trained or used, which changes how `Dropout`
+/// and `BatchNorm` layers behave.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Inference,
+    Training,
+}
+
+impl<T: Scalar> Network<T> {
+    /// Switches every layer to the given mode; networks start out in
+    /// `Mode::Inference`.
+    pub fn set_mode(&mut self, mode: Mode) {
+        for layer in &mut self.layers {
+            layer.set_mode(mode);
+        }
+    }
+}
+
+/// Dropout (Srivastava et al., 2014): in `Mode::Training`, every input is
+/// zeroed with probability `rate`, and the others are scaled up to keep the
+/// expected sum the same. In `Mode::Inference`, inputs pass through as they
+/// are.
+///
+/// The layer has as many neurons as inputs, and no weights. Which inputs
+/// are dropped comes from a small generator of the layer's own, so training
+/// runs are reproducible. It's seeded from the network's random generator,
+/// or from the layer's position when built from weights, so that layers of
+/// the same width don't drop the same inputs.
+#[derive(Clone, Debug)]
+pub(crate) struct Dropout<T = f32> {
+    neurons: usize,
+    rate: f32,
+    activation: Activation,
+    training: bool,
+    //xorshift64* state
+    state: u64,
+    scalar: PhantomData<T>,
+}
+
+impl<T: Scalar> Dropout<T> {
+    pub(crate) fn new(neurons: usize, rate: f32, activation: Activation, seed: u64) -> Self {
+        assert!(
+            (0.0..1.0).contains(&rate),
+            "dropout rate {rate} isn't in [0, 1)"
+        );
+
+        Self {
+            neurons,
+            rate,
+            activation,
+            training: false,
+            state: mix(seed),
+            scalar: PhantomData,
+        }
+    }
+
+    pub(crate) fn neurons(&self) -> usize {
+        self.neurons
+    }
+
+    pub(crate) fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    pub(crate) fn activation(&self) -> Activation {
+        self.activation
+    }
+
+    pub(crate) fn set_mode(&mut self, mode: Mode) {
+        self.training = mode == Mode::Training;
+    }
+
+    /// Factors for every neuron's input: 0 for dropped ones, 1 / (1 - rate)
+    /// for the rest, or all 1 outside of training.
+    pub(crate) fn mask(&mut self) -> Vec<f32> {
+        (0..self.neurons).map(|_| self.factor()).collect()
+    }
+
+    pub(crate) fn propagate_into(&mut self, inputs: &[T], outputs: &mut Vec<T>) {
+        self.step(inputs, None, outputs);
+    }
+
+    pub(crate) fn trace_into(
+        &mut self,
+        inputs: &[T],
+        pre_activations: &mut Vec<T>,
+        outputs: &mut Vec<T>,
+    ) {
+        pre_activations.clear();
+        self.step(inputs, Some(pre_activations), outputs);
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[T],
+        mut pre_activations: Option<&mut Vec<T>>,
+        outputs: &mut Vec<T>,
+    ) {
+        assert_eq!(inputs.len() % self.neurons, 0);
+
+        outputs.clear();
+
+        for &input in inputs {
+            let value = input * T::from_f32(self.factor());
+
+            if let Some(pre_activations) = pre_activations.as_deref_mut() {
+                pre_activations.push(value);
+            }
+
+            outputs.push(self.activation.apply(value));
+        }
+    }
+
+    fn factor(&mut self) -> f32 {
+        if !self.training {
+            1.0
+        } else if self.next() < self.rate {
+            0.0
+        } else {
+            1.0 / (1.0 - self.rate)
+        }
+    }
+
+    //Uniform in [0, 1)
+    fn next(&mut self) -> f32 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+
+        (self.state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 40) as f32 / (1u32 << 24) as f32
+    }
+}
+
+//splitmix64's finalizer, so that close seeds (like layer positions) still
+//give unrelated states; xorshift gets stuck at zero, so that one is skipped
+fn mix(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+    (z ^ (z >> 31)).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_modes() {
+        let mut layer: Dropout = Dropout::new(1000, 0.25, Activation::Identity, 0);
+        let inputs = vec![1.0; 1000];
+        let mut outputs = Vec::new();
+
+        layer.propagate_into(&inputs, &mut outputs);
+        assert_eq!(outputs, inputs);
+
+        layer.set_mode(Mode::Training);
+        layer.propagate_into(&inputs, &mut outputs);
+
+        let dropped = outputs.iter().filter(|&&output| output == 0.0).count();
+        assert!((200..300).contains(&dropped), "{dropped} dropped");
+
+        //What's kept is scaled, so that the expected sum stays the same
+        assert!(outputs
+            .iter()
+            .all(|&output| output == 0.0 || output == 1.0 / 0.75));
+        assert_relative_eq!(outputs.iter().sum::<f32>(), 1000.0, max_relative = 0.1);
+    }
+
+    #[test]
+    fn test_layers_draw_their_own_masks() {
+        let topology = [
+            LayerTopology::new(64),
+            LayerTopology::new(64).with_kind(LayerKind::Dropout(0.5)),
+            LayerTopology::new(64).with_kind(LayerKind::Dropout(0.5)),
+        ];
+
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        for mut network in [
+            Network::random_with(&mut rng, &topology, Init::default()),
+            Network::from_weights(&topology, vec![]),
+        ] {
+            network.set_mode(Mode::Training);
+
+            let masks: Vec<_> = network
+                .layers
+                .iter_mut()
+                .map(|layer| match layer {
+                    Layer::Dropout(layer) => layer.mask(),
+                    _ => unreachable!(),
+                })
+                .collect();
+
+            assert_ne!(masks[0], masks[1]);
+        }
+    }
+
+    #[test]
+    fn test_in_network() {
+        let topology = [
+            LayerTopology::new(4),
+            LayerTopology::new(4)
+                .with_activation(Activation::Identity)
+                .with_kind(LayerKind::LayerNorm),
+            LayerTopology::new(4)
+                .with_activation(Activation::Identity)
+                .with_kind(LayerKind::Dropout(0.5)),
+            LayerTopology::new(4)
+                .with_activation(Activation::Identity)
+                .with_kind(LayerKind::BatchNorm),
+        ];
+
+        let mut network = Network::random(&topology);
+        assert_eq!(network.topology(), topology);
+        assert_eq!(network.weights().len(), 2 * 4 + 2 * 4);
+
+        let inputs = vec![1.0, 2.0, 3.0, 4.0];
+        let outputs = network.propagate(inputs.clone());
+
+        //A freshly initialized network only normalizes its inputs
+        assert_relative_eq!(outputs.iter().sum::<f32>(), 0.0, epsilon = 1e-4);
+        assert_relative_eq!(
+            outputs.iter().map(|output| output * output).sum::<f32>(),
+            4.0,
+            max_relative = 1e-3
+        );
+
+        network.set_mode(Mode::Training);
+        assert_ne!(network.propagate(inputs.clone()), outputs);
+
+        network.set_mode(Mode::Inference);
+        assert_eq!(network.propagate(inputs), outputs);
+
+        let mismatched = [
+            LayerTopology::new(4),
+            LayerTopology::new(3).with_kind(LayerKind::LayerNorm),
+        ];
+
+        assert_eq!(
+            Network::try_random(&mismatched).unwrap_err(),
+            NetworkError::WidthMismatch { layer: 1 }
+        );
+
+        let invalid = [
+            LayerTopology::new(4),
+            LayerTopology::new(4).with_kind(LayerKind::Dropout(1.0)),
+        ];
+
+        assert_eq!(
+            Network::try_random(&invalid).unwrap_err(),
+            NetworkError::InvalidDropout { layer: 1 }
+        );
+    }
+}
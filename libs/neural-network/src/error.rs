@@ -0,0 +1,363 @@
+use crate::*;
+use core::fmt;
+
+/// Why a `Network` couldn't be built from a topology and weights.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NetworkError {
+    /// The topology doesn't have both an input and an output layer.
+    EmptyTopology {
+        layers: usize,
+    },
+    /// One of the layers has no neurons.
+    ZeroWidthLayer {
+        layer: usize,
+    },
+    /// One of the layers' activation has a parameter that's NaN or
+    /// infinite.
+    InvalidActivation {
+        layer: usize,
+    },
+    /// The topology has more weights than can be counted in a `usize`.
+    TopologyTooLarge,
+    TooFewWeights {
+        expected: usize,
+        found: usize,
+    },
+    TooManyWeights {
+        expected: usize,
+        found: usize,
+    },
+    /// The weight at the given position (in the layout of
+    /// `Network::weights()`) is NaN.
+    NanWeight {
+        index: usize,
+    },
+    /// A `SparseNetwork` refers to a neuron it wasn't given.
+    UnknownNeuron {
+        id: usize,
+    },
+    /// A `SparseNetwork` was given more than one input or neuron with the
+    /// same id.
+    DuplicateNeuron {
+        id: usize,
+    },
+    ConnectionIntoInput {
+        from: usize,
+        to: usize,
+    },
+    /// The neuron with the given id is part of a cycle, so there's no order
+    /// in which the `SparseNetwork` could be evaluated.
+    Cycle {
+        id: usize,
+    },
+    /// A `Graph` doesn't have any output nodes.
+    NoOutputs,
+    /// A `Graph` node doesn't read from any other node.
+    NoSources {
+        node: usize,
+    },
+    /// A `Graph` node reads from a node that doesn't exist, or that isn't
+    /// added before it.
+    UnknownNode {
+        node: usize,
+    },
+    /// A `Graph` node adds up nodes of different sizes.
+    SizeMismatch {
+        node: usize,
+    },
+    /// A convolutional layer's shape doesn't fit the layer before it, or
+    /// its neuron count doesn't match that shape.
+    InvalidConvolution {
+        layer: usize,
+    },
+    /// A spiking layer's threshold isn't positive, or its leak isn't within
+    /// [0, 1].
+    InvalidSpiking {
+        layer: usize,
+    },
+    /// A normalization or dropout layer isn't as wide as the layer before
+    /// it.
+    WidthMismatch {
+        layer: usize,
+    },
+    /// A dropout layer's rate isn't within [0, 1).
+    InvalidDropout {
+        layer: usize,
+    },
+    /// Backpropagation can't go through the given layer: only dense,
+    /// normalization and dropout layers support it.
+    Untrainable {
+        layer: usize,
+    },
+    /// `Network::quantize` only supports dense layers.
+    Unquantizable {
+        layer: usize,
+    },
+    /// A layer of a `QuantizedNetwork` has a scale that isn't finite and
+    /// positive.
+    InvalidQuantization {
+        layer: usize,
+    },
+    /// A layer of a `QuantizedNetwork` doesn't take as many inputs as the
+    /// layer before it has neurons.
+    InputMismatch {
+        layer: usize,
+    },
+    /// `Heads` were given more than one head with the same name.
+    DuplicateHead {
+        name: String,
+    },
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyTopology { layers } => write!(
+                f,
+                "topology has {layers} layer(s), but at least two are needed"
+            ),
+            Self::ZeroWidthLayer { layer } => write!(f, "layer {layer} has no neurons"),
+            Self::InvalidActivation { layer } => {
+                write!(f, "activation parameter of layer {layer} isn't finite")
+            }
+            Self::TopologyTooLarge => write!(f, "topology has too many weights"),
+            Self::TooFewWeights { expected, found } => write!(
+                f,
+                "got not enough weights (expected {expected}, found {found})"
+            ),
+            Self::TooManyWeights { expected, found } => write!(
+                f,
+                "got too many weights (expected {expected}, found {found})"
+            ),
+            Self::NanWeight { index } => write!(f, "weight {index} is NaN"),
+            Self::UnknownNeuron { id } => write!(f, "neuron {id} doesn't exist"),
+            Self::DuplicateNeuron { id } => write!(f, "neuron {id} is given more than once"),
+            Self::ConnectionIntoInput { from, to } => {
+                write!(f, "neuron {from} is connected into input {to}")
+            }
+            Self::Cycle { id } => write!(f, "neuron {id} is part of a cycle"),
+            Self::NoOutputs => write!(f, "graph has no outputs"),
+            Self::NoSources { node } => write!(f, "node {node} has no sources"),
+            Self::UnknownNode { node } => write!(f, "node {node} doesn't exist"),
+            Self::SizeMismatch { node } => {
+                write!(f, "nodes added by node {node} differ in size")
+            }
+            Self::InvalidConvolution { layer } => {
+                write!(f, "convolution of layer {layer} doesn't fit its inputs")
+            }
+            Self::InvalidSpiking { layer } => {
+                write!(f, "spiking parameters of layer {layer} are out of range")
+            }
+            Self::WidthMismatch { layer } => {
+                write!(f, "layer {layer} has to be as wide as the layer before it")
+            }
+            Self::InvalidDropout { layer } => {
+                write!(f, "dropout rate of layer {layer} isn't in [0, 1)")
+            }
+            Self::Untrainable { layer } => {
+                write!(f, "layer {layer} can't be trained by backpropagation")
+            }
+            Self::Unquantizable { layer } => write!(f, "layer {layer} can't be quantized"),
+            Self::InvalidQuantization { layer } => {
+                write!(f, "quantization scale of layer {layer} isn't positive")
+            }
+            Self::InputMismatch { layer } => write!(
+                f,
+                "layer {layer} doesn't take as many inputs as the layer before it has neurons"
+            ),
+            Self::DuplicateHead { name } => write!(f, "head {name} already exists"),
+        }
+    }
+}
+
+impl core::error::Error for NetworkError {}
+
+pub(crate) fn check_topology(layers: &[LayerTopology]) -> Result<(), NetworkError> {
+    if layers.len() < 2 {
+        return Err(NetworkError::EmptyTopology {
+            layers: layers.len(),
+        });
+    }
+
+    //The input layer only has a width, everything else is checked along
+    //with the layer it feeds
+    if layers[0].neurons == 0 {
+        return Err(NetworkError::ZeroWidthLayer { layer: 0 });
+    }
+
+    if !layers[0].activation.is_valid() {
+        return Err(NetworkError::InvalidActivation { layer: 0 });
+    }
+
+    for (index, pair) in layers.windows(2).enumerate() {
+        check_layer(index + 1, pair[0].neurons, &pair[1])?;
+    }
+
+    weights_len(layers)?;
+
+    Ok(())
+}
+
+/// Checks a single layer that takes `inputs` values, reporting errors as
+/// coming from the layer at `index`; shared by `Network`s and `Graph`s.
+pub(crate) fn check_layer(
+    index: usize,
+    inputs: usize,
+    topology: &LayerTopology,
+) -> Result<(), NetworkError> {
+    if topology.neurons == 0 {
+        return Err(NetworkError::ZeroWidthLayer { layer: index });
+    }
+
+    if !topology.activation.is_valid() {
+        return Err(NetworkError::InvalidActivation { layer: index });
+    }
+
+    match topology.kind {
+        LayerKind::Conv1d(convolution) if convolution.neurons(inputs) != Some(topology.neurons) => {
+            Err(NetworkError::InvalidConvolution { layer: index })
+        }
+        LayerKind::Spiking(spiking) if !spiking.is_valid() => {
+            Err(NetworkError::InvalidSpiking { layer: index })
+        }
+        kind if kind.keeps_width() && inputs != topology.neurons => {
+            Err(NetworkError::WidthMismatch { layer: index })
+        }
+        LayerKind::Dropout(rate) if !(0.0..1.0).contains(&rate) => {
+            Err(NetworkError::InvalidDropout { layer: index })
+        }
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn check_weights<T: Scalar>(
+    layers: &[LayerTopology],
+    weights: &[T],
+) -> Result<(), NetworkError> {
+    check_weights_len(weights_len(layers)?, weights)
+}
+
+//How many weights a network of the given topology has
+fn weights_len(layers: &[LayerTopology]) -> Result<usize, NetworkError> {
+    layers
+        .windows(2)
+        .try_fold(0usize, |total, layers| {
+            total.checked_add(Layer::weights_len(layers[0].neurons, &layers[1])?)
+        })
+        .ok_or(NetworkError::TopologyTooLarge)
+}
+
+/// Checks that there are exactly `expected` weights, none of them NaN.
+pub(crate) fn check_weights_len<T: Scalar>(
+    expected: usize,
+    weights: &[T],
+) -> Result<(), NetworkError> {
+    let found = weights.len();
+
+    if found < expected {
+        return Err(NetworkError::TooFewWeights { expected, found });
+    }
+
+    if found > expected {
+        return Err(NetworkError::TooManyWeights { expected, found });
+    }
+
+    if let Some(index) = weights.iter().position(|weight| weight.is_nan()) {
+        return Err(NetworkError::NanWeight { index });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn topology() -> [LayerTopology; 2] {
+        [LayerTopology::new(2), LayerTopology::new(1)]
+    }
+
+    #[test]
+    fn test_empty_topology() {
+        assert_eq!(
+            Network::try_random(&[LayerTopology::new(3)]).unwrap_err(),
+            NetworkError::EmptyTopology { layers: 1 }
+        );
+    }
+
+    #[test]
+    fn test_zero_width_layer() {
+        let topology = [LayerTopology::new(2), LayerTopology::new(0)];
+
+        assert_eq!(
+            Network::<f32>::try_from_weights(&topology, vec![]).unwrap_err(),
+            NetworkError::ZeroWidthLayer { layer: 1 }
+        );
+    }
+
+    #[test]
+    fn test_invalid_activation() {
+        let topology = [
+            LayerTopology::new(2),
+            LayerTopology::new(1).with_activation(Activation::LeakyRelu(f32::NAN)),
+        ];
+
+        assert_eq!(
+            Network::try_from_weights(&topology, vec![0.1; 3]).unwrap_err(),
+            NetworkError::InvalidActivation { layer: 1 }
+        );
+
+        let topology = [
+            LayerTopology::new(2),
+            LayerTopology::new(1).with_activation(Activation::Elu(f32::INFINITY)),
+        ];
+
+        assert_eq!(
+            Network::try_random(&topology).unwrap_err(),
+            NetworkError::InvalidActivation { layer: 1 }
+        );
+    }
+
+    #[test]
+    fn test_weight_count() {
+        assert_eq!(
+            Network::try_from_weights(&topology(), vec![0.1, 0.2]).unwrap_err(),
+            NetworkError::TooFewWeights {
+                expected: 3,
+                found: 2
+            }
+        );
+
+        assert_eq!(
+            Network::try_from_weights(&topology(), vec![0.1; 5]).unwrap_err(),
+            NetworkError::TooManyWeights {
+                expected: 3,
+                found: 5
+            }
+        );
+    }
+
+    #[test]
+    fn test_topology_too_large() {
+        let topology = [LayerTopology::new(usize::MAX / 2), LayerTopology::new(3)];
+
+        assert_eq!(
+            Network::<f32>::try_from_weights(&topology, vec![]).unwrap_err(),
+            NetworkError::TopologyTooLarge
+        );
+    }
+
+    #[test]
+    fn test_nan_weight() {
+        assert_eq!(
+            Network::try_from_weights(&topology(), vec![0.1, f32::NAN, 0.3]).unwrap_err(),
+            NetworkError::NanWeight { index: 1 }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "got too many weights")]
+    fn test_from_weights_panics() {
+        Network::from_weights(&topology(), vec![0.1; 4]);
+    }
+}
@@ -0,0 +1,46 @@
+//Without `std`, floats lack the methods that need a math library, so they come
+//from libm instead. `exp`, `tanh`, `abs` and `sqrt` are left out, since
+//`Scalar` already provides them.
+
+pub(crate) trait Float {
+    fn ln(self) -> Self;
+    fn cos(self) -> Self;
+    fn round(self) -> Self;
+    fn powi(self, n: i32) -> Self;
+}
+
+impl Float for f32 {
+    fn ln(self) -> Self {
+        libm::logf(self)
+    }
+
+    fn cos(self) -> Self {
+        libm::cosf(self)
+    }
+
+    fn round(self) -> Self {
+        libm::roundf(self)
+    }
+
+    fn powi(self, n: i32) -> Self {
+        libm::powf(self, n as f32)
+    }
+}
+
+impl Float for f64 {
+    fn ln(self) -> Self {
+        libm::log(self)
+    }
+
+    fn cos(self) -> Self {
+        libm::cos(self)
+    }
+
+    fn round(self) -> Self {
+        libm::round(self)
+    }
+
+    fn powi(self, n: i32) -> Self {
+        libm::pow(self, n as f64)
+    }
+}
@@ -0,0 +1,733 @@
+use crate::*;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::{fs, io, path::Path};
+
+/// Version written into every saved network, and the only one that can be
+/// loaded; bump it whenever the layout of either format changes.
+///
+/// Both formats hold the version, the topology - every layer's width,
+/// activation and kind, along with the kind's parameters - then the weights,
+/// laid out like `Network::weights()`, and last the running mean and
+/// variance of every batch normalization neuron.
+pub const FORMAT_VERSION: u32 = 1;
+
+//Binary files start with these bytes, which is also how `load` tells the
+//two formats apart
+const MAGIC: &[u8; 4] = b"NNET";
+
+/// On-disk representation of a `Network`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Human-readable, self-describing JSON.
+    Json,
+    /// Compact little-endian encoding.
+    Binary,
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    #[cfg(feature = "std")]
+    Json(serde_json::Error),
+    /// The data was written by a newer (or unknown) version of this crate.
+    UnsupportedVersion { found: u32, supported: u32 },
+    /// The binary data is truncated or contains unknown values.
+    Corrupted(&'static str),
+    /// The stored weights don't fit the stored topology.
+    Network(NetworkError),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(err) => write!(f, "couldn't access the network file: {err}"),
+            #[cfg(feature = "std")]
+            Self::Json(err) => write!(f, "couldn't parse the network JSON: {err}"),
+            Self::UnsupportedVersion { found, supported } => write!(
+                f,
+                "network was saved with format version {found}, but only version {supported} is supported"
+            ),
+            Self::Corrupted(reason) => write!(f, "network data is corrupted: {reason}"),
+            Self::Network(err) => write!(f, "network doesn't match its topology: {err}"),
+        }
+    }
+}
+
+impl core::error::Error for FormatError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(err) => Some(err),
+            #[cfg(feature = "std")]
+            Self::Json(err) => Some(err),
+            Self::Network(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for FormatError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<NetworkError> for FormatError {
+    fn from(err: NetworkError) -> Self {
+        Self::Network(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<serde_json::Error> for FormatError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedNetwork {
+    version: u32,
+    topology: Vec<LayerTopology>,
+    weights: Vec<f32>,
+    //Running statistics of batch normalization layers
+    statistics: Vec<f32>,
+}
+
+//Read on its own first, so that files from other versions are reported as
+//such instead of as whatever field happens not to parse
+#[cfg(feature = "std")]
+#[derive(Deserialize)]
+struct SavedVersion {
+    version: u32,
+}
+
+impl SavedNetwork {
+    fn into_network(self) -> Result<Network, FormatError> {
+        check_version(self.version)?;
+
+        let statistics = self.statistics;
+        let mut network = Network::try_from_weights(&self.topology, self.weights)?;
+
+        if statistics.len() != network.statistics().len() {
+            return Err(FormatError::Corrupted(
+                "running statistics don't fit the topology",
+            ));
+        }
+
+        //Every mean is followed by a variance, which can't be negative
+        if statistics
+            .chunks_exact(2)
+            .any(|pair| pair[0].is_nan() || pair[1].is_nan() || pair[1] < 0.0)
+        {
+            return Err(FormatError::Corrupted("invalid running statistics"));
+        }
+
+        network.set_statistics(&statistics);
+        Ok(network)
+    }
+}
+
+impl Network {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let saved = self.to_saved();
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&saved.version.to_le_bytes());
+        bytes.extend_from_slice(&(saved.topology.len() as u32).to_le_bytes());
+
+        for layer in &saved.topology {
+            let (tag, param) = encode_activation(layer.activation);
+
+            bytes.extend_from_slice(&(layer.neurons as u32).to_le_bytes());
+            bytes.push(tag);
+            bytes.extend_from_slice(&param.to_le_bytes());
+            encode_kind(layer.kind, &mut bytes);
+        }
+
+        bytes.extend_from_slice(&(saved.weights.len() as u32).to_le_bytes());
+
+        for weight in &saved.weights {
+            bytes.extend_from_slice(&weight.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&(saved.statistics.len() as u32).to_le_bytes());
+
+        for value in &saved.statistics {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FormatError> {
+        let mut reader = Reader { bytes };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(FormatError::Corrupted("missing header"));
+        }
+
+        let version = reader.u32()?;
+        check_version(version)?;
+
+        let topology = (0..reader.u32()?)
+            .map(|_| {
+                let neurons = reader.u32()? as usize;
+                let activation = decode_activation(reader.u8()?, reader.f32()?)?;
+
+                let kind = decode_kind(&mut reader)?;
+
+                Ok(LayerTopology::new(neurons)
+                    .with_activation(activation)
+                    .with_kind(kind))
+            })
+            .collect::<Result<_, FormatError>>()?;
+
+        let weights = (0..reader.u32()?)
+            .map(|_| reader.f32())
+            .collect::<Result<_, _>>()?;
+
+        let statistics = (0..reader.u32()?)
+            .map(|_| reader.f32())
+            .collect::<Result<_, _>>()?;
+
+        if !reader.bytes.is_empty() {
+            return Err(FormatError::Corrupted("unexpected data after the weights"));
+        }
+
+        SavedNetwork {
+            version,
+            topology,
+            weights,
+            statistics,
+        }
+        .into_network()
+    }
+
+    fn to_saved(&self) -> SavedNetwork {
+        SavedNetwork {
+            version: FORMAT_VERSION,
+            topology: self.topology(),
+            weights: self.weights(),
+            statistics: self.statistics(),
+        }
+    }
+}
+
+//JSON and files need `std`; the binary format only needs `alloc`
+#[cfg(feature = "std")]
+impl Network {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.to_saved()).expect("network is always serializable")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, FormatError> {
+        let SavedVersion { version } = serde_json::from_str(json)?;
+        check_version(version)?;
+
+        serde_json::from_str::<SavedNetwork>(json)?.into_network()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>, format: Format) -> Result<(), FormatError> {
+        let bytes = match format {
+            Format::Json => self.to_json().into_bytes(),
+            Format::Binary => self.to_bytes(),
+        };
+
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Loads a network saved in either format.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FormatError> {
+        let bytes = fs::read(path)?;
+
+        if bytes.starts_with(MAGIC) {
+            Self::from_bytes(&bytes)
+        } else {
+            let json = std::str::from_utf8(&bytes)
+                .map_err(|_| FormatError::Corrupted("neither JSON nor binary"))?;
+
+            Self::from_json(json)
+        }
+    }
+}
+
+fn check_version(version: u32) -> Result<(), FormatError> {
+    if version == FORMAT_VERSION {
+        Ok(())
+    } else {
+        Err(FormatError::UnsupportedVersion {
+            found: version,
+            supported: FORMAT_VERSION,
+        })
+    }
+}
+
+fn encode_activation(activation: Activation) -> (u8, f32) {
+    match activation {
+        Activation::Relu => (0, 0.0),
+        Activation::LeakyRelu(slope) => (1, slope),
+        Activation::Elu(alpha) => (2, alpha),
+        Activation::Sigmoid => (3, 0.0),
+        Activation::Tanh => (4, 0.0),
+        Activation::Identity => (5, 0.0),
+        Activation::Softsign => (6, 0.0),
+        Activation::Step => (7, 0.0),
+    }
+}
+
+fn decode_activation(tag: u8, param: f32) -> Result<Activation, FormatError> {
+    Ok(match tag {
+        0 => Activation::Relu,
+        1 => Activation::LeakyRelu(param),
+        2 => Activation::Elu(param),
+        3 => Activation::Sigmoid,
+        4 => Activation::Tanh,
+        5 => Activation::Identity,
+        6 => Activation::Softsign,
+        7 => Activation::Step,
+        _ => return Err(FormatError::Corrupted("unknown activation")),
+    })
+}
+
+fn encode_kind(kind: LayerKind, bytes: &mut Vec<u8>) {
+    match kind {
+        LayerKind::Dense => bytes.push(0),
+        LayerKind::Elman => bytes.push(1),
+        LayerKind::Gru => bytes.push(2),
+        LayerKind::Conv1d(convolution) => {
+            bytes.push(3);
+
+            for param in [
+                convolution.channels,
+                convolution.filters,
+                convolution.kernel,
+            ] {
+                bytes.extend_from_slice(&(param as u32).to_le_bytes());
+            }
+
+            bytes.push(match convolution.padding {
+                Padding::Valid => 0,
+                Padding::Circular => 1,
+            });
+        }
+        LayerKind::Hebbian(plasticity) => {
+            bytes.push(4);
+            bytes.push(match plasticity {
+                Plasticity::PerLayer => 0,
+                Plasticity::PerConnection => 1,
+            });
+        }
+        LayerKind::Spiking(spiking) => {
+            bytes.push(5);
+            bytes.extend_from_slice(&spiking.threshold.to_le_bytes());
+            bytes.extend_from_slice(&spiking.leak.to_le_bytes());
+            bytes.extend_from_slice(&spiking.refractory.to_le_bytes());
+            bytes.push(match spiking.output {
+                SpikeOutput::Spikes => 0,
+                SpikeOutput::Counts => 1,
+                SpikeOutput::Potentials => 2,
+            });
+        }
+        LayerKind::LayerNorm => bytes.push(6),
+        LayerKind::BatchNorm => bytes.push(7),
+        LayerKind::Dropout(rate) => {
+            bytes.push(8);
+            bytes.extend_from_slice(&rate.to_le_bytes());
+        }
+    }
+}
+
+fn decode_kind(reader: &mut Reader) -> Result<LayerKind, FormatError> {
+    Ok(match reader.u8()? {
+        0 => LayerKind::Dense,
+        1 => LayerKind::Elman,
+        2 => LayerKind::Gru,
+        3 => LayerKind::Conv1d(Convolution {
+            channels: reader.u32()? as usize,
+            filters: reader.u32()? as usize,
+            kernel: reader.u32()? as usize,
+            padding: match reader.u8()? {
+                0 => Padding::Valid,
+                1 => Padding::Circular,
+                _ => return Err(FormatError::Corrupted("unknown padding")),
+            },
+        }),
+        4 => LayerKind::Hebbian(match reader.u8()? {
+            0 => Plasticity::PerLayer,
+            1 => Plasticity::PerConnection,
+            _ => return Err(FormatError::Corrupted("unknown plasticity")),
+        }),
+        5 => LayerKind::Spiking(Spiking {
+            threshold: reader.f32()?,
+            leak: reader.f32()?,
+            refractory: reader.u32()?,
+            output: match reader.u8()? {
+                0 => SpikeOutput::Spikes,
+                1 => SpikeOutput::Counts,
+                2 => SpikeOutput::Potentials,
+                _ => return Err(FormatError::Corrupted("unknown spike output")),
+            },
+        }),
+        6 => LayerKind::LayerNorm,
+        7 => LayerKind::BatchNorm,
+        8 => LayerKind::Dropout(reader.f32()?),
+        _ => return Err(FormatError::Corrupted("unknown layer kind")),
+    })
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], FormatError> {
+        if self.bytes.len() < len {
+            return Err(FormatError::Corrupted("unexpected end of data"));
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, FormatError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, FormatError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, FormatError> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network() -> Network {
+        let topology = [
+            LayerTopology::new(3),
+            LayerTopology::new(2)
+                .with_activation(Activation::LeakyRelu(0.1))
+                .with_kind(LayerKind::Elman),
+            LayerTopology::new(1).with_activation(Activation::Tanh),
+        ];
+
+        Network::from_weights(&topology, (0..15).map(|n| n as f32 / 10.0 - 0.5))
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let network = network();
+        let loaded = Network::from_json(&network.to_json()).unwrap();
+
+        assert_eq!(loaded.topology(), network.topology());
+        assert_eq!(loaded.weights(), network.weights());
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let network = network();
+        let loaded = Network::from_bytes(&network.to_bytes()).unwrap();
+
+        assert_eq!(loaded.topology(), network.topology());
+        assert_eq!(loaded.weights(), network.weights());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let network = network();
+        let dir = std::env::temp_dir();
+
+        for (name, format) in [("nn.json", Format::Json), ("nn.bin", Format::Binary)] {
+            let path = dir.join(format!("neural-network-{}-{name}", std::process::id()));
+
+            network.save(&path, format).unwrap();
+            let loaded = Network::load(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+
+            assert_eq!(loaded.weights(), network.weights());
+        }
+    }
+
+    #[test]
+    fn test_conv_round_trip() {
+        let convolution = Convolution::new(2, 3)
+            .with_channels(2)
+            .with_padding(Padding::Circular);
+
+        let topology = [
+            LayerTopology::new(8),
+            LayerTopology::conv1d(8, convolution),
+            LayerTopology::new(1),
+        ];
+
+        let network = Network::random(&topology);
+        let loaded = Network::from_bytes(&network.to_bytes()).unwrap();
+
+        assert_eq!(loaded.topology(), topology);
+        assert_eq!(loaded.weights(), network.weights());
+    }
+
+    #[test]
+    fn test_hebbian_round_trip() {
+        let topology = [
+            LayerTopology::new(3),
+            LayerTopology::new(2).with_kind(LayerKind::Hebbian(Plasticity::PerConnection)),
+            LayerTopology::new(2).with_kind(LayerKind::Hebbian(Plasticity::PerLayer)),
+        ];
+
+        let network = Network::random(&topology);
+
+        for loaded in [
+            Network::from_bytes(&network.to_bytes()).unwrap(),
+            Network::from_json(&network.to_json()).unwrap(),
+        ] {
+            assert_eq!(loaded.topology(), topology);
+            assert_eq!(loaded.weights(), network.weights());
+        }
+    }
+
+    #[test]
+    fn test_spiking_round_trip() {
+        let spiking = Spiking::new(0.8, 0.25)
+            .with_refractory(3)
+            .with_output(SpikeOutput::Potentials);
+
+        let topology = [
+            LayerTopology::new(3),
+            LayerTopology::new(2).with_kind(LayerKind::Spiking(spiking)),
+        ];
+
+        let network = Network::random(&topology);
+        let loaded = Network::from_bytes(&network.to_bytes()).unwrap();
+
+        assert_eq!(loaded.topology(), topology);
+        assert_eq!(loaded.weights(), network.weights());
+    }
+
+    #[test]
+    fn test_normalization_round_trip() {
+        let topology = [
+            LayerTopology::new(3),
+            LayerTopology::new(3).with_kind(LayerKind::BatchNorm),
+            LayerTopology::new(3).with_kind(LayerKind::Dropout(0.2)),
+            LayerTopology::new(3).with_kind(LayerKind::LayerNorm),
+        ];
+
+        let mut network = Network::random(&topology);
+
+        //Moves the running statistics away from where they start
+        network.set_mode(Mode::Training);
+        network.propagate_batch(&[1.0, 2.0, 3.0, -1.0, 0.5, 4.0]);
+        network.set_mode(Mode::Inference);
+
+        for loaded in [
+            Network::from_bytes(&network.to_bytes()).unwrap(),
+            Network::from_json(&network.to_json()).unwrap(),
+        ] {
+            assert_eq!(loaded.topology(), topology);
+            assert_eq!(loaded.weights(), network.weights());
+            assert_eq!(loaded.statistics(), network.statistics());
+        }
+    }
+
+    #[test]
+    fn test_invalid_statistics() {
+        let topology = [
+            LayerTopology::new(1),
+            LayerTopology::new(1).with_kind(LayerKind::BatchNorm),
+        ];
+
+        let json = Network::random(&topology).to_json();
+        let statistics = json.find("\"statistics\"").unwrap();
+
+        let (weights, rest) = json.split_at(statistics);
+        let negative = format!("{weights}{}", rest.replacen("1.0", "-1.0", 1));
+        let missing = format!(
+            "{weights}{}",
+            rest.replacen("1.0", "", 1).replacen(",", "", 1)
+        );
+
+        for json in [negative, missing] {
+            assert!(
+                matches!(Network::from_json(&json), Err(FormatError::Corrupted(_))),
+                "{json}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_version_mismatch() {
+        let json = network()
+            .to_json()
+            .replace(&format!("\"version\": {FORMAT_VERSION}"), "\"version\": 99");
+
+        assert!(matches!(
+            Network::from_json(&json),
+            Err(FormatError::UnsupportedVersion { found: 99, .. })
+        ));
+
+        let mut bytes = network().to_bytes();
+        bytes[4] = 99;
+
+        assert!(matches!(
+            Network::from_bytes(&bytes),
+            Err(FormatError::UnsupportedVersion { found: 99, .. })
+        ));
+    }
+
+    #[test]
+    fn test_binary_layout() {
+        #[rustfmt::skip]
+        let bytes: Vec<u8> = [
+            &b"NNET"[..],
+            &FORMAT_VERSION.to_le_bytes(),
+            //Two dense layers: neurons, activation with its parameter, kind
+            &2u32.to_le_bytes(),
+            &1u32.to_le_bytes(), &[0], &0.0f32.to_le_bytes(), &[0],
+            &1u32.to_le_bytes(), &[5], &0.0f32.to_le_bytes(), &[0],
+            //Weights, then no running statistics
+            &2u32.to_le_bytes(),
+            &0.5f32.to_le_bytes(), &2.0f32.to_le_bytes(),
+            &0u32.to_le_bytes(),
+        ]
+        .concat();
+
+        let mut network = Network::from_bytes(&bytes).unwrap();
+
+        assert_eq!(network.topology()[1].kind, LayerKind::Dense);
+        assert_eq!(network.propagate(vec![1.0]), vec![2.5]);
+        assert_eq!(network.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_shape_mismatch() {
+        let json = r#"{
+            "version": 1,
+            "topology": [
+                { "neurons": 2, "activation": "Relu", "kind": "Dense" },
+                { "neurons": 1, "activation": "Relu", "kind": "Dense" }
+            ],
+            "weights": [0.1, 0.2],
+            "statistics": []
+        }"#;
+
+        assert!(matches!(
+            Network::from_json(json),
+            Err(FormatError::Network(NetworkError::TooFewWeights {
+                expected: 3,
+                found: 2
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_activation() {
+        let mut bytes = network().to_bytes();
+
+        //The leaky ReLU's slope comes before any weight
+        let slope = bytes
+            .windows(4)
+            .position(|window| window == 0.1f32.to_le_bytes())
+            .unwrap();
+
+        bytes[slope..][..4].copy_from_slice(&f32::NAN.to_le_bytes());
+
+        assert!(matches!(
+            Network::from_bytes(&bytes),
+            Err(FormatError::Network(NetworkError::InvalidActivation {
+                layer: 1
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_huge_topology() {
+        //Three dense layers as wide as the format allows, and no weights
+        let layer = [
+            &u32::MAX.to_le_bytes()[..],
+            &[0],
+            &0.0f32.to_le_bytes(),
+            &[0],
+        ]
+        .concat();
+
+        let bytes = [
+            &b"NNET"[..],
+            &FORMAT_VERSION.to_le_bytes(),
+            &3u32.to_le_bytes(),
+            &layer,
+            &layer,
+            &layer,
+            &0u32.to_le_bytes(),
+            &0u32.to_le_bytes(),
+        ]
+        .concat();
+
+        assert!(matches!(
+            Network::from_bytes(&bytes),
+            Err(FormatError::Network(NetworkError::TopologyTooLarge))
+        ));
+    }
+
+    #[test]
+    fn test_huge_recurrent_layer() {
+        //Two layers as wide as the format allows, the second a GRU
+        let layer = |kind| {
+            [
+                &u32::MAX.to_le_bytes()[..],
+                &[0],
+                &0.0f32.to_le_bytes(),
+                &[kind],
+            ]
+            .concat()
+        };
+
+        let bytes = [
+            &b"NNET"[..],
+            &FORMAT_VERSION.to_le_bytes(),
+            &2u32.to_le_bytes(),
+            &layer(0),
+            &layer(2),
+            &0u32.to_le_bytes(),
+            &0u32.to_le_bytes(),
+        ]
+        .concat();
+
+        assert!(matches!(
+            Network::from_bytes(&bytes),
+            Err(FormatError::Network(NetworkError::TopologyTooLarge))
+        ));
+
+        let topology = [
+            LayerTopology::new(usize::MAX / 2),
+            LayerTopology::new(3).with_kind(LayerKind::Elman),
+        ];
+
+        assert_eq!(
+            Network::<f32>::try_from_weights(&topology, vec![]).unwrap_err(),
+            NetworkError::TopologyTooLarge
+        );
+    }
+
+    #[test]
+    fn test_truncated_binary() {
+        let bytes = network().to_bytes();
+
+        assert!(matches!(
+            Network::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(FormatError::Corrupted(_))
+        ));
+    }
+}
@@ -0,0 +1,572 @@
+use crate::*;
+
+/// Handle to a node of a `Graph`, returned when adding the node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NodeId(usize);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GraphNode {
+    /// Group of the network's inputs, e.g. vision or internal state.
+    Input { size: usize },
+    /// Layer fed with the outputs of `from`, one after another.
+    Layer {
+        from: Vec<NodeId>,
+        topology: LayerTopology,
+    },
+    /// Element-wise sum of the outputs of `from`, e.g. for a residual
+    /// connection.
+    Add { from: Vec<NodeId> },
+    /// Outputs of `from`, one after another.
+    Concat { from: Vec<NodeId> },
+}
+
+/// Shape of a `GraphNetwork`: layers wired into an arbitrary directed acyclic
+/// graph instead of a chain.
+///
+/// Nodes can only read from nodes added before them, which rules out cycles
+/// and makes the order they were added in an evaluation order.
+///
+/// Adding a malformed node panics; deserializing a graph checks every node
+/// the same way, but returns a `NetworkError` instead. Errors about a layer
+/// refer to it by its node's index.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedGraph")]
+pub struct Graph {
+    nodes: Vec<GraphNode>,
+    outputs: Vec<NodeId>,
+}
+
+impl Graph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a group of inputs. `propagate` expects the groups one after
+    /// another, in the order they were added.
+    pub fn input(&mut self, size: usize) -> NodeId {
+        self.push(GraphNode::Input { size })
+    }
+
+    pub fn layer(&mut self, from: &[NodeId], topology: LayerTopology) -> NodeId {
+        self.push(GraphNode::Layer {
+            from: from.to_vec(),
+            topology,
+        })
+    }
+
+    pub fn add(&mut self, from: &[NodeId]) -> NodeId {
+        self.push(GraphNode::Add {
+            from: from.to_vec(),
+        })
+    }
+
+    pub fn concat(&mut self, from: &[NodeId]) -> NodeId {
+        self.push(GraphNode::Concat {
+            from: from.to_vec(),
+        })
+    }
+
+    /// Appends the node's outputs to the network's outputs.
+    pub fn output(&mut self, node: NodeId) {
+        self.try_output(node).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn nodes(&self) -> &[GraphNode] {
+        &self.nodes
+    }
+
+    /// How many values a node outputs.
+    pub fn size(&self, node: NodeId) -> usize {
+        match &self.nodes[node.0] {
+            GraphNode::Input { size } => *size,
+            GraphNode::Layer { topology, .. } => topology.neurons,
+            GraphNode::Add { from } => self.size(from[0]),
+            GraphNode::Concat { from } => self.sizes(from),
+        }
+    }
+
+    /// How many inputs the network takes, over all input groups.
+    pub fn inputs(&self) -> usize {
+        self.nodes
+            .iter()
+            .map(|node| match node {
+                GraphNode::Input { size } => *size,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    pub fn outputs(&self) -> usize {
+        self.sizes(&self.outputs)
+    }
+
+    //Every layer with the number of inputs it takes
+    fn layers(&self) -> impl Iterator<Item = (usize, &LayerTopology)> + '_ {
+        self.nodes.iter().filter_map(|node| match node {
+            GraphNode::Layer { from, topology } => Some((self.sizes(from), topology)),
+            _ => None,
+        })
+    }
+
+    fn sizes(&self, nodes: &[NodeId]) -> usize {
+        nodes.iter().map(|&node| self.size(node)).sum()
+    }
+
+    fn push(&mut self, node: GraphNode) -> NodeId {
+        self.try_push(node).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    fn try_push(&mut self, node: GraphNode) -> Result<NodeId, NetworkError> {
+        self.check(&node)?;
+        self.nodes.push(node);
+
+        Ok(NodeId(self.nodes.len() - 1))
+    }
+
+    fn try_output(&mut self, node: NodeId) -> Result<(), NetworkError> {
+        self.check_sources(&[node])?;
+        self.outputs.push(node);
+
+        Ok(())
+    }
+
+    //Checks a node that's about to be added
+    fn check(&self, node: &GraphNode) -> Result<(), NetworkError> {
+        let index = self.nodes.len();
+
+        match node {
+            GraphNode::Input { size } => {
+                if *size == 0 {
+                    return Err(NetworkError::ZeroWidthLayer { layer: index });
+                }
+            }
+            GraphNode::Layer { from, topology } => {
+                self.check_sources(from)?;
+
+                check_layer(index, self.sizes(from), topology)?;
+            }
+            GraphNode::Add { from } => {
+                self.check_sources(from)?;
+
+                let size = self.size(from[0]);
+
+                if from.iter().any(|&node| self.size(node) != size) {
+                    return Err(NetworkError::SizeMismatch { node: index });
+                }
+            }
+            GraphNode::Concat { from } => self.check_sources(from)?,
+        }
+
+        Ok(())
+    }
+
+    //Nodes can only read from nodes added before them
+    fn check_sources(&self, nodes: &[NodeId]) -> Result<(), NetworkError> {
+        if nodes.is_empty() {
+            return Err(NetworkError::NoSources {
+                node: self.nodes.len(),
+            });
+        }
+
+        match nodes.iter().find(|node| node.0 >= self.nodes.len()) {
+            Some(node) => Err(NetworkError::UnknownNode { node: node.0 }),
+            None => Ok(()),
+        }
+    }
+}
+
+//What a `Graph` is deserialized from, before it's checked
+#[derive(Deserialize)]
+struct UncheckedGraph {
+    nodes: Vec<GraphNode>,
+    outputs: Vec<NodeId>,
+}
+
+impl TryFrom<UncheckedGraph> for Graph {
+    type Error = NetworkError;
+
+    fn try_from(unchecked: UncheckedGraph) -> Result<Self, NetworkError> {
+        let mut graph = Self::new();
+
+        for node in unchecked.nodes {
+            graph.try_push(node)?;
+        }
+
+        for node in unchecked.outputs {
+            graph.try_output(node)?;
+        }
+
+        Ok(graph)
+    }
+}
+
+/// Network shaped like a `Graph`, with skip connections, concatenations and
+/// several input groups.
+///
+/// Like `Network`, its weights flatten into a single vector: every layer's
+/// weights, in the order the layers were added to the graph.
+#[derive(Clone, Debug)]
+pub struct GraphNetwork {
+    graph: Graph,
+    //One per layer node, in order
+    layers: Vec<Layer>,
+    //Every node's latest outputs, reused between calls
+    values: Vec<Vec<f32>>,
+    joined: Vec<f32>,
+}
+
+impl GraphNetwork {
+    pub fn from_weights(graph: &Graph, weights: impl IntoIterator<Item = f32>) -> Self {
+        Self::try_from_weights(graph, weights).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_from_weights(
+        graph: &Graph,
+        weights: impl IntoIterator<Item = f32>,
+    ) -> Result<Self, NetworkError> {
+        if graph.outputs.is_empty() {
+            return Err(NetworkError::NoOutputs);
+        }
+
+        let weights: Vec<f32> = weights.into_iter().collect();
+
+        let expected = graph
+            .layers()
+            .try_fold(0usize, |total, (inputs, topology)| {
+                total.checked_add(Layer::weights_len(inputs, topology)?)
+            })
+            .ok_or(NetworkError::TopologyTooLarge)?;
+
+        check_weights_len(expected, &weights)?;
+
+        let mut weights = weights.into_iter();
+
+        let layers = graph
+            .layers()
+            .enumerate()
+            .map(|(index, (inputs, topology))| {
+                Layer::from_weights(index, inputs, topology, &mut weights)
+            })
+            .collect();
+
+        Ok(Self::new(graph, layers))
+    }
+
+    /// Propagates the input groups, laid out one after another, and returns
+    /// the outputs of the graph's output nodes, one after another.
+    ///
+    /// Recurrent layers update their state on every call, just like in
+    /// `Network::propagate`.
+    pub fn propagate(&mut self, inputs: Vec<f32>) -> Vec<f32> {
+        assert_eq!(inputs.len(), self.graph.inputs());
+
+        let mut inputs = inputs.as_slice();
+        let mut layers = self.layers.iter_mut();
+
+        for (index, node) in self.graph.nodes.iter().enumerate() {
+            //Taken out, so that the other nodes' values can be read meanwhile
+            let mut values = core::mem::take(&mut self.values[index]);
+            values.clear();
+
+            match node {
+                GraphNode::Input { size } => {
+                    let (group, rest) = inputs.split_at(*size);
+                    values.extend_from_slice(group);
+                    inputs = rest;
+                }
+                GraphNode::Layer { from, .. } => {
+                    join(&self.values, from, &mut self.joined);
+
+                    layers
+                        .next()
+                        .unwrap()
+                        .propagate_into(&self.joined, &mut values);
+                }
+                GraphNode::Add { from } => {
+                    values.extend_from_slice(&self.values[from[0].0]);
+
+                    for node in &from[1..] {
+                        for (value, other) in values.iter_mut().zip(&self.values[node.0]) {
+                            *value += other;
+                        }
+                    }
+                }
+                GraphNode::Concat { from } => join(&self.values, from, &mut values),
+            }
+
+            self.values[index] = values;
+        }
+
+        let mut outputs = Vec::new();
+        join(&self.values, &self.graph.outputs, &mut outputs);
+        outputs
+    }
+
+    /// Makes recurrent layers forget everything they've seen so far.
+    pub fn reset_state(&mut self) {
+        for layer in &mut self.layers {
+            layer.reset_state();
+        }
+    }
+
+    /// Like `Network::set_mode`.
+    pub fn set_mode(&mut self, mode: Mode) {
+        for layer in &mut self.layers {
+            layer.set_mode(mode);
+        }
+    }
+
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    pub fn weights(&self) -> Vec<f32> {
+        self.layers
+            .iter()
+            .flat_map(|layer| layer.weights())
+            .copied()
+            .collect()
+    }
+
+    fn new(graph: &Graph, layers: Vec<Layer>) -> Self {
+        Self {
+            graph: graph.clone(),
+            layers,
+            values: vec![Vec::new(); graph.nodes.len()],
+            joined: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "rand")]
+impl GraphNetwork {
+    pub fn random_with(rng: &mut dyn RngCore, graph: &Graph, init: Init) -> Self {
+        assert!(!graph.outputs.is_empty(), "{}", NetworkError::NoOutputs);
+
+        let layers = graph
+            .layers()
+            .map(|(inputs, topology)| Layer::random(rng, inputs, topology, init))
+            .collect();
+
+        Self::new(graph, layers)
+    }
+}
+
+//Writes the outputs of `nodes` into `joined`, one after another
+fn join(values: &[Vec<f32>], nodes: &[NodeId], joined: &mut Vec<f32>) {
+    joined.clear();
+
+    for node in nodes {
+        joined.extend_from_slice(&values[node.0]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_residual() {
+        let mut graph = Graph::new();
+        let input = graph.input(2);
+        let hidden = graph.layer(&[input], LayerTopology::new(2));
+        let sum = graph.add(&[input, hidden]);
+        graph.output(sum);
+
+        #[rustfmt::skip]
+        let weights = [
+            0.0, 1.0, 0.0,
+            0.5, 0.0, -1.0,
+        ];
+
+        let mut network = GraphNetwork::from_weights(&graph, weights);
+
+        //Hidden = relu([x, 0.5 - y]), which is added onto the input
+        assert_relative_eq!(
+            network.propagate(vec![1.0, 2.0]).as_slice(),
+            [2.0, 2.0].as_slice()
+        );
+    }
+
+    #[test]
+    fn test_multiple_inputs() {
+        let mut graph = Graph::new();
+        let vision = graph.input(3);
+        let state = graph.input(1);
+        let seen = graph.layer(
+            &[vision],
+            LayerTopology::new(1).with_activation(Activation::Identity),
+        );
+        let output = graph.layer(
+            &[seen, state],
+            LayerTopology::new(1).with_activation(Activation::Identity),
+        );
+        graph.output(output);
+        graph.output(seen);
+
+        assert_eq!((graph.inputs(), graph.outputs()), (4, 2));
+
+        #[rustfmt::skip]
+        let weights = [
+            0.0, 1.0, 1.0, 1.0,
+            0.5, 2.0, -1.0,
+        ];
+
+        let mut network = GraphNetwork::from_weights(&graph, weights);
+
+        //seen = 1 + 2 + 3, output = 0.5 + 2 * 6 - 4
+        assert_relative_eq!(
+            network.propagate(vec![1.0, 2.0, 3.0, 4.0]).as_slice(),
+            [8.5, 6.0].as_slice()
+        );
+    }
+
+    #[test]
+    fn test_weights_round_trip() {
+        let mut graph = Graph::new();
+        let a = graph.input(3);
+        let b = graph.input(2);
+        let joined = graph.concat(&[a, b]);
+        let hidden = graph.layer(&[joined], LayerTopology::new(4).with_kind(LayerKind::Gru));
+        let skip = graph.layer(&[hidden, a], LayerTopology::new(2));
+        graph.output(skip);
+
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut network = GraphNetwork::random_with(&mut rng, &graph, Init::default());
+        let mut copy = GraphNetwork::from_weights(&graph, network.weights());
+
+        assert_eq!(copy.weights(), network.weights());
+
+        for inputs in [[0.1, 0.2, 0.3, 0.4, 0.5], [0.5, -0.4, 0.3, -0.2, 0.1]] {
+            assert_eq!(
+                copy.propagate(inputs.to_vec()),
+                network.propagate(inputs.to_vec())
+            );
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut graph = Graph::new();
+        let input = graph.input(2);
+        let output = graph.layer(&[input], LayerTopology::new(1));
+
+        assert_eq!(
+            GraphNetwork::try_from_weights(&graph, vec![]).unwrap_err(),
+            NetworkError::NoOutputs
+        );
+
+        graph.output(output);
+
+        assert_eq!(
+            GraphNetwork::try_from_weights(&graph, vec![0.0; 2]).unwrap_err(),
+            NetworkError::TooFewWeights {
+                expected: 3,
+                found: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let mut graph = Graph::new();
+        let input = graph.input(2);
+        let hidden = graph.layer(&[input], LayerTopology::new(2));
+        let sum = graph.add(&[input, hidden]);
+        graph.output(sum);
+
+        let json = serde_json::to_string(&graph).unwrap();
+        assert_eq!(serde_json::from_str::<Graph>(&json).unwrap(), graph);
+
+        let input = r#"{"Input":{"size":2}}"#;
+        let layer = |from: &str, neurons: usize| {
+            format!(
+                r#"{{"Layer":{{"from":{from},"topology":{}}}}}"#,
+                serde_json::to_string(&LayerTopology::new(neurons)).unwrap()
+            )
+        };
+        let dropout = format!(
+            r#"{{"Layer":{{"from":[0],"topology":{}}}}}"#,
+            serde_json::to_string(&LayerTopology::new(2).with_kind(LayerKind::Dropout(1.5)))
+                .unwrap()
+        );
+
+        for (nodes, outputs, err) in [
+            (
+                format!("[{input}]"),
+                "[3]",
+                NetworkError::UnknownNode { node: 3 },
+            ),
+            (
+                format!("[{input},{}]", layer("[1]", 2)),
+                "[1]",
+                NetworkError::UnknownNode { node: 1 },
+            ),
+            (
+                format!("[{input},{}]", layer("[]", 2)),
+                "[1]",
+                NetworkError::NoSources { node: 1 },
+            ),
+            (
+                format!("[{input},{}]", layer("[0]", 0)),
+                "[1]",
+                NetworkError::ZeroWidthLayer { layer: 1 },
+            ),
+            (
+                format!(
+                    r#"[{input},{},{{"Add":{{"from":[0,1]}}}}]"#,
+                    layer("[0]", 3)
+                ),
+                "[2]",
+                NetworkError::SizeMismatch { node: 2 },
+            ),
+            (
+                format!("[{input},{dropout}]"),
+                "[1]",
+                NetworkError::InvalidDropout { layer: 1 },
+            ),
+        ] {
+            let json = format!(r#"{{"nodes":{nodes},"outputs":{outputs}}}"#);
+
+            let found = serde_json::from_str::<Graph>(&json).unwrap_err();
+            assert!(found.to_string().starts_with(&err.to_string()), "{found}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "dropout rate of layer 1 isn't in [0, 1)")]
+    fn test_dropout_rate() {
+        let mut graph = Graph::new();
+        let input = graph.input(2);
+
+        graph.layer(
+            &[input],
+            LayerTopology::new(2).with_kind(LayerKind::Dropout(1.0)),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "activation parameter of layer 1 isn't finite")]
+    fn test_invalid_activation() {
+        let mut graph = Graph::new();
+        let input = graph.input(2);
+
+        graph.layer(
+            &[input],
+            LayerTopology::new(1).with_activation(Activation::LeakyRelu(f32::NAN)),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "nodes added by node 2 differ in size")]
+    fn test_add_mismatch() {
+        let mut graph = Graph::new();
+        let a = graph.input(2);
+        let b = graph.input(3);
+
+        graph.add(&[a, b]);
+    }
+}
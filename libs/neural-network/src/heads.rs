@@ -0,0 +1,281 @@
+use crate::*;
+use core::ops::Range;
+
+/// How a `Head` turns its share of a network's outputs into values.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum HeadKind {
+    /// Outputs are passed through as they are.
+    Linear,
+    /// Outputs become probabilities summing up to 1, e.g. to pick one of
+    /// several discrete actions with `HeadValues::choice`.
+    Softmax,
+    /// Every output goes through tanh, scaled from (-1, 1) to (min, max).
+    Bounded { min: f32, max: f32 },
+}
+
+/// Named group of consecutive outputs of a network.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Head {
+    pub name: String,
+    pub size: usize,
+    pub kind: HeadKind,
+}
+
+/// Splits a network's outputs into named `Head`s, in order, so that callers
+/// don't have to know which index means what.
+///
+/// The network's output layer should be linear (`Activation::Identity`),
+/// since heads apply their own non-linearities.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedHeads")]
+pub struct Heads {
+    heads: Vec<Head>,
+}
+
+impl Heads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a head reading the `size` outputs after the previous heads'.
+    pub fn with_head(self, name: impl Into<String>, size: usize, kind: HeadKind) -> Self {
+        self.try_with_head(name, size, kind)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Like `with_head`, but fails instead of panicking if there's already a
+    /// head with the same name.
+    pub fn try_with_head(
+        mut self,
+        name: impl Into<String>,
+        size: usize,
+        kind: HeadKind,
+    ) -> Result<Self, NetworkError> {
+        let name = name.into();
+
+        if self.find(&name).is_some() {
+            return Err(NetworkError::DuplicateHead { name });
+        }
+
+        self.heads.push(Head { name, size, kind });
+        Ok(self)
+    }
+
+    pub fn heads(&self) -> &[Head] {
+        &self.heads
+    }
+
+    /// How many outputs the network has to have.
+    pub fn outputs(&self) -> usize {
+        self.heads.iter().map(|head| head.size).sum()
+    }
+
+    /// Topology of an output layer matching these heads.
+    pub fn topology(&self) -> LayerTopology {
+        LayerTopology::new(self.outputs()).with_activation(Activation::Identity)
+    }
+
+    /// Applies every head to the network's raw `outputs`, writing the values
+    /// into `values` so that the buffer can be reused between calls.
+    pub fn apply_into<'a>(&'a self, outputs: &[f32], values: &'a mut Vec<f32>) -> HeadValues<'a> {
+        assert_eq!(outputs.len(), self.outputs());
+
+        values.clear();
+
+        for (head, range) in self.heads.iter().zip(self.ranges()) {
+            let outputs = &outputs[range];
+
+            match head.kind {
+                HeadKind::Linear => values.extend_from_slice(outputs),
+                HeadKind::Softmax => {
+                    //Shifting by the maximum keeps `exp` from overflowing
+                    let max = outputs.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                    let start = values.len();
+
+                    values.extend(outputs.iter().map(|&output| (output - max).exp()));
+
+                    let sum: f32 = values[start..].iter().sum();
+                    values[start..].iter_mut().for_each(|value| *value /= sum);
+                }
+                HeadKind::Bounded { min, max } => values.extend(
+                    outputs
+                        .iter()
+                        .map(|&output| min + (max - min) * (output.tanh() + 1.0) / 2.0),
+                ),
+            }
+        }
+
+        HeadValues {
+            heads: self,
+            values,
+        }
+    }
+
+    fn find(&self, name: &str) -> Option<(&Head, Range<usize>)> {
+        self.heads
+            .iter()
+            .zip(self.ranges())
+            .find(|(head, _)| head.name == name)
+    }
+
+    fn ranges(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.heads.iter().scan(0, |start, head| {
+            let range = *start..*start + head.size;
+            *start = range.end;
+            Some(range)
+        })
+    }
+}
+
+/// Values of all heads, as computed by `Heads::apply_into`.
+#[derive(Clone, Copy, Debug)]
+pub struct HeadValues<'a> {
+    heads: &'a Heads,
+    values: &'a [f32],
+}
+
+impl<'a> HeadValues<'a> {
+    /// Values of the head with the given name.
+    pub fn get(&self, name: &str) -> &'a [f32] {
+        let (_, range) = self
+            .heads
+            .find(name)
+            .unwrap_or_else(|| panic!("head {name} doesn't exist"));
+
+        &self.values[range]
+    }
+
+    /// Value of a head with a single output.
+    pub fn value(&self, name: &str) -> f32 {
+        match self.get(name) {
+            [value] => *value,
+            values => panic!("head {name} has {} outputs, not one", values.len()),
+        }
+    }
+
+    /// Index of the head's largest value, e.g. the most likely action of a
+    /// softmax head.
+    pub fn choice(&self, name: &str) -> usize {
+        self.get(name)
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+            .unwrap_or_else(|| panic!("head {name} is empty"))
+    }
+
+    /// Every head's values, in order.
+    pub fn values(&self) -> &'a [f32] {
+        self.values
+    }
+}
+
+//What `Heads` are deserialized from, before they're checked
+#[derive(Deserialize)]
+struct UncheckedHeads {
+    heads: Vec<Head>,
+}
+
+impl TryFrom<UncheckedHeads> for Heads {
+    type Error = NetworkError;
+
+    fn try_from(unchecked: UncheckedHeads) -> Result<Self, NetworkError> {
+        unchecked
+            .heads
+            .into_iter()
+            .try_fold(Self::new(), |heads, head| {
+                heads.try_with_head(head.name, head.size, head.kind)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn heads() -> Heads {
+        Heads::new()
+            .with_head("action", 3, HeadKind::Softmax)
+            .with_head("speed", 1, HeadKind::Bounded { min: 0.0, max: 2.0 })
+            .with_head("raw", 2, HeadKind::Linear)
+    }
+
+    #[test]
+    fn test_apply() {
+        let heads = heads();
+        let mut buffer = Vec::new();
+
+        assert_eq!(heads.outputs(), 6);
+
+        let values = heads.apply_into(&[1.0, 3.0, 1.0, 0.0, -4.0, 5.0], &mut buffer);
+
+        let action = values.get("action");
+        assert_relative_eq!(action.iter().sum::<f32>(), 1.0);
+        assert_relative_eq!(action[1] / action[0], 2.0f32.exp(), max_relative = 1e-5);
+        assert_eq!(values.choice("action"), 1);
+
+        //tanh(0) is halfway between the bounds
+        assert_relative_eq!(values.value("speed"), 1.0);
+        assert_eq!(values.get("raw"), [-4.0, 5.0]);
+        assert_eq!(values.values().len(), 6);
+    }
+
+    #[test]
+    fn test_softmax_is_stable() {
+        let heads = Heads::new().with_head("action", 2, HeadKind::Softmax);
+        let mut buffer = Vec::new();
+
+        let values = heads.apply_into(&[1000.0, 1000.0], &mut buffer);
+
+        assert_eq!(values.get("action"), [0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_bounded() {
+        let heads = Heads::new().with_head(
+            "turn",
+            2,
+            HeadKind::Bounded {
+                min: -3.0,
+                max: 3.0,
+            },
+        );
+        let mut buffer = Vec::new();
+
+        let values = heads.apply_into(&[100.0, -0.5], &mut buffer);
+
+        assert_relative_eq!(values.get("turn")[0], 3.0);
+        assert_relative_eq!(values.get("turn")[1], 3.0 * (-0.5f32).tanh());
+    }
+
+    #[test]
+    #[should_panic(expected = "head speed has 2 outputs, not one")]
+    fn test_value_of_wide_head() {
+        let heads = Heads::new().with_head("speed", 2, HeadKind::Linear);
+        let mut buffer = Vec::new();
+
+        heads.apply_into(&[0.0, 0.0], &mut buffer).value("speed");
+    }
+
+    #[test]
+    #[should_panic(expected = "head speed already exists")]
+    fn test_duplicate_head() {
+        Heads::new()
+            .with_head("speed", 1, HeadKind::Linear)
+            .with_head("speed", 2, HeadKind::Softmax);
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let json = serde_json::to_string(&heads()).unwrap();
+        assert_eq!(serde_json::from_str::<Heads>(&json).unwrap(), heads());
+
+        let duplicated = r#"{"heads":[{"name":"speed","size":1,"kind":"Linear"},{"name":"speed","size":1,"kind":"Linear"}]}"#;
+
+        assert!(serde_json::from_str::<Heads>(duplicated)
+            .unwrap_err()
+            .to_string()
+            .starts_with("head speed already exists"));
+    }
+}
@@ -0,0 +1,318 @@
+use crate::*;
+
+//Coefficients of a learning rule: η, A, B, C and D
+const RULE_LEN: usize = 5;
+
+/// Which connections of a `LayerKind::Hebbian` layer share a learning rule.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Plasticity {
+    /// One rule for the whole layer.
+    #[default]
+    PerLayer,
+    /// Every connection has a rule of its own.
+    PerConnection,
+}
+
+impl Plasticity {
+    //`None` if the count doesn't fit in a `usize`
+    fn rules(&self, inputs: usize, neurons: usize) -> Option<usize> {
+        match self {
+            Self::PerLayer => Some(1),
+            Self::PerConnection => inputs.checked_mul(neurons),
+        }
+    }
+}
+
+/// Dense layer whose weights keep changing while it propagates, following
+/// the "ABCD" Hebbian rule: after every step, the weight between input `x`
+/// and neuron output `y` changes by `η * (A*x*y + B*x + C*y + D)`.
+///
+/// The weights hold the starting matrix (laid out like a dense layer's)
+/// followed by the rules' coefficients, η first, so that evolution tunes how
+/// the layer learns rather than what it knows. What the layer has learned so
+/// far is its state: it's forgotten on `reset_state`. Biases don't change.
+#[derive(Clone, Debug)]
+pub(crate) struct Hebbian<T = f32> {
+    inputs: usize,
+    neurons: usize,
+    plasticity: Plasticity,
+    weights: Vec<T>,
+    activation: Activation,
+    //How far each connection has moved from its starting weight
+    deltas: Vec<T>,
+}
+
+impl<T: Scalar> Hebbian<T> {
+    /// How far learning can move a weight from where it started, so that
+    /// rules that keep strengthening a connection can't blow it up.
+    const MAX_DELTA: f32 = 1.0;
+
+    pub(crate) fn new(
+        inputs: usize,
+        neurons: usize,
+        plasticity: Plasticity,
+        weights: Vec<T>,
+        activation: Activation,
+    ) -> Self {
+        assert_eq!(
+            Some(weights.len()),
+            Self::weights_len(inputs, neurons, plasticity)
+        );
+
+        Self {
+            inputs,
+            neurons,
+            plasticity,
+            weights,
+            activation,
+            deltas: vec![T::ZERO; inputs * neurons],
+        }
+    }
+
+    /// `None` if the count doesn't fit in a `usize`.
+    pub(crate) fn weights_len(
+        inputs: usize,
+        neurons: usize,
+        plasticity: Plasticity,
+    ) -> Option<usize> {
+        let rules = plasticity.rules(inputs, neurons)?;
+
+        inputs
+            .checked_add(1)?
+            .checked_mul(neurons)?
+            .checked_add(rules.checked_mul(RULE_LEN)?)
+    }
+
+    pub(crate) fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    pub(crate) fn neurons(&self) -> usize {
+        self.neurons
+    }
+
+    pub(crate) fn plasticity(&self) -> Plasticity {
+        self.plasticity
+    }
+
+    pub(crate) fn activation(&self) -> Activation {
+        self.activation
+    }
+
+    pub(crate) fn weights(&self) -> &[T] {
+        &self.weights
+    }
+
+    pub(crate) fn weights_mut(&mut self) -> &mut [T] {
+        &mut self.weights
+    }
+
+    /// The given neuron's bias followed by its starting weights.
+    pub(crate) fn row(&self, neuron: usize) -> &[T] {
+        &self.weights[neuron * (self.inputs + 1)..][..self.inputs + 1]
+    }
+
+    /// Length of the starting matrix, before the rules.
+    pub(crate) fn rows_len(&self) -> usize {
+        self.neurons * (self.inputs + 1)
+    }
+
+    pub(crate) fn reset_state(&mut self) {
+        self.deltas.fill(T::ZERO);
+    }
+
+    pub(crate) fn propagate_into(&mut self, inputs: &[T], outputs: &mut Vec<T>) {
+        self.step(inputs, None, outputs);
+    }
+
+    pub(crate) fn trace_into(
+        &mut self,
+        inputs: &[T],
+        pre_activations: &mut Vec<T>,
+        outputs: &mut Vec<T>,
+    ) {
+        pre_activations.clear();
+        self.step(inputs, Some(pre_activations), outputs);
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[T],
+        mut pre_activations: Option<&mut Vec<T>>,
+        outputs: &mut Vec<T>,
+    ) {
+        assert_eq!(inputs.len() % self.inputs, 0);
+
+        outputs.clear();
+
+        for inputs in inputs.chunks_exact(self.inputs) {
+            let start = outputs.len();
+
+            for neuron in 0..self.neurons {
+                let row = self.row(neuron);
+                let deltas = &self.deltas[neuron * self.inputs..][..self.inputs];
+
+                let sum = row[0]
+                    + row[1..]
+                        .iter()
+                        .zip(deltas)
+                        .zip(inputs)
+                        .map(|((&weight, &delta), &input)| (weight + delta) * input)
+                        .sum();
+
+                if let Some(pre_activations) = pre_activations.as_deref_mut() {
+                    pre_activations.push(sum);
+                }
+
+                outputs.push(self.activation.apply(sum));
+            }
+
+            //Learns from this step, so that the next one sees the new weights
+            self.learn(inputs, &outputs[start..]);
+        }
+    }
+
+    fn learn(&mut self, inputs: &[T], outputs: &[T]) {
+        let limit = T::from_f32(Self::MAX_DELTA);
+
+        for (neuron, &output) in outputs.iter().enumerate() {
+            for (input_idx, &input) in inputs.iter().enumerate() {
+                let connection = neuron * self.inputs + input_idx;
+
+                let &[eta, a, b, c, d] = self.rule(connection) else {
+                    unreachable!()
+                };
+
+                let delta = self.deltas[connection]
+                    + eta * (a * input * output + b * input + c * output + d);
+
+                self.deltas[connection] = if delta > limit {
+                    limit
+                } else {
+                    delta.max(-limit)
+                };
+            }
+        }
+    }
+
+    fn rule(&self, connection: usize) -> &[T] {
+        let rule = match self.plasticity {
+            Plasticity::PerLayer => 0,
+            Plasticity::PerConnection => connection,
+        };
+
+        &self.weights[self.rows_len() + rule * RULE_LEN..][..RULE_LEN]
+    }
+}
+
+//Random weights are always drawn as f32, see `Network::cast`
+impl Hebbian {
+    /// Starting weights come from `init`; the rules' coefficients are drawn
+    /// from [-1, 1], whatever the scheme.
+    #[cfg(feature = "rand")]
+    pub(crate) fn random(
+        rng: &mut dyn RngCore,
+        inputs: usize,
+        neurons: usize,
+        plasticity: Plasticity,
+        activation: Activation,
+        init: Init,
+    ) -> Self {
+        let rules = RULE_LEN
+            * plasticity
+                .rules(inputs, neurons)
+                .expect("topology is checked beforehand");
+
+        let weights = init
+            .weights(rng, inputs, neurons)
+            .into_iter()
+            .chain((0..rules).map(|_| rng.gen_range(-1.0..=1.0)))
+            .collect();
+
+        Self::new(inputs, neurons, plasticity, weights, activation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn propagate(layer: &mut Hebbian, inputs: &[f32]) -> Vec<f32> {
+        let mut outputs = Vec::new();
+        layer.propagate_into(inputs, &mut outputs);
+        outputs
+    }
+
+    #[test]
+    fn test_learns_between_steps() {
+        //w = 0.5, plain Hebb (A = 1) with η = 0.1
+        let weights = vec![0.0, 0.5, 0.1, 1.0, 0.0, 0.0, 0.0];
+        let mut layer = Hebbian::new(1, 1, Plasticity::PerLayer, weights, Activation::Identity);
+
+        assert_relative_eq!(propagate(&mut layer, &[2.0])[0], 1.0);
+        //Δw = 0.1 * 2 * 1
+        assert_relative_eq!(propagate(&mut layer, &[2.0])[0], 1.4);
+
+        //The starting weights don't change, only the state does
+        assert_eq!(layer.weights()[1], 0.5);
+
+        layer.reset_state();
+        assert_relative_eq!(propagate(&mut layer, &[2.0])[0], 1.0);
+    }
+
+    #[test]
+    fn test_per_connection() {
+        #[rustfmt::skip]
+        let weights = vec![
+            0.0, 1.0, 1.0,
+            //First connection only decays (D = -1), the second never changes
+            0.5, 0.0, 0.0, 0.0, -1.0,
+            0.0, 1.0, 1.0, 1.0, 1.0,
+        ];
+
+        let mut layer = Hebbian::new(
+            2,
+            1,
+            Plasticity::PerConnection,
+            weights,
+            Activation::Identity,
+        );
+
+        assert_relative_eq!(propagate(&mut layer, &[1.0, 1.0])[0], 2.0);
+        assert_relative_eq!(propagate(&mut layer, &[1.0, 1.0])[0], 1.5);
+        //A batch is a sequence of steps, too
+        assert_relative_eq!(
+            propagate(&mut layer, &[1.0, 1.0, 1.0, 1.0]).as_slice(),
+            //The first weight bottoms out at 1 - MAX_DELTA
+            [1.0, 1.0].as_slice()
+        );
+    }
+
+    #[test]
+    fn test_in_network() {
+        let topology = [
+            LayerTopology::new(3),
+            LayerTopology::new(4).with_kind(LayerKind::Hebbian(Plasticity::PerConnection)),
+            LayerTopology::new(2).with_activation(Activation::Tanh),
+        ];
+
+        let mut network = Network::random(&topology);
+        assert_eq!(network.topology(), topology);
+        assert_eq!(network.weights().len(), 4 * 4 + 5 * 12 + 2 * 5);
+
+        let mut copy = Network::from_weights(&topology, network.weights());
+        let inputs = vec![0.3, -0.6, 0.9];
+
+        let first = network.propagate(inputs.clone());
+        assert_eq!(copy.propagate(inputs.clone()), first);
+
+        //Both learn the same way, but keep the weights they started with
+        let second = network.propagate(inputs.clone());
+        assert_eq!(copy.propagate(inputs.clone()), second);
+        assert_eq!(copy.weights(), network.weights());
+
+        network.reset_state();
+        assert_eq!(network.propagate(inputs), first);
+    }
+}
@@ -0,0 +1,131 @@
+#[cfg(feature = "rand")]
+use crate::*;
+
+/// How `Network::random_with` picks the starting weights.
+///
+/// All schemes draw from a uniform distribution; they only differ in its
+/// range. With `zero_bias` set, every bias starts at exactly zero instead of
+/// being drawn from that same range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Init {
+    /// Everything in [-1, 1], which is what `Network::random` uses.
+    Uniform { zero_bias: bool },
+    /// Glorot & Bengio: [-l, l] with l = sqrt(6 / (inputs + outputs)), which
+    /// keeps sigmoid and tanh layers away from saturation.
+    Xavier { zero_bias: bool },
+    /// He et al.: [-l, l] with l = sqrt(6 / inputs), suited for ReLU layers.
+    He { zero_bias: bool },
+}
+
+impl Default for Init {
+    fn default() -> Self {
+        Self::Uniform { zero_bias: false }
+    }
+}
+
+#[cfg(feature = "rand")]
+impl Init {
+    fn limit(&self, inputs: usize, outputs: usize) -> f32 {
+        match self {
+            Self::Uniform { .. } => 1.0,
+            Self::Xavier { .. } => (6.0 / (inputs + outputs) as f32).sqrt(),
+            Self::He { .. } => (6.0 / inputs as f32).sqrt(),
+        }
+    }
+
+    fn zero_bias(&self) -> bool {
+        match *self {
+            Self::Uniform { zero_bias } | Self::Xavier { zero_bias } | Self::He { zero_bias } => {
+                zero_bias
+            }
+        }
+    }
+
+    /// Draws a layer's weights in the layout of `Network::weights()`.
+    pub(crate) fn weights(&self, rng: &mut dyn RngCore, inputs: usize, outputs: usize) -> Vec<f32> {
+        let limit = self.limit(inputs, outputs);
+        let mut weights = Vec::with_capacity(outputs * (inputs + 1));
+
+        for _ in 0..outputs {
+            if self.zero_bias() {
+                weights.push(0.0);
+            } else {
+                weights.push(rng.gen_range(-limit..=limit));
+            }
+
+            weights.extend((0..inputs).map(|_| rng.gen_range(-limit..=limit)));
+        }
+
+        weights
+    }
+}
+
+#[cfg(feature = "rand")]
+impl Network {
+    /// Like `random`, but reproducible from the given `rng`.
+    pub fn random_with(rng: &mut dyn RngCore, layers: &[LayerTopology], init: Init) -> Self {
+        Self::try_random_with(rng, layers, init).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_random_with(
+        rng: &mut dyn RngCore,
+        layers: &[LayerTopology],
+        init: Init,
+    ) -> Result<Self, NetworkError> {
+        //Ensure that there is more than one layer, and none of them is empty
+        check_topology(layers)?;
+
+        let layers = layers
+            .windows(2)
+            .map(|layers| Layer::random(rng, layers[0].neurons, &layers[1], init))
+            .collect();
+
+        Ok(Self { layers })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn topology() -> [LayerTopology; 3] {
+        [
+            LayerTopology::new(20),
+            LayerTopology::new(10),
+            LayerTopology::new(2),
+        ]
+    }
+
+    #[test]
+    fn test_same_seed_same_network() {
+        let init = Init::He { zero_bias: false };
+
+        let a = Network::random_with(&mut ChaCha8Rng::seed_from_u64(7), &topology(), init);
+        let b = Network::random_with(&mut ChaCha8Rng::seed_from_u64(7), &topology(), init);
+
+        assert_eq!(a.weights(), b.weights());
+    }
+
+    #[test]
+    fn test_xavier_range() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let weights = Init::Xavier { zero_bias: false }.weights(&mut rng, 20, 10);
+        let limit = (6.0f32 / 30.0).sqrt();
+
+        assert_eq!(weights.len(), 10 * 21);
+        assert!(weights.iter().all(|weight| weight.abs() <= limit));
+    }
+
+    #[test]
+    fn test_zero_bias() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let weights = Init::He { zero_bias: true }.weights(&mut rng, 4, 3);
+
+        for row in weights.chunks(5) {
+            assert_eq!(row[0], 0.0);
+            assert!(row[1..].iter().all(|&weight| weight != 0.0));
+        }
+    }
+}
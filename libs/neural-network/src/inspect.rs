@@ -0,0 +1,174 @@
+use crate::*;
+
+/// Everything `Network::propagate_traced` computed on the way through the
+/// network.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Trace {
+    pub inputs: Vec<f32>,
+    /// One entry per layer after the input layer.
+    pub layers: Vec<LayerTrace>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LayerTrace {
+    /// Every neuron's weighted sum, before the activation. For GRU layers,
+    /// that's the sum of the candidate state.
+    pub pre_activations: Vec<f32>,
+    pub outputs: Vec<f32>,
+}
+
+impl Trace {
+    /// What `propagate` would have returned.
+    pub fn outputs(&self) -> &[f32] {
+        self.layers
+            .last()
+            .map_or(&self.inputs, |layer| &layer.outputs)
+    }
+}
+
+/// Read-only view of one of a `Network`'s layers.
+#[derive(Clone, Copy, Debug)]
+pub struct LayerView<'a> {
+    layer: &'a Layer,
+}
+
+impl LayerView<'_> {
+    pub fn topology(&self) -> LayerTopology {
+        self.layer.topology()
+    }
+
+    pub fn inputs(&self) -> usize {
+        self.layer.inputs()
+    }
+
+    pub fn neurons(&self) -> usize {
+        self.layer.topology().neurons
+    }
+
+    pub fn bias(&self, neuron: usize) -> f32 {
+        self.layer.bias(neuron)
+    }
+
+    /// Weight of the connection from the previous layer's `input` to
+    /// `neuron`. For GRU layers, that's the weight of the candidate state;
+    /// for convolutional ones, it's zero for inputs outside of the neuron's
+    /// kernel. Normalization and dropout layers only connect a neuron to the
+    /// input at the same index, with γ (or 1) as its weight.
+    pub fn weight(&self, neuron: usize, input: usize) -> f32 {
+        self.layer.weight(neuron, input)
+    }
+
+    /// This layer's share of `Network::weights()`.
+    pub fn weights(&self) -> &[f32] {
+        self.layer.weights()
+    }
+}
+
+impl Network {
+    /// Like `propagate`, but records what every layer computed.
+    pub fn propagate_traced(&mut self, inputs: Vec<f32>) -> Trace {
+        assert_eq!(inputs.len(), self.layers[0].inputs());
+
+        let mut layers: Vec<LayerTrace> = Vec::with_capacity(self.layers.len());
+
+        for layer in &mut self.layers {
+            let inputs = layers.last().map_or(&inputs, |previous| &previous.outputs);
+            let mut trace = LayerTrace::default();
+
+            layer.trace_into(inputs, &mut trace.pre_activations, &mut trace.outputs);
+            layers.push(trace);
+        }
+
+        Trace { inputs, layers }
+    }
+
+    /// Layers after the input layer, in order.
+    pub fn layers(&self) -> impl ExactSizeIterator<Item = LayerView<'_>> {
+        self.layers.iter().map(|layer| LayerView { layer })
+    }
+
+    /// The layer at `index`, counting from the first layer after the input
+    /// layer.
+    pub fn layer(&self, index: usize) -> LayerView<'_> {
+        LayerView {
+            layer: &self.layers[index],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn network() -> Network {
+        let topology = [
+            LayerTopology::new(2),
+            LayerTopology::new(2),
+            LayerTopology::new(1).with_activation(Activation::Tanh),
+        ];
+
+        #[rustfmt::skip]
+        let weights = [
+            0.0, 1.0, -1.0,
+            0.5, 0.5, 0.5,
+            0.1, 1.0, 2.0,
+        ];
+
+        Network::from_weights(&topology, weights)
+    }
+
+    #[test]
+    fn test_propagate_traced() {
+        let mut network = network();
+        let trace = network.propagate_traced(vec![1.0, 2.0]);
+
+        assert_eq!(trace.inputs, [1.0, 2.0]);
+        assert_eq!(trace.layers.len(), 2);
+
+        //The ReLU cuts the first neuron off
+        assert_relative_eq!(
+            trace.layers[0].pre_activations.as_slice(),
+            [-1.0, 2.0].as_slice()
+        );
+        assert_relative_eq!(trace.layers[0].outputs.as_slice(), [0.0, 2.0].as_slice());
+
+        assert_relative_eq!(trace.layers[1].pre_activations[0], 4.1);
+        assert_relative_eq!(trace.outputs()[0], 4.1f32.tanh());
+        assert_eq!(trace.outputs(), network.propagate(vec![1.0, 2.0]));
+    }
+
+    #[test]
+    fn test_traced_recurrent() {
+        let topology = [
+            LayerTopology::new(2),
+            LayerTopology::new(3).with_kind(LayerKind::Gru),
+        ];
+
+        let mut a = Network::random(&topology);
+        let mut b = a.clone();
+
+        for inputs in [[0.1, 0.2], [0.3, -0.4]] {
+            assert_eq!(
+                a.propagate_traced(inputs.to_vec()).outputs(),
+                b.propagate(inputs.to_vec())
+            );
+        }
+    }
+
+    #[test]
+    fn test_accessors() {
+        let network = network();
+
+        assert_eq!(network.layers().len(), 2);
+
+        let hidden = network.layer(0);
+        assert_eq!((hidden.inputs(), hidden.neurons()), (2, 2));
+        assert_eq!(hidden.bias(1), 0.5);
+        assert_eq!(hidden.weight(0, 1), -1.0);
+
+        let output = network.layer(1);
+        assert_eq!(output.topology().activation, Activation::Tanh);
+        assert_eq!(output.weights(), [0.1, 1.0, 2.0]);
+    }
+}
@@ -0,0 +1,361 @@
+use crate::*;
+
+#[derive(Clone, Debug)]
+pub(crate) enum Layer<T = f32> {
+    Dense(Dense<T>),
+    Elman(Elman<T>),
+    Gru(Gru<T>),
+    Conv1d(Conv1d<T>),
+    Hebbian(Hebbian<T>),
+    Spiking(Lif<T>),
+    LayerNorm(LayerNorm<T>),
+    BatchNorm(BatchNorm<T>),
+    Dropout(Dropout<T>),
+}
+
+//Neither of these depends on the scalar type, so they are only defined once
+impl Layer {
+    #[cfg(feature = "rand")]
+    pub(crate) fn random(
+        rng: &mut dyn RngCore,
+        inputs: usize,
+        topology: &LayerTopology,
+        init: Init,
+    ) -> Self {
+        let LayerTopology {
+            neurons,
+            activation,
+            kind,
+        } = *topology;
+
+        match kind {
+            LayerKind::Dense => Self::Dense(Dense::random(rng, inputs, neurons, activation, init)),
+            //Recurrent weights count towards the fan-in, just like the inputs
+            LayerKind::Elman => Self::Elman(Elman::new(Dense::random(
+                rng,
+                inputs + neurons,
+                neurons,
+                activation,
+                init,
+            ))),
+            LayerKind::Gru => {
+                let weights = (0..3)
+                    .flat_map(|_| init.weights(rng, inputs + neurons, neurons))
+                    .collect();
+
+                Self::Gru(Gru::new(inputs, neurons, weights, activation))
+            }
+            //Only the kernel counts towards the fan-in, since weights are shared
+            LayerKind::Conv1d(convolution) => {
+                let weights = init.weights(
+                    rng,
+                    convolution.kernel * convolution.channels,
+                    convolution.filters,
+                );
+
+                Self::Conv1d(Conv1d::new(inputs, convolution, weights, activation))
+            }
+            LayerKind::Hebbian(plasticity) => Self::Hebbian(Hebbian::random(
+                rng, inputs, neurons, plasticity, activation, init,
+            )),
+            LayerKind::Spiking(spiking) => Self::Spiking(Lif::new(
+                Dense::random(rng, inputs, neurons, activation, init),
+                spiking,
+            )),
+            //Normalization starts out as just that, whatever `init` says
+            LayerKind::LayerNorm => Self::LayerNorm(LayerNorm::identity(neurons, activation)),
+            LayerKind::BatchNorm => Self::BatchNorm(BatchNorm::identity(neurons, activation)),
+            LayerKind::Dropout(rate) => {
+                Self::Dropout(Dropout::new(neurons, rate, activation, rng.next_u64()))
+            }
+        }
+    }
+
+    /// How many weights a layer of the given shape has, or `None` if that
+    /// doesn't fit in a `usize`.
+    pub(crate) fn weights_len(inputs: usize, topology: &LayerTopology) -> Option<usize> {
+        let neurons = topology.neurons;
+
+        match topology.kind {
+            LayerKind::Dense | LayerKind::Spiking(_) => inputs.checked_add(1)?.checked_mul(neurons),
+            LayerKind::Elman => inputs
+                .checked_add(neurons)?
+                .checked_add(1)?
+                .checked_mul(neurons),
+            LayerKind::Gru => Gru::<f32>::weights_len(inputs, neurons),
+            LayerKind::Conv1d(convolution) => convolution.weights_len(),
+            LayerKind::Hebbian(plasticity) => {
+                Hebbian::<f32>::weights_len(inputs, neurons, plasticity)
+            }
+            LayerKind::LayerNorm | LayerKind::BatchNorm => neurons.checked_mul(2),
+            LayerKind::Dropout(_) => Some(0),
+        }
+    }
+}
+
+impl<T: Scalar> Layer<T> {
+    /// Builds the layer at the given position of its network, which seeds
+    /// its dropout mask if it has one.
+    pub(crate) fn from_weights(
+        index: usize,
+        inputs: usize,
+        topology: &LayerTopology,
+        weights: &mut dyn Iterator<Item = T>,
+    ) -> Self {
+        let LayerTopology {
+            neurons,
+            activation,
+            kind,
+        } = *topology;
+
+        let len = Layer::weights_len(inputs, topology).expect("topology is checked beforehand");
+
+        match kind {
+            LayerKind::Dense => {
+                Self::Dense(Dense::from_weights(inputs, neurons, activation, weights))
+            }
+            LayerKind::Elman => Self::Elman(Elman::new(Dense::from_weights(
+                inputs + neurons,
+                neurons,
+                activation,
+                weights,
+            ))),
+            LayerKind::Gru => Self::Gru(Gru::new(inputs, neurons, take(weights, len), activation)),
+            LayerKind::Conv1d(convolution) => Self::Conv1d(Conv1d::new(
+                inputs,
+                convolution,
+                take(weights, len),
+                activation,
+            )),
+            LayerKind::Hebbian(plasticity) => Self::Hebbian(Hebbian::new(
+                inputs,
+                neurons,
+                plasticity,
+                take(weights, len),
+                activation,
+            )),
+            LayerKind::Spiking(spiking) => Self::Spiking(Lif::new(
+                Dense::from_weights(inputs, neurons, activation, weights),
+                spiking,
+            )),
+            LayerKind::LayerNorm => {
+                Self::LayerNorm(LayerNorm::new(take(weights, 2 * neurons), activation))
+            }
+            LayerKind::BatchNorm => {
+                Self::BatchNorm(BatchNorm::new(take(weights, 2 * neurons), activation))
+            }
+            LayerKind::Dropout(rate) => {
+                Self::Dropout(Dropout::new(neurons, rate, activation, index as u64))
+            }
+        }
+    }
+
+    pub(crate) fn inputs(&self) -> usize {
+        match self {
+            Self::Dense(layer) => layer.inputs,
+            Self::Elman(layer) => layer.inputs(),
+            Self::Gru(layer) => layer.inputs(),
+            Self::Conv1d(layer) => layer.inputs(),
+            Self::Hebbian(layer) => layer.inputs(),
+            Self::Spiking(layer) => layer.inputs(),
+            Self::LayerNorm(layer) => layer.neurons(),
+            Self::BatchNorm(layer) => layer.neurons(),
+            Self::Dropout(layer) => layer.neurons(),
+        }
+    }
+
+    pub(crate) fn topology(&self) -> LayerTopology {
+        let (neurons, activation, kind) = match self {
+            Self::Dense(layer) => (layer.neurons(), layer.activation, LayerKind::Dense),
+            Self::Elman(layer) => (layer.neurons(), layer.activation(), LayerKind::Elman),
+            Self::Gru(layer) => (layer.neurons(), layer.activation(), LayerKind::Gru),
+            Self::Conv1d(layer) => (
+                layer.neurons(),
+                layer.activation(),
+                LayerKind::Conv1d(layer.convolution()),
+            ),
+            Self::Hebbian(layer) => (
+                layer.neurons(),
+                layer.activation(),
+                LayerKind::Hebbian(layer.plasticity()),
+            ),
+            Self::Spiking(layer) => (
+                layer.neurons(),
+                layer.activation(),
+                LayerKind::Spiking(layer.spiking()),
+            ),
+            Self::LayerNorm(layer) => (layer.neurons(), layer.activation(), LayerKind::LayerNorm),
+            Self::BatchNorm(layer) => (layer.neurons(), layer.activation(), LayerKind::BatchNorm),
+            Self::Dropout(layer) => (
+                layer.neurons(),
+                layer.activation(),
+                LayerKind::Dropout(layer.rate()),
+            ),
+        };
+
+        LayerTopology::new(neurons)
+            .with_activation(activation)
+            .with_kind(kind)
+    }
+
+    /// This layer's share of `Network::weights()`.
+    pub(crate) fn weights(&self) -> &[T] {
+        match self {
+            Self::Dense(layer) => &layer.weights,
+            Self::Elman(layer) => layer.weights(),
+            Self::Gru(layer) => layer.weights(),
+            Self::Conv1d(layer) => layer.weights(),
+            Self::Hebbian(layer) => layer.weights(),
+            Self::Spiking(layer) => layer.weights(),
+            Self::LayerNorm(layer) => layer.weights(),
+            Self::BatchNorm(layer) => layer.weights(),
+            Self::Dropout(_) => &[],
+        }
+    }
+
+    pub(crate) fn weights_mut(&mut self) -> &mut [T] {
+        match self {
+            Self::Dense(layer) => &mut layer.weights,
+            Self::Elman(layer) => layer.weights_mut(),
+            Self::Gru(layer) => layer.weights_mut(),
+            Self::Conv1d(layer) => layer.weights_mut(),
+            Self::Hebbian(layer) => layer.weights_mut(),
+            Self::Spiking(layer) => layer.weights_mut(),
+            Self::LayerNorm(layer) => layer.weights_mut(),
+            Self::BatchNorm(layer) => layer.weights_mut(),
+            Self::Dropout(_) => &mut [],
+        }
+    }
+
+    pub(crate) fn propagate_into(&mut self, inputs: &[T], outputs: &mut Vec<T>) {
+        match self {
+            Self::Dense(layer) => layer.propagate_into(inputs, outputs),
+            Self::Elman(layer) => layer.propagate_into(inputs, outputs),
+            Self::Gru(layer) => layer.propagate_into(inputs, outputs),
+            Self::Conv1d(layer) => layer.propagate_into(inputs, outputs),
+            Self::Hebbian(layer) => layer.propagate_into(inputs, outputs),
+            Self::Spiking(layer) => layer.propagate_into(inputs, outputs),
+            Self::LayerNorm(layer) => layer.propagate_into(inputs, outputs),
+            Self::BatchNorm(layer) => layer.propagate_into(inputs, outputs),
+            Self::Dropout(layer) => layer.propagate_into(inputs, outputs),
+        }
+    }
+
+    pub(crate) fn trace_into(
+        &mut self,
+        inputs: &[T],
+        pre_activations: &mut Vec<T>,
+        outputs: &mut Vec<T>,
+    ) {
+        match self {
+            Self::Dense(layer) => layer.trace_into(inputs, pre_activations, outputs),
+            Self::Elman(layer) => layer.trace_into(inputs, pre_activations, outputs),
+            Self::Gru(layer) => layer.trace_into(inputs, pre_activations, outputs),
+            Self::Conv1d(layer) => layer.trace_into(inputs, pre_activations, outputs),
+            Self::Hebbian(layer) => layer.trace_into(inputs, pre_activations, outputs),
+            Self::Spiking(layer) => layer.trace_into(inputs, pre_activations, outputs),
+            Self::LayerNorm(layer) => layer.trace_into(inputs, pre_activations, outputs),
+            Self::BatchNorm(layer) => layer.trace_into(inputs, pre_activations, outputs),
+            Self::Dropout(layer) => layer.trace_into(inputs, pre_activations, outputs),
+        }
+    }
+
+    /// The given neuron's bias. For GRU layers, that's the bias of the
+    /// candidate state; for normalization layers, the shift (β).
+    pub(crate) fn bias(&self, neuron: usize) -> T {
+        match self {
+            Self::Dense(layer) => layer.row(neuron)[0],
+            Self::Elman(layer) => layer.row(neuron)[0],
+            Self::Gru(layer) => layer.row(2, neuron)[0],
+            Self::Conv1d(layer) => layer.bias(neuron),
+            Self::Hebbian(layer) => layer.row(neuron)[0],
+            Self::Spiking(layer) => layer.row(neuron)[0],
+            Self::LayerNorm(layer) => layer.row(neuron)[0],
+            Self::BatchNorm(layer) => layer.row(neuron)[0],
+            Self::Dropout(_) => T::ZERO,
+        }
+    }
+
+    /// Weight of the connection from `input` to `neuron`. For GRU layers,
+    /// that's the weight of the candidate state; for convolutional ones, the
+    /// shared weight if `input` is under the neuron's kernel and zero if not;
+    /// for Hebbian ones, the weight it starts with. Layers with one input per
+    /// neuron only connect the two at the same index, normalization layers
+    /// through their scale (γ).
+    pub(crate) fn weight(&self, neuron: usize, input: usize) -> T {
+        assert!(input < self.inputs());
+
+        match self {
+            Self::Dense(layer) => layer.row(neuron)[1 + input],
+            Self::Elman(layer) => layer.row(neuron)[1 + input],
+            Self::Gru(layer) => layer.row(2, neuron)[1 + input],
+            Self::Conv1d(layer) => layer.weight(neuron, input),
+            Self::Hebbian(layer) => layer.row(neuron)[1 + input],
+            Self::Spiking(layer) => layer.row(neuron)[1 + input],
+            Self::LayerNorm(layer) if input == neuron => layer.row(neuron)[1],
+            Self::BatchNorm(layer) if input == neuron => layer.row(neuron)[1],
+            Self::Dropout(_) if input == neuron => T::ONE,
+            Self::LayerNorm(_) | Self::BatchNorm(_) | Self::Dropout(_) => T::ZERO,
+        }
+    }
+
+    pub(crate) fn as_dense(&self) -> Option<&Dense<T>> {
+        match self {
+            Self::Dense(layer) => Some(layer),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_dense_mut(&mut self) -> Option<&mut Dense<T>> {
+        match self {
+            Self::Dense(layer) => Some(layer),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn reset_state(&mut self) {
+        match self {
+            Self::Dense(_) | Self::Conv1d(_) => (),
+            Self::Elman(layer) => layer.reset_state(),
+            Self::Gru(layer) => layer.reset_state(),
+            Self::Hebbian(layer) => layer.reset_state(),
+            Self::Spiking(layer) => layer.reset_state(),
+            Self::LayerNorm(_) | Self::BatchNorm(_) | Self::Dropout(_) => (),
+        }
+    }
+
+    pub(crate) fn set_mode(&mut self, mode: Mode) {
+        match self {
+            Self::BatchNorm(layer) => layer.set_mode(mode),
+            Self::Dropout(layer) => layer.set_mode(mode),
+            _ => (),
+        }
+    }
+
+    /// Length of every row - a bias followed by weights - and of all rows
+    /// together, which may be followed by other parameters, such as a
+    /// Hebbian layer's rules. `None` for layers without connections.
+    pub(crate) fn rows(&self) -> Option<(usize, usize)> {
+        let inputs = self.inputs();
+        let len = self.weights().len();
+
+        match self {
+            Self::Dense(_) | Self::Spiking(_) => Some((inputs + 1, len)),
+            Self::Elman(_) | Self::Gru(_) => Some((inputs + self.topology().neurons + 1, len)),
+            Self::Conv1d(layer) => {
+                let convolution = layer.convolution();
+                Some((1 + convolution.kernel * convolution.channels, len))
+            }
+            Self::Hebbian(layer) => Some((inputs + 1, layer.rows_len())),
+            Self::LayerNorm(_) | Self::BatchNorm(_) | Self::Dropout(_) => None,
+        }
+    }
+}
+
+//The next `len` weights, for a layer of that many
+fn take<T>(weights: &mut dyn Iterator<Item = T>, len: usize) -> Vec<T> {
+    let weights: Vec<_> = weights.take(len).collect();
+    assert_eq!(weights.len(), len, "got not enough weights");
+
+    weights
+}
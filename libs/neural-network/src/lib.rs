@@ -1,147 +1,260 @@
-use core::panic;
-
-use rand::Rng;
-#[derive(Debug)]
-pub struct Network {
-    layers: Vec<Layer>,
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(not(feature = "alloc"))]
+compile_error!("neural-network needs the `alloc` feature when built without `std`");
+
+extern crate alloc;
+
+#[cfg(not(feature = "std"))]
+use self::float::*;
+pub use self::{
+    activation::*, conv::*, data::*, dropout::*, error::*, format::*, graph::*, heads::*,
+    hebbian::*, init::*, inspect::*, optimizer::*, penalty::*, quantize::*, scalar::*, schedule::*,
+    sparse::*, spiking::*, train::*,
+};
+use self::{dense::*, layer::*, norm::*, recurrent::*};
+mod activation;
+mod conv;
+mod data;
+mod dense;
+mod distance;
+mod dropout;
+mod error;
+#[cfg(not(feature = "std"))]
+mod float;
+mod format;
+mod graph;
+mod heads;
+mod hebbian;
+mod init;
+mod inspect;
+mod layer;
+mod norm;
+mod optimizer;
+mod penalty;
+mod prune;
+mod quantize;
+mod recurrent;
+mod render;
+mod scalar;
+mod schedule;
+mod sparse;
+mod spiking;
+mod surgery;
+mod train;
+
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+#[cfg(feature = "rand")]
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+/// Layered neural network computing with `T`: `f32` by default, `f64` or
+/// `Fixed` after a `cast`.
+///
+/// Only `f32` networks can be created at random, trained or saved; the other
+/// scalar types are meant for running networks built that way.
+#[derive(Clone, Debug)]
+pub struct Network<T = f32> {
+    layers: Vec<Layer<T>>,
 }
 
-#[derive(Debug)]
+/// Describes one layer of a `Network`.
+///
+/// The activation of the first (input) layer is never applied, since that
+/// layer only describes how many inputs the network takes.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct LayerTopology {
     pub neurons: usize,
+    pub activation: Activation,
+    pub kind: LayerKind,
 }
-#[derive(Debug)]
-struct Layer {
-    neurons: Vec<Neuron>,
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum LayerKind {
+    /// Every neuron sees the previous layer's outputs.
+    #[default]
+    Dense,
+    /// Every neuron also sees the whole layer's outputs from the previous
+    /// `propagate` call.
+    Elman,
+    /// Gated recurrent unit, which learns what to keep from the previous
+    /// `propagate` call and what to overwrite.
+    Gru,
+    /// Small filters slid along the previous layer's outputs, see
+    /// `Convolution`. Create such layers with `LayerTopology::conv1d`.
+    Conv1d(Convolution),
+    /// Dense layer that keeps adjusting its weights as it propagates,
+    /// following evolvable Hebbian rules; see `Plasticity`.
+    Hebbian(Plasticity),
+    /// Leaky integrate-and-fire neurons, which carry their membrane
+    /// potential from one `propagate` call to the next; see `Spiking`.
+    Spiking(Spiking),
+    /// Normalizes every input vector across its values, then scales and
+    /// shifts each one. Like the two kinds below, it has one neuron per
+    /// input and still applies its activation, usually
+    /// `Activation::Identity`.
+    LayerNorm,
+    /// Normalizes every input across input vectors, see `Mode`.
+    BatchNorm,
+    /// Randomly zeroes inputs at the given rate in `Mode::Training`.
+    Dropout(f32),
 }
-#[derive(Debug)]
-struct Neuron {
-    bias: f32,
-    weights: Vec<f32>,
+
+impl LayerKind {
+    //Whether layers of this kind have one neuron per input
+    pub(crate) fn keeps_width(&self) -> bool {
+        matches!(self, Self::LayerNorm | Self::BatchNorm | Self::Dropout(_))
+    }
+}
+
+/// Reusable buffers for `Network::propagate_into` and
+/// `Network::propagate_batch_into`, so that propagating doesn't allocate once
+/// the buffers have grown to fit the network.
+#[derive(Clone, Debug, Default)]
+pub struct Scratch<T = f32> {
+    front: Vec<T>,
+    back: Vec<T>,
+}
+
+impl<T: Scalar> Scratch<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl LayerTopology {
+    pub fn new(neurons: usize) -> Self {
+        Self {
+            neurons,
+            activation: Activation::default(),
+            kind: LayerKind::default(),
+        }
+    }
+
+    pub fn with_activation(mut self, activation: Activation) -> Self {
+        self.activation = activation;
+        self
+    }
+
+    pub fn with_kind(mut self, kind: LayerKind) -> Self {
+        self.kind = kind;
+        self
+    }
 }
 
 impl Network {
-    pub fn new(layers: Vec<Layer>) -> Self {
+    #[cfg(test)]
+    fn new(layers: Vec<Layer>) -> Self {
         Self { layers }
     }
+    #[cfg(all(feature = "std", feature = "rand"))]
     pub fn random(layers: &[LayerTopology]) -> Self {
-        //Ensure that the layer is more than one layer
-        assert!(layers.len() > 1);
+        Self::try_random(layers).unwrap_or_else(|err| panic!("{err}"))
+    }
 
-        let layers = layers
-            .windows(2)
-            .map(|layers| Layer::random(layers[0].neurons, layers[1].neurons))
-            .collect();
-        Self { layers }
+    #[cfg(all(feature = "std", feature = "rand"))]
+    pub fn try_random(layers: &[LayerTopology]) -> Result<Self, NetworkError> {
+        Self::try_random_with(&mut rand::thread_rng(), layers, Init::default())
     }
+}
 
-    pub fn propagate(&self, inputs: Vec<f32>) -> Vec<f32> {
-        self.layers
-            .iter()
-            .fold(inputs, |inputs, layer| layer.propagate(inputs))
+impl<T: Scalar> Network<T> {
+    /// Propagates `inputs` through the network.
+    ///
+    /// Recurrent layers update their state on every call, so that the next
+    /// call sees it; see `reset_state`.
+    pub fn propagate(&mut self, inputs: Vec<T>) -> Vec<T> {
+        self.propagate_into(&inputs, &mut Scratch::new()).to_vec()
     }
 
-    pub fn weights(&self) -> Vec<f32> {
-        //Fun idomatic solution to this function
-        //We just want to collect all the weights through the layers, for each neuron
-        use std::iter::once;
+    /// Like `propagate`, but writes into `scratch` instead of allocating.
+    pub fn propagate_into<'a>(&mut self, inputs: &[T], scratch: &'a mut Scratch<T>) -> &'a [T] {
+        assert_eq!(inputs.len(), self.layers[0].inputs());
 
-        self.layers
-            .iter()
-            .flat_map(|layer| layer.neurons.iter())
-            .flat_map(|neuron| once(&neuron.bias).chain(&neuron.weights))
-            .copied()
-            .collect()
+        self.propagate_batch_into(inputs, scratch)
     }
 
-    pub fn from_weights(layers: &[LayerTopology], weights: impl IntoIterator<Item = f32>) -> Self {
-        assert!(layers.len() > 1);
+    /// Propagates many input vectors at once; `inputs` holds them one after
+    /// another and so does the returned vector for their outputs.
+    ///
+    /// Dense layers compute the whole batch as a single matrix product.
+    /// Recurrent layers treat the batch as a sequence, carrying their state
+    /// from one input vector to the next.
+    pub fn propagate_batch(&mut self, inputs: &[T]) -> Vec<T> {
+        self.propagate_batch_into(inputs, &mut Scratch::new())
+            .to_vec()
+    }
 
-        let mut weights = weights.into_iter();
+    pub fn propagate_batch_into<'a>(
+        &mut self,
+        inputs: &[T],
+        scratch: &'a mut Scratch<T>,
+    ) -> &'a [T] {
+        let Scratch { front, back } = scratch;
 
-        let layers = layers
-            .windows(2)
-            .map(|layers| Layer::from_weights(layers[0].neurons, layers[1].neurons, &mut weights))
-            .collect();
+        self.layers[0].propagate_into(inputs, front);
 
-        if weights.next().is_some() {
-            panic!("got too many weights");
+        for layer in &mut self.layers[1..] {
+            layer.propagate_into(front, back);
+            core::mem::swap(front, back);
         }
 
-        Self { layers }
+        front
     }
-}
 
-impl Layer {
-    pub fn new(neurons: Vec<Neuron>) -> Self {
-        Self { neurons }
+    /// Makes recurrent layers forget everything they've seen so far.
+    pub fn reset_state(&mut self) {
+        for layer in &mut self.layers {
+            layer.reset_state();
+        }
     }
-    pub fn random(input_size: usize, output_size: usize) -> Self {
-        let neurons = (0..output_size)
-            .map(|_| Neuron::random(input_size))
-            .collect();
 
-        Self { neurons }
-    }
-    pub fn from_weights(
-        input_size: usize,
-        output_size: usize,
-        weights: &mut dyn Iterator<Item = f32>,
-    ) -> Self {
-        let neurons = (0..output_size)
-            .map(|_| Neuron::from_weights(input_size, weights))
-            .collect();
-        Self { neurons }
+    /// Describes the shape of this network, as accepted by `from_weights`.
+    pub fn topology(&self) -> Vec<LayerTopology> {
+        core::iter::once(LayerTopology::new(self.layers[0].inputs()))
+            .chain(self.layers.iter().map(Layer::topology))
+            .collect()
     }
-    fn propagate(&self, inputs: Vec<f32>) -> Vec<f32> {
-        self.neurons
+
+    pub fn weights(&self) -> Vec<T> {
+        //Every layer already stores its neurons' biases and weights in order
+        self.layers
             .iter()
-            .map(|neuron| neuron.propagate(&inputs))
+            .flat_map(|layer| layer.weights())
+            .copied()
             .collect()
     }
-}
 
-impl Neuron {
-    pub fn new(bias: f32, weights: Vec<f32>) -> Self {
-        Self { bias, weights }
+    pub fn from_weights(layers: &[LayerTopology], weights: impl IntoIterator<Item = T>) -> Self {
+        Self::try_from_weights(layers, weights).unwrap_or_else(|err| panic!("{err}"))
     }
-    pub fn random(input_size: usize) -> Self {
-        let mut rng = rand::thread_rng();
-
-        let bias = rng.gen_range(-1.0..=1.0);
 
-        let weights = (0..input_size).map(|_| rng.gen_range(-1.0..=1.0)).collect();
+    pub fn try_from_weights(
+        layers: &[LayerTopology],
+        weights: impl IntoIterator<Item = T>,
+    ) -> Result<Self, NetworkError> {
+        check_topology(layers)?;
 
-        Self { bias, weights }
-    }
+        let weights: Vec<T> = weights.into_iter().collect();
+        check_weights(layers, &weights)?;
 
-    pub fn from_weights(input_size: usize, weights: &mut dyn Iterator<Item = f32>) -> Self {
-        let bias = weights.next().expect("got not enough weights");
+        let mut weights = weights.into_iter();
 
-        let weights = (0..input_size)
-            .map(|_| weights.next().expect("got not enough weights"))
+        let layers = layers
+            .windows(2)
+            .enumerate()
+            .map(|(index, layers)| {
+                Layer::from_weights(index, layers[0].neurons, &layers[1], &mut weights)
+            })
             .collect();
-        Self { bias, weights }
-    }
-
-    #[warn(dead_code)]
-    fn random_test(rng: &mut dyn rand::RngCore, input_size: usize) -> Self {
-        let bias = rng.gen_range(-1.0..=1.0);
-
-        let weights = (0..input_size).map(|_| rng.gen_range(-1.0..=1.0)).collect();
 
-        Self { bias, weights }
-    }
-    fn propagate(&self, inputs: &[f32]) -> f32 {
-        assert_eq!(inputs.len(), self.weights.len());
-
-        let output = inputs
-            .iter()
-            .zip(&self.weights)
-            .map(|(input, weight)| input * weight)
-            .sum::<f32>();
-
-        (output + self.bias).max(0.0)
+        Ok(Self { layers })
     }
 }
 
@@ -149,129 +262,237 @@ impl Neuron {
 mod tests {
     use super::*;
 
-    mod random {
+    mod propagate {
         use super::*;
 
         #[test]
-        fn test_random() {
-            use approx::assert_relative_eq;
-            use rand::SeedableRng;
-            use rand_chacha::ChaCha8Rng;
-            // Because we always use the same seed, our `rng` in here will
-            // always return the same set of values
-            let mut rng = ChaCha8Rng::from_seed(Default::default());
-            let neuron = Neuron::random_test(&mut rng, 4);
-
-            assert_relative_eq!(neuron.bias, -0.6255188);
-            assert_relative_eq!(
-                neuron.weights.as_slice(),
-                [0.67383957, 0.8181262, 0.26284897, 0.5238807].as_ref()
+        fn test_propagate_network() {
+            let layers = &[
+                LayerTopology::new(2),
+                LayerTopology::new(2),
+                LayerTopology::new(1).with_activation(Activation::Tanh),
+            ];
+
+            #[rustfmt::skip]
+            let weights = vec![
+                0.1, 0.5, -0.5,
+                -0.2, 1.0, 2.0,
+                0.3, 2.0, -1.0,
+            ];
+
+            let mut network = Network::from_weights(layers, weights);
+
+            //Hidden: relu(0.1 + 0.25 - 0.25) = 0.1 and relu(-0.2 + 0.5 + 1.0) = 1.3
+            approx::assert_relative_eq!(
+                network.propagate(vec![0.5, 0.5])[0],
+                (0.3f32 + 2.0 * 0.1 - 1.3).tanh()
             );
         }
-    }
-
-    mod propagate {
-        use super::*;
 
         #[test]
-        fn test_propagate_neuron() {
-            let neuron = Neuron {
-                bias: 0.5,
-                weights: vec![-0.3, 0.8],
-            };
+        fn test_propagate_batch() {
+            let mut network = Network::random(&[LayerTopology::new(3), LayerTopology::new(2)]);
+            let inputs = [0.1, 0.2, 0.3, -0.4, -0.5, -0.6];
 
-            //Ensure that our .max() works as intended:
-            approx::assert_relative_eq!(neuron.propagate(&[-10.0, -10.0]), 0.0,);
+            let outputs = network.propagate_batch(&inputs);
 
-            // 0.5 and 1.0 test it
             approx::assert_relative_eq!(
-                neuron.propagate(&[0.5, 1.0]),
-                (-0.3 * 0.5) + (0.8 * 1.0) + 0.5
+                &outputs[..2],
+                network.propagate(inputs[..3].to_vec()).as_slice()
+            );
+            approx::assert_relative_eq!(
+                &outputs[2..],
+                network.propagate(inputs[3..].to_vec()).as_slice()
             );
         }
 
         #[test]
-        fn test_propagate_Layer() {
-            let layer = Layer {
-                neurons: vec![
-                    Neuron {
-                        bias: 0.5,
-                        weights: vec![-0.3, 1.0],
-                    },
-                    Neuron {
-                        bias: 0.2,
-                        weights: vec![-0.3, 0.8],
-                    },
-                    Neuron {
-                        bias: 0.1,
-                        weights: vec![0.3, 0.2],
-                    },
-                ],
-            };
-
-            let inputs = vec![-0.3, 0.5];
-
-            //Ensure that the .max() works (ReLu)
-            assert_eq!(&layer.propagate(inputs), &vec![1.09, 0.69, 0.11]);
+        fn test_propagate_into_reuses_scratch() {
+            let mut network = Network::random(&[LayerTopology::new(3), LayerTopology::new(2)]);
+            let mut scratch = Scratch::new();
+
+            let first = network
+                .propagate_into(&[0.1, 0.2, 0.3], &mut scratch)
+                .to_vec();
+            let second = network.propagate_into(&[0.1, 0.2, 0.3], &mut scratch);
+
+            approx::assert_relative_eq!(first.as_slice(), second);
+        }
+    }
+
+    //Hand-rolled property tests: every case is drawn from a seeded RNG, so
+    //failures can be reproduced
+    mod properties {
+        use super::*;
+        use rand::{Rng, SeedableRng};
+        use rand_chacha::ChaCha8Rng;
+
+        const CASES: usize = 100;
+
+        fn random_activation(rng: &mut ChaCha8Rng) -> Activation {
+            match rng.gen_range(0..8) {
+                0 => Activation::Relu,
+                1 => Activation::LeakyRelu(0.1),
+                2 => Activation::Elu(1.0),
+                3 => Activation::Sigmoid,
+                4 => Activation::Tanh,
+                5 => Activation::Identity,
+                6 => Activation::Softsign,
+                _ => Activation::Step,
+            }
         }
+
+        //Mixes every layer kind, with convolutions shaped to fit
+        fn random_topology(rng: &mut ChaCha8Rng) -> Vec<LayerTopology> {
+            let mut layers = vec![LayerTopology::new(rng.gen_range(1..8))];
+
+            for _ in 0..rng.gen_range(1..5) {
+                let inputs = layers.last().unwrap().neurons;
+                let activation = random_activation(rng);
+
+                let layer = match rng.gen_range(0..7) {
+                    0 => LayerTopology::new(rng.gen_range(1..6)).with_kind(LayerKind::Elman),
+                    1 => LayerTopology::new(rng.gen_range(1..6)).with_kind(LayerKind::Gru),
+                    2 => {
+                        let plasticity = if rng.gen() {
+                            Plasticity::PerLayer
+                        } else {
+                            Plasticity::PerConnection
+                        };
+
+                        LayerTopology::new(rng.gen_range(1..6))
+                            .with_kind(LayerKind::Hebbian(plasticity))
+                    }
+                    3 => {
+                        let spiking =
+                            Spiking::new(rng.gen_range(0.1..2.0), rng.gen_range(0.0..=1.0))
+                                .with_refractory(rng.gen_range(0..3))
+                                .with_output(match rng.gen_range(0..3) {
+                                    0 => SpikeOutput::Spikes,
+                                    1 => SpikeOutput::Counts,
+                                    _ => SpikeOutput::Potentials,
+                                });
+
+                        LayerTopology::new(rng.gen_range(1..6))
+                            .with_kind(LayerKind::Spiking(spiking))
+                    }
+                    4 => {
+                        let padding = if rng.gen() {
+                            Padding::Valid
+                        } else {
+                            Padding::Circular
+                        };
+
+                        let convolution =
+                            Convolution::new(rng.gen_range(1..3), rng.gen_range(1..=inputs))
+                                .with_padding(padding);
+
+                        LayerTopology::conv1d(inputs, convolution)
+                    }
+                    5 => LayerTopology::new(inputs).with_kind(match rng.gen_range(0..3) {
+                        0 => LayerKind::LayerNorm,
+                        1 => LayerKind::BatchNorm,
+                        _ => LayerKind::Dropout(rng.gen_range(0.0..0.9)),
+                    }),
+                    _ => LayerTopology::new(rng.gen_range(1..6)),
+                };
+
+                layers.push(layer.with_activation(activation));
+            }
+
+            layers
+        }
+
+        fn random_inputs(rng: &mut ChaCha8Rng, len: usize) -> Vec<f32> {
+            (0..len).map(|_| rng.gen_range(-1.0..=1.0)).collect()
+        }
+
         #[test]
-        fn test_propagate_layer() {
-            let layer = Layer {
-                neurons: vec![
-                    Neuron {
-                        bias: 0.5,
-                        weights: vec![-0.3, 1.0],
-                    },
-                    Neuron {
-                        bias: 0.2,
-                        weights: vec![-0.3, 0.8],
-                    },
-                    Neuron {
-                        bias: 0.1,
-                        weights: vec![0.3, 0.2],
-                    },
-                ],
-            };
-
-            let inputs = vec![-0.3, 0.5];
-
-            //Ensure that the .max() works (ReLu)
-            assert_eq!(&layer.propagate(inputs), &vec![1.09, 0.69, 0.11]);
+        fn test_from_weights_round_trips() {
+            let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+            for _ in 0..CASES {
+                let topology = random_topology(&mut rng);
+                let mut network = Network::random_with(&mut rng, &topology, Init::default());
+                let mut copy = Network::from_weights(&topology, network.weights());
+
+                assert_eq!(copy.topology(), topology);
+                assert_eq!(copy.weights(), network.weights());
+
+                //Including the recurrent state, step after step
+                for _ in 0..3 {
+                    let inputs = random_inputs(&mut rng, topology[0].neurons);
+
+                    assert_eq!(
+                        copy.propagate(inputs.clone()),
+                        network.propagate(inputs),
+                        "{topology:?}"
+                    );
+                }
+            }
         }
+
         #[test]
-        fn test_propagate_network() {}
+        fn test_propagate_matches_topology() {
+            let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+            for _ in 0..CASES {
+                let topology = random_topology(&mut rng);
+                let mut network = Network::random_with(&mut rng, &topology, Init::default());
+                let inputs = random_inputs(&mut rng, topology[0].neurons);
+                let outputs = network.propagate(inputs);
+
+                assert_eq!(
+                    outputs.len(),
+                    topology.last().unwrap().neurons,
+                    "{topology:?}"
+                );
+                assert!(outputs.iter().all(|output| output.is_finite()));
+            }
+        }
     }
-}
 
-mod weights {
-    use super::*;
+    mod weights {
+        use super::*;
 
-    #[test]
-    fn test() {
-        let network = Network::new(vec![
-            Layer::new(vec![Neuron::new(0.1, vec![0.2, 0.3, 0.4])]),
-            Layer::new(vec![Neuron::new(0.1, vec![0.6, 0.7, 0.8])]),
-        ]);
+        #[test]
+        fn test() {
+            let network = Network::new(vec![
+                Layer::Dense(Dense::new(3, vec![0.1, 0.2, 0.3, 0.4], Activation::Relu)),
+                Layer::Dense(Dense::new(3, vec![0.5, 0.6, 0.7, 0.8], Activation::Relu)),
+            ]);
 
-        let actual = network.weights();
-        let expected = vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8];
+            let actual = network.weights();
+            let expected = vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8];
 
-        approx::assert_relative_eq!(actual.as_slice(), expected.as_slice());
+            approx::assert_relative_eq!(actual.as_slice(), expected.as_slice());
+        }
     }
-}
+    mod from_weights {
+        use super::*;
 
-mod from_weights {
-    use super::*;
+        #[test]
+        fn test() {
+            let layers = &[LayerTopology::new(3), LayerTopology::new(2)];
+
+            let weights = vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8];
+
+            let network = Network::from_weights(layers, weights.clone());
+            let actual: Vec<_> = network.weights();
 
-    #[test]
-    fn test() {
-        let layers = &[LayerTopology { neurons: 3 }, LayerTopology { neurons: 2 }];
+            approx::assert_relative_eq!(actual.as_slice(), weights.as_slice());
+        }
 
-        let weights = vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8];
+        #[test]
+        fn test_keeps_activations() {
+            let layers = &[
+                LayerTopology::new(1),
+                LayerTopology::new(1).with_activation(Activation::Tanh),
+            ];
 
-        let network = Network::from_weights(layers, weights.clone());
-        let actual: Vec<_> = network.weights();
+            let mut network = Network::from_weights(layers, vec![0.0, -1.0]);
 
-        approx::assert_relative_eq!(actual.as_slice(), weights.as_slice());
+            approx::assert_relative_eq!(network.propagate(vec![1.0])[0], (-1.0f32).tanh());
+        }
     }
 }
@@ -0,0 +1,585 @@
+use crate::*;
+
+//Added to every variance, so that constant inputs don't divide by zero
+const EPSILON: f64 = 1e-5;
+
+//Fraction of a `BatchNorm`'s running statistics that each training batch
+//replaces
+const MOMENTUM: f64 = 0.1;
+
+/// Layer normalization (Ba et al., 2016): every input vector is shifted and
+/// scaled to zero mean and unit variance over its values, then each neuron
+/// applies a learned scale (γ) and shift (β) to its input.
+///
+/// Every row of the weights holds a neuron's β followed by its γ, which
+/// start out at 0 and 1. The layer has as many neurons as inputs.
+#[derive(Clone, Debug)]
+pub(crate) struct LayerNorm<T = f32> {
+    weights: Vec<T>,
+    activation: Activation,
+}
+
+impl<T: Scalar> LayerNorm<T> {
+    pub(crate) fn new(weights: Vec<T>, activation: Activation) -> Self {
+        assert_eq!(weights.len() % 2, 0);
+
+        Self {
+            weights,
+            activation,
+        }
+    }
+
+    pub(crate) fn neurons(&self) -> usize {
+        self.weights.len() / 2
+    }
+
+    pub(crate) fn activation(&self) -> Activation {
+        self.activation
+    }
+
+    pub(crate) fn weights(&self) -> &[T] {
+        &self.weights
+    }
+
+    pub(crate) fn weights_mut(&mut self) -> &mut [T] {
+        &mut self.weights
+    }
+
+    /// The given neuron's β followed by its γ.
+    pub(crate) fn row(&self, neuron: usize) -> &[T] {
+        &self.weights[2 * neuron..][..2]
+    }
+
+    pub(crate) fn propagate_into(&self, inputs: &[T], outputs: &mut Vec<T>) {
+        self.step(inputs, None, outputs);
+    }
+
+    pub(crate) fn trace_into(
+        &self,
+        inputs: &[T],
+        pre_activations: &mut Vec<T>,
+        outputs: &mut Vec<T>,
+    ) {
+        pre_activations.clear();
+        self.step(inputs, Some(pre_activations), outputs);
+    }
+
+    /// Appends the normalized, scaled and shifted `inputs` of a single
+    /// input vector to `values`.
+    pub(crate) fn normalize(&self, inputs: &[T], values: &mut Vec<T>) {
+        let (mean, deviation) = statistics(inputs);
+
+        for (neuron, &input) in inputs.iter().enumerate() {
+            let &[beta, gamma] = self.row(neuron) else {
+                unreachable!()
+            };
+
+            values.push(gamma * (input - mean) / deviation + beta);
+        }
+    }
+
+    fn step(&self, inputs: &[T], mut pre_activations: Option<&mut Vec<T>>, outputs: &mut Vec<T>) {
+        let neurons = self.neurons();
+        assert_eq!(inputs.len() % neurons, 0);
+
+        outputs.clear();
+
+        for inputs in inputs.chunks_exact(neurons) {
+            let start = outputs.len();
+            self.normalize(inputs, outputs);
+
+            if let Some(pre_activations) = pre_activations.as_deref_mut() {
+                pre_activations.extend_from_slice(&outputs[start..]);
+            }
+
+            for output in &mut outputs[start..] {
+                *output = self.activation.apply(*output);
+            }
+        }
+    }
+}
+
+impl LayerNorm {
+    #[cfg(feature = "rand")]
+    pub(crate) fn identity(neurons: usize, activation: Activation) -> Self {
+        Self::new([0.0, 1.0].repeat(neurons), activation)
+    }
+
+    /// Writes the gradients of this layer's weights into `gradients`, given
+    /// the loss' derivative with respect to every neuron's weighted sum, and
+    /// returns the derivative with respect to every input.
+    pub(crate) fn backward(
+        &self,
+        inputs: &[f32],
+        deltas: &[f32],
+        gradients: &mut [f32],
+    ) -> Vec<f32> {
+        let (mean, deviation) = statistics(inputs);
+        let normalized: Vec<f32> = inputs.iter().map(|x| (x - mean) / deviation).collect();
+
+        //Derivative with respect to every normalized input
+        let mut scaled = Vec::with_capacity(inputs.len());
+
+        for (neuron, (&delta, &normalized)) in deltas.iter().zip(&normalized).enumerate() {
+            gradients[2 * neuron] = delta;
+            gradients[2 * neuron + 1] = delta * normalized;
+            scaled.push(delta * self.row(neuron)[1]);
+        }
+
+        let n = inputs.len() as f32;
+        let scaled_mean = scaled.iter().sum::<f32>() / n;
+        let projection = scaled
+            .iter()
+            .zip(&normalized)
+            .map(|(s, x)| s * x)
+            .sum::<f32>()
+            / n;
+
+        scaled
+            .iter()
+            .zip(&normalized)
+            .map(|(s, x)| (s - scaled_mean - x * projection) / deviation)
+            .collect()
+    }
+}
+
+/// Batch normalization (Ioffe & Szegedy, 2015): every neuron shifts and
+/// scales its input to zero mean and unit variance across examples, then
+/// applies a learned scale (γ) and shift (β).
+///
+/// Every row of the weights holds a neuron's β followed by its γ, which
+/// start out at 0 and 1. The running mean and variance of every neuron's
+/// input, starting out at 0 and 1, aren't weights: they're estimated from
+/// the inputs rather than evolved or trained, and only kept along with the
+/// weights when a network is saved.
+///
+/// In `Mode::Inference`, inputs are normalized with the running statistics.
+/// In `Mode::Training`, a batch of several input vectors is normalized with
+/// its own statistics instead, which then update the running ones; single
+/// input vectors still use the running statistics.
+///
+/// `Network::train_batch` backpropagates through whichever statistics the
+/// batch was normalized with. The batch's own depend on every input vector,
+/// so the gradients account for that; the running ones are constants.
+/// Either way, only β and γ are learned.
+#[derive(Clone, Debug)]
+pub(crate) struct BatchNorm<T = f32> {
+    weights: Vec<T>,
+    //Running mean and variance of every neuron's input
+    statistics: Vec<T>,
+    activation: Activation,
+    training: bool,
+    //Statistics of the current batch, one mean and variance per neuron
+    batch: Vec<T>,
+}
+
+impl<T: Scalar> BatchNorm<T> {
+    pub(crate) fn new(weights: Vec<T>, activation: Activation) -> Self {
+        assert_eq!(weights.len() % 2, 0);
+
+        let statistics = [T::ZERO, T::ONE].repeat(weights.len() / 2);
+
+        Self {
+            weights,
+            statistics,
+            activation,
+            training: false,
+            batch: Vec::new(),
+        }
+    }
+
+    pub(crate) fn neurons(&self) -> usize {
+        self.weights.len() / 2
+    }
+
+    pub(crate) fn activation(&self) -> Activation {
+        self.activation
+    }
+
+    pub(crate) fn weights(&self) -> &[T] {
+        &self.weights
+    }
+
+    pub(crate) fn weights_mut(&mut self) -> &mut [T] {
+        &mut self.weights
+    }
+
+    /// The given neuron's β followed by its γ.
+    pub(crate) fn row(&self, neuron: usize) -> &[T] {
+        &self.weights[2 * neuron..][..2]
+    }
+
+    /// Every neuron's running mean followed by its running variance.
+    pub(crate) fn statistics(&self) -> &[T] {
+        &self.statistics
+    }
+
+    pub(crate) fn statistics_mut(&mut self) -> &mut [T] {
+        &mut self.statistics
+    }
+
+    pub(crate) fn set_mode(&mut self, mode: Mode) {
+        self.training = mode == Mode::Training;
+    }
+
+    /// Whether a batch of `examples` input vectors is normalized with its
+    /// own statistics rather than the running ones.
+    pub(crate) fn normalizes_batch(&self, examples: usize) -> bool {
+        self.training && examples > 1
+    }
+
+    pub(crate) fn propagate_into(&mut self, inputs: &[T], outputs: &mut Vec<T>) {
+        self.step(inputs, None, outputs);
+    }
+
+    pub(crate) fn trace_into(
+        &mut self,
+        inputs: &[T],
+        pre_activations: &mut Vec<T>,
+        outputs: &mut Vec<T>,
+    ) {
+        pre_activations.clear();
+        self.step(inputs, Some(pre_activations), outputs);
+    }
+
+    /// Appends a single input vector, normalized with the running
+    /// statistics, scaled and shifted, to `values`.
+    pub(crate) fn normalize(&self, inputs: &[T], values: &mut Vec<T>) {
+        let epsilon = T::from_f64(EPSILON);
+
+        for (neuron, &input) in inputs.iter().enumerate() {
+            let (&[beta, gamma], &[mean, variance]) =
+                (self.row(neuron), &self.statistics[2 * neuron..][..2])
+            else {
+                unreachable!()
+            };
+
+            values.push(gamma * (input - mean) / (variance + epsilon).sqrt() + beta);
+        }
+    }
+
+    fn step(&mut self, inputs: &[T], pre_activations: Option<&mut Vec<T>>, outputs: &mut Vec<T>) {
+        let neurons = self.neurons();
+        assert_eq!(inputs.len() % neurons, 0);
+
+        outputs.clear();
+
+        if self.normalizes_batch(inputs.len() / neurons) {
+            self.normalize_batch(inputs, outputs);
+        } else {
+            for inputs in inputs.chunks_exact(neurons) {
+                self.normalize(inputs, outputs);
+            }
+        }
+
+        if let Some(pre_activations) = pre_activations {
+            pre_activations.extend_from_slice(outputs);
+        }
+
+        for output in outputs.iter_mut() {
+            *output = self.activation.apply(*output);
+        }
+    }
+
+    fn normalize_batch(&mut self, inputs: &[T], outputs: &mut Vec<T>) {
+        let neurons = self.neurons();
+        let epsilon = T::from_f64(EPSILON);
+        let momentum = T::from_f64(MOMENTUM);
+
+        self.batch.clear();
+
+        for neuron in 0..neurons {
+            let column = || inputs.iter().skip(neuron).step_by(neurons).copied();
+            let (mean, variance) = moments(column());
+
+            self.batch.extend([mean, variance]);
+        }
+
+        for inputs in inputs.chunks_exact(neurons) {
+            for (neuron, &input) in inputs.iter().enumerate() {
+                let &[beta, gamma] = self.row(neuron) else {
+                    unreachable!()
+                };
+
+                let (mean, variance) = (self.batch[2 * neuron], self.batch[2 * neuron + 1]);
+
+                outputs.push(gamma * (input - mean) / (variance + epsilon).sqrt() + beta);
+            }
+        }
+
+        for (running, batch) in self.statistics.iter_mut().zip(&self.batch) {
+            *running = *running + momentum * (*batch - *running);
+        }
+    }
+}
+
+impl BatchNorm {
+    #[cfg(feature = "rand")]
+    pub(crate) fn identity(neurons: usize, activation: Activation) -> Self {
+        Self::new([0.0, 1.0].repeat(neurons), activation)
+    }
+
+    /// Like `LayerNorm::backward`, treating the running statistics as
+    /// constants: they're learned from the batches, not by gradient descent.
+    pub(crate) fn backward(
+        &self,
+        inputs: &[f32],
+        deltas: &[f32],
+        gradients: &mut [f32],
+    ) -> Vec<f32> {
+        inputs
+            .iter()
+            .zip(deltas)
+            .enumerate()
+            .map(|(neuron, (&input, &delta))| {
+                let (&[_, gamma], &[mean, variance]) =
+                    (self.row(neuron), &self.statistics[2 * neuron..][..2])
+                else {
+                    unreachable!()
+                };
+
+                let deviation = (variance + EPSILON as f32).sqrt();
+
+                gradients[2 * neuron] = delta;
+                gradients[2 * neuron + 1] = delta * (input - mean) / deviation;
+
+                delta * gamma / deviation
+            })
+            .collect()
+    }
+
+    /// Like `backward`, for the batch of input vectors that was last
+    /// normalized with its own statistics: since every input moved the
+    /// batch's mean and variance, every input's derivative depends on all
+    /// of the deltas.
+    pub(crate) fn backward_batch(
+        &self,
+        inputs: &[f32],
+        deltas: &[f32],
+        gradients: &mut [f32],
+    ) -> Vec<f32> {
+        let neurons = self.neurons();
+        let n = (inputs.len() / neurons) as f32;
+        let mut input_deltas = vec![0.0; inputs.len()];
+
+        for neuron in 0..neurons {
+            let (&[_, gamma], &[mean, variance]) =
+                (self.row(neuron), &self.batch[2 * neuron..][..2])
+            else {
+                unreachable!()
+            };
+
+            let deviation = (variance + EPSILON as f32).sqrt();
+            let column = (neuron..inputs.len()).step_by(neurons);
+            let normalized = |idx: usize| (inputs[idx] - mean) / deviation;
+
+            let delta_sum: f32 = column.clone().map(|idx| deltas[idx]).sum();
+            let projection: f32 = column
+                .clone()
+                .map(|idx| deltas[idx] * normalized(idx))
+                .sum();
+
+            gradients[2 * neuron] = delta_sum;
+            gradients[2 * neuron + 1] = projection;
+
+            for idx in column {
+                input_deltas[idx] = gamma
+                    * (deltas[idx] - (delta_sum + normalized(idx) * projection) / n)
+                    / deviation;
+            }
+        }
+
+        input_deltas
+    }
+}
+
+impl<T: Scalar> Network<T> {
+    /// Running mean and variance of every batch normalization neuron, one
+    /// layer after another; see `BatchNorm`.
+    pub(crate) fn statistics(&self) -> Vec<T> {
+        self.layers
+            .iter()
+            .flat_map(|layer| match layer {
+                Layer::BatchNorm(layer) => layer.statistics(),
+                _ => &[],
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Replaces the running statistics, laid out like `statistics()`.
+    pub(crate) fn set_statistics(&mut self, statistics: &[T]) {
+        assert_eq!(statistics.len(), self.statistics().len());
+
+        let mut statistics = statistics.iter();
+
+        for layer in &mut self.layers {
+            if let Layer::BatchNorm(layer) = layer {
+                for (running, &value) in layer.statistics_mut().iter_mut().zip(&mut statistics) {
+                    *running = value;
+                }
+            }
+        }
+    }
+}
+
+//Mean and standard deviation of the values
+fn statistics<T: Scalar>(values: &[T]) -> (T, T) {
+    let (mean, variance) = moments(values.iter().copied());
+
+    (mean, (variance + T::from_f64(EPSILON)).sqrt())
+}
+
+//Mean and variance of the values
+fn moments<T: Scalar>(values: impl Iterator<Item = T> + Clone) -> (T, T) {
+    let n = T::from_f64(values.clone().count() as f64);
+    let mean = values.clone().sum::<T>() / n;
+    let variance = values
+        .map(|value| (value - mean) * (value - mean))
+        .sum::<T>()
+        / n;
+
+    (mean, variance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn propagate(layer: &mut BatchNorm, inputs: &[f32]) -> Vec<f32> {
+        let mut outputs = Vec::new();
+        layer.propagate_into(inputs, &mut outputs);
+        outputs
+    }
+
+    #[test]
+    fn test_layer_norm() {
+        let layer = LayerNorm::new(vec![0.0, 1.0, 0.0, 1.0, 1.0, 2.0], Activation::Identity);
+        let mut outputs = Vec::new();
+
+        //Mean 2, variance 2/3
+        layer.propagate_into(&[1.0, 2.0, 3.0], &mut outputs);
+
+        let deviation = (2.0f32 / 3.0 + 1e-5).sqrt();
+        assert_relative_eq!(
+            outputs.as_slice(),
+            [-1.0 / deviation, 0.0, 1.0 + 2.0 / deviation].as_slice(),
+            max_relative = 1e-5
+        );
+
+        //Scaling the inputs changes nothing
+        let scaled = outputs.clone();
+        layer.propagate_into(&[10.0, 20.0, 30.0], &mut outputs);
+        assert_relative_eq!(outputs.as_slice(), scaled.as_slice(), max_relative = 1e-4);
+    }
+
+    #[test]
+    fn test_batch_norm() {
+        let mut layer = BatchNorm::identity(2, Activation::Identity);
+
+        //The running statistics start out as the identity
+        assert_relative_eq!(
+            propagate(&mut layer, &[3.0, -1.0]).as_slice(),
+            [3.0, -1.0].as_slice(),
+            max_relative = 1e-4
+        );
+
+        layer.set_mode(Mode::Training);
+
+        //The first neuron sees 1 and 3, the second 10 and 10
+        let outputs = propagate(&mut layer, &[1.0, 10.0, 3.0, 10.0]);
+        assert_relative_eq!(
+            outputs.as_slice(),
+            [-1.0, 0.0, 1.0, 0.0].as_slice(),
+            max_relative = 1e-4
+        );
+
+        //Running means moved a tenth of the way, and so did the variances,
+        //while the weights stayed put
+        assert_relative_eq!(layer.statistics(), [0.2, 1.0, 1.0, 0.9].as_slice());
+        assert_eq!(layer.weights(), [0.0, 1.0, 0.0, 1.0]);
+
+        layer.set_mode(Mode::Inference);
+        propagate(&mut layer, &[1.0, 10.0, 3.0, 10.0]);
+        assert_eq!(layer.statistics()[0], 0.2);
+    }
+
+    #[test]
+    fn test_backward() {
+        let layer = LayerNorm::new(vec![0.1, 1.5, -0.2, 0.5, 0.0, 2.0], Activation::Identity);
+        let inputs = [0.3, -0.8, 1.1];
+        let deltas = [0.5, -1.0, 0.25];
+
+        let mut gradients = vec![0.0; 6];
+        let input_deltas = layer.backward(&inputs, &deltas, &mut gradients);
+
+        //Against finite differences of Σ delta * output
+        let objective = |inputs: &[f32]| {
+            let mut outputs = Vec::new();
+            layer.normalize(inputs, &mut outputs);
+            outputs.iter().zip(&deltas).map(|(o, d)| o * d).sum::<f32>()
+        };
+
+        for input in 0..3 {
+            let h = 1e-2;
+            let mut nudged = inputs;
+
+            nudged[input] += h;
+            let above = objective(&nudged);
+
+            nudged[input] -= 2.0 * h;
+            let below = objective(&nudged);
+
+            assert_relative_eq!(
+                input_deltas[input],
+                (above - below) / (2.0 * h),
+                epsilon = 1e-3
+            );
+        }
+
+        assert_eq!(gradients[0], 0.5);
+    }
+
+    #[test]
+    fn test_batch_backward() {
+        let mut layer = BatchNorm::new(vec![0.1, 1.5, -0.2, 0.5], Activation::Identity);
+        layer.set_mode(Mode::Training);
+
+        //Three input vectors of two values each
+        let inputs = [0.3, -0.8, 1.1, 0.2, -0.5, 0.9];
+        let deltas = [0.5, -1.0, 0.25, 0.75, -0.5, 0.1];
+
+        propagate(&mut layer, &inputs);
+
+        let mut gradients = vec![0.0; 4];
+        let input_deltas = layer.backward_batch(&inputs, &deltas, &mut gradients);
+
+        //Against finite differences of Σ delta * output, with the statistics
+        //of the nudged batch
+        let objective = |inputs: &[f32]| {
+            let outputs = propagate(&mut layer.clone(), inputs);
+            outputs.iter().zip(&deltas).map(|(o, d)| o * d).sum::<f32>()
+        };
+
+        for input in 0..inputs.len() {
+            let h = 1e-2;
+            let mut nudged = inputs;
+
+            nudged[input] += h;
+            let above = objective(&nudged);
+
+            nudged[input] -= 2.0 * h;
+            let below = objective(&nudged);
+
+            assert_relative_eq!(
+                input_deltas[input],
+                (above - below) / (2.0 * h),
+                epsilon = 1e-3
+            );
+        }
+
+        //β's gradient sums up the first neuron's deltas
+        assert_relative_eq!(gradients[0], 0.5 + 0.25 - 0.5);
+    }
+}
@@ -0,0 +1,327 @@
+use crate::*;
+
+/// Turns gradients into parameter updates.
+///
+/// Both `params` and `grads` follow the flat layout produced by
+/// `Network::weights()` - for every neuron its bias, then its weights - and
+/// optimizers that keep per-parameter state (momentum, running averages)
+/// store it in that same layout.
+pub trait Optimizer {
+    fn step(&mut self, params: &mut [f32], grads: &[f32]);
+
+    fn learning_rate(&self) -> f32;
+
+    fn set_learning_rate(&mut self, learning_rate: f32);
+}
+
+/// Plain stochastic gradient descent.
+#[derive(Clone, Debug)]
+pub struct Sgd {
+    learning_rate: f32,
+}
+
+impl Sgd {
+    pub fn new(learning_rate: f32) -> Self {
+        assert!(learning_rate > 0.0);
+
+        Self { learning_rate }
+    }
+}
+
+impl Optimizer for Sgd {
+    fn step(&mut self, params: &mut [f32], grads: &[f32]) {
+        assert_eq!(params.len(), grads.len());
+
+        for (param, grad) in params.iter_mut().zip(grads) {
+            *param -= self.learning_rate * grad;
+        }
+    }
+
+    fn learning_rate(&self) -> f32 {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: f32) {
+        self.learning_rate = learning_rate;
+    }
+}
+
+/// SGD with (classical) momentum.
+#[derive(Clone, Debug)]
+pub struct Momentum {
+    learning_rate: f32,
+    momentum: f32,
+    velocity: Vec<f32>,
+}
+
+impl Momentum {
+    pub fn new(learning_rate: f32, momentum: f32) -> Self {
+        assert!(learning_rate > 0.0);
+        assert!((0.0..1.0).contains(&momentum));
+
+        Self {
+            learning_rate,
+            momentum,
+            velocity: Vec::new(),
+        }
+    }
+}
+
+impl Optimizer for Momentum {
+    fn step(&mut self, params: &mut [f32], grads: &[f32]) {
+        assert_eq!(params.len(), grads.len());
+        init_state(&mut self.velocity, params.len());
+
+        for ((param, grad), velocity) in params.iter_mut().zip(grads).zip(&mut self.velocity) {
+            *velocity = self.momentum * *velocity - self.learning_rate * grad;
+            *param += *velocity;
+        }
+    }
+
+    fn learning_rate(&self) -> f32 {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: f32) {
+        self.learning_rate = learning_rate;
+    }
+}
+
+/// RMSProp: divides every step by a running average of the gradient's
+/// magnitude.
+#[derive(Clone, Debug)]
+pub struct RmsProp {
+    learning_rate: f32,
+    decay: f32,
+    epsilon: f32,
+    mean_square: Vec<f32>,
+}
+
+impl RmsProp {
+    pub fn new(learning_rate: f32, decay: f32) -> Self {
+        assert!(learning_rate > 0.0);
+        assert!((0.0..1.0).contains(&decay));
+
+        Self {
+            learning_rate,
+            decay,
+            epsilon: 1e-8,
+            mean_square: Vec::new(),
+        }
+    }
+}
+
+impl Optimizer for RmsProp {
+    fn step(&mut self, params: &mut [f32], grads: &[f32]) {
+        assert_eq!(params.len(), grads.len());
+        init_state(&mut self.mean_square, params.len());
+
+        for ((param, grad), mean_square) in params.iter_mut().zip(grads).zip(&mut self.mean_square)
+        {
+            *mean_square = self.decay * *mean_square + (1.0 - self.decay) * grad * grad;
+            *param -= self.learning_rate * grad / (mean_square.sqrt() + self.epsilon);
+        }
+    }
+
+    fn learning_rate(&self) -> f32 {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: f32) {
+        self.learning_rate = learning_rate;
+    }
+}
+
+/// Adam, with the usual bias correction of both moments.
+#[derive(Clone, Debug)]
+pub struct Adam {
+    learning_rate: f32,
+    beta1: f32,
+    beta2: f32,
+    epsilon: f32,
+    steps: i32,
+    first_moment: Vec<f32>,
+    second_moment: Vec<f32>,
+}
+
+impl Adam {
+    pub fn new(learning_rate: f32) -> Self {
+        Self::with_betas(learning_rate, 0.9, 0.999)
+    }
+
+    pub fn with_betas(learning_rate: f32, beta1: f32, beta2: f32) -> Self {
+        assert!(learning_rate > 0.0);
+        assert!((0.0..1.0).contains(&beta1));
+        assert!((0.0..1.0).contains(&beta2));
+
+        Self {
+            learning_rate,
+            beta1,
+            beta2,
+            epsilon: 1e-8,
+            steps: 0,
+            first_moment: Vec::new(),
+            second_moment: Vec::new(),
+        }
+    }
+}
+
+impl Optimizer for Adam {
+    fn step(&mut self, params: &mut [f32], grads: &[f32]) {
+        assert_eq!(params.len(), grads.len());
+        init_state(&mut self.first_moment, params.len());
+        init_state(&mut self.second_moment, params.len());
+
+        self.steps += 1;
+
+        let correction1 = 1.0 - self.beta1.powi(self.steps);
+        let correction2 = 1.0 - self.beta2.powi(self.steps);
+
+        for (idx, (param, grad)) in params.iter_mut().zip(grads).enumerate() {
+            let m = &mut self.first_moment[idx];
+            let v = &mut self.second_moment[idx];
+
+            *m = self.beta1 * *m + (1.0 - self.beta1) * grad;
+            *v = self.beta2 * *v + (1.0 - self.beta2) * grad * grad;
+
+            let m = *m / correction1;
+            let v = *v / correction2;
+
+            *param -= self.learning_rate * m / (v.sqrt() + self.epsilon);
+        }
+    }
+
+    fn learning_rate(&self) -> f32 {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: f32) {
+        self.learning_rate = learning_rate;
+    }
+}
+
+/// How gradients get clipped before reaching an optimizer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Clip {
+    /// Clamps every gradient to `[-limit, limit]`.
+    Value(f32),
+    /// Rescales the whole gradient vector so that its L2 norm is at most
+    /// `limit`.
+    Norm(f32),
+}
+
+impl Clip {
+    pub fn apply(&self, grads: &mut [f32]) {
+        match *self {
+            Self::Value(limit) => {
+                for grad in grads {
+                    *grad = grad.clamp(-limit, limit);
+                }
+            }
+            Self::Norm(limit) => {
+                let norm = grads.iter().map(|grad| grad * grad).sum::<f32>().sqrt();
+
+                if norm > limit {
+                    for grad in grads {
+                        *grad *= limit / norm;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Wraps another optimizer, clipping gradients before every step.
+#[derive(Clone, Debug)]
+pub struct Clipped<O> {
+    optimizer: O,
+    clip: Clip,
+}
+
+impl<O: Optimizer> Clipped<O> {
+    pub fn new(optimizer: O, clip: Clip) -> Self {
+        Self { optimizer, clip }
+    }
+}
+
+impl<O: Optimizer> Optimizer for Clipped<O> {
+    fn step(&mut self, params: &mut [f32], grads: &[f32]) {
+        let mut grads = grads.to_vec();
+        self.clip.apply(&mut grads);
+
+        self.optimizer.step(params, &grads);
+    }
+
+    fn learning_rate(&self) -> f32 {
+        self.optimizer.learning_rate()
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: f32) {
+        self.optimizer.set_learning_rate(learning_rate);
+    }
+}
+
+//Per-parameter state is created lazily, on the first step
+fn init_state(state: &mut Vec<f32>, len: usize) {
+    if state.is_empty() {
+        state.resize(len, 0.0);
+    }
+
+    assert_eq!(state.len(), len, "optimizer used with a different network");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    //Minimizes f(x, y) = x^2 + 10 * y^2, starting from (3, -2)
+    fn minimize(optimizer: &mut dyn Optimizer, steps: usize) -> f32 {
+        let mut params = [3.0, -2.0];
+
+        for _ in 0..steps {
+            let grads = [2.0 * params[0], 20.0 * params[1]];
+            optimizer.step(&mut params, &grads);
+        }
+
+        params[0].powi(2) + 10.0 * params[1].powi(2)
+    }
+
+    #[test]
+    fn test_sgd() {
+        let mut params = [1.0, 2.0];
+        Sgd::new(0.5).step(&mut params, &[1.0, -2.0]);
+
+        assert_relative_eq!(params.as_ref(), [0.5, 3.0].as_ref());
+    }
+
+    #[test]
+    fn test_optimizers_converge() {
+        let optimizers: Vec<Box<dyn Optimizer>> = vec![
+            Box::new(Sgd::new(0.05)),
+            Box::new(Momentum::new(0.02, 0.9)),
+            Box::new(RmsProp::new(0.05, 0.9)),
+            Box::new(Adam::new(0.1)),
+        ];
+
+        for mut optimizer in optimizers {
+            assert!(minimize(optimizer.as_mut(), 300) < 1e-2);
+        }
+    }
+
+    #[test]
+    fn test_clip_value() {
+        let mut grads = [3.0, -0.5, -4.0];
+        Clip::Value(1.0).apply(&mut grads);
+
+        assert_relative_eq!(grads.as_ref(), [1.0, -0.5, -1.0].as_ref());
+    }
+
+    #[test]
+    fn test_clip_norm() {
+        let mut grads = [3.0, 4.0];
+        Clip::Norm(1.0).apply(&mut grads);
+
+        assert_relative_eq!(grads.as_ref(), [0.6, 0.8].as_ref());
+    }
+}
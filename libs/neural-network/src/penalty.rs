@@ -0,0 +1,128 @@
+use crate::*;
+
+/// L1 and L2 penalties on a network's connection weights, favouring small
+/// ones: `l1 * Σ|w| + l2 * Σw²`.
+///
+/// Biases, normalization parameters and Hebbian rules aren't penalized. Pass
+/// one to `Network::train_batch_with_penalty` for weight decay, or subtract
+/// `penalty` from a fitness to make evolution prefer simpler networks.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Penalty {
+    pub l1: f32,
+    pub l2: f32,
+}
+
+impl Penalty {
+    pub fn l1(strength: f32) -> Self {
+        Self {
+            l1: strength,
+            l2: 0.0,
+        }
+    }
+
+    pub fn l2(strength: f32) -> Self {
+        Self {
+            l1: 0.0,
+            l2: strength,
+        }
+    }
+
+    pub fn penalty(&self, network: &Network) -> f32 {
+        connections(network)
+            .map(|(_, weight)| self.l1 * weight.abs() + self.l2 * weight * weight)
+            .sum()
+    }
+
+    /// Derivative of `penalty` with respect to every weight, in the same
+    /// layout as `Network::weights()`.
+    pub fn gradient(&self, network: &Network) -> Vec<f32> {
+        let mut gradient = vec![0.0; network.weights().len()];
+
+        for (idx, weight) in connections(network) {
+            //The L1 term isn't differentiable at zero; it stays put there
+            let sign = if weight == 0.0 { 0.0 } else { weight.signum() };
+
+            gradient[idx] = self.l1 * sign + 2.0 * self.l2 * weight;
+        }
+
+        gradient
+    }
+}
+
+//Connection weights, along with where they are in `Network::weights()`
+fn connections(network: &Network) -> impl Iterator<Item = (usize, f32)> + '_ {
+    network
+        .layers
+        .iter()
+        .scan(0, |offset, layer| {
+            let start = *offset;
+            *offset += layer.weights().len();
+            Some((start, layer))
+        })
+        .filter_map(|(start, layer)| Some((start, layer, layer.rows()?)))
+        .flat_map(|(start, layer, (row_len, rows_len))| {
+            layer.weights()[..rows_len]
+                .iter()
+                .enumerate()
+                .filter(move |(idx, _)| idx % row_len != 0)
+                .map(move |(idx, &weight)| (start + idx, weight))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn network() -> Network {
+        let topology = [
+            LayerTopology::new(2),
+            LayerTopology::new(1).with_activation(Activation::Identity),
+            LayerTopology::new(1)
+                .with_activation(Activation::Identity)
+                .with_kind(LayerKind::LayerNorm),
+        ];
+
+        //Bias 5 and weights 1, -2; then the layer norm's β and γ
+        Network::from_weights(&topology, vec![5.0, 1.0, -2.0, 3.0, 4.0])
+    }
+
+    #[test]
+    fn test_penalty() {
+        let network = network();
+
+        assert_eq!(Penalty::default().penalty(&network), 0.0);
+        assert_relative_eq!(Penalty::l1(0.5).penalty(&network), 1.5);
+        assert_relative_eq!(Penalty::l2(0.5).penalty(&network), 2.5);
+
+        assert_relative_eq!(
+            Penalty { l1: 1.0, l2: 1.0 }.gradient(&network).as_slice(),
+            [0.0, 3.0, -5.0, 0.0, 0.0].as_slice()
+        );
+    }
+
+    #[test]
+    fn test_weight_decay() {
+        let topology = [
+            LayerTopology::new(1),
+            LayerTopology::new(1).with_activation(Activation::Identity),
+        ];
+
+        //The output doesn't depend on the weight, so only the penalty moves it
+        let mut network = Network::from_weights(&topology, vec![0.0, 1.0]);
+        let mut optimizer = Sgd::new(0.1);
+
+        let loss = network
+            .train_batch_with_penalty(
+                &[vec![0.0]],
+                &[vec![0.0]],
+                Loss::MeanSquaredError,
+                Penalty::l2(1.0),
+                &mut optimizer,
+            )
+            .unwrap();
+
+        assert_relative_eq!(loss, 1.0);
+        assert_relative_eq!(network.weights().as_slice(), [0.0, 0.8].as_slice());
+    }
+}
@@ -0,0 +1,268 @@
+use crate::*;
+
+impl Network {
+    /// Zeroes every weight whose magnitude is below `threshold`, returning
+    /// how many were zeroed. Biases are left alone.
+    pub fn prune_by_magnitude(&mut self, threshold: f32) -> usize {
+        let mut pruned = 0;
+
+        for layer in &mut self.layers {
+            let Some((row_len, rows_len)) = layer.rows() else {
+                continue;
+            };
+
+            for row in layer.weights_mut()[..rows_len].chunks_exact_mut(row_len) {
+                for weight in &mut row[1..] {
+                    if *weight != 0.0 && weight.abs() < threshold {
+                        *weight = 0.0;
+                        pruned += 1;
+                    }
+                }
+            }
+        }
+
+        pruned
+    }
+
+    /// Zeroes every weight of a dense layer whose contribution to its
+    /// neuron's weighted sum - the weight times the input it's applied to -
+    /// averages below `threshold` in magnitude over the `probes`. Returns how
+    /// many weights were zeroed.
+    ///
+    /// Unlike `prune_by_magnitude`, this keeps small weights on inputs that
+    /// tend to be large, and drops large ones on inputs that are rarely set.
+    pub fn prune_by_activity(&mut self, probes: &[Vec<f32>], threshold: f32) -> usize {
+        let traces = self.probe(probes);
+        let mut pruned = 0;
+
+        for (index, layer) in self.layers.iter_mut().enumerate() {
+            let Some(layer) = layer.as_dense_mut() else {
+                continue;
+            };
+
+            let inputs = layer.inputs;
+
+            for row in layer.weights.chunks_exact_mut(inputs + 1) {
+                for (input, weight) in row[1..].iter_mut().enumerate() {
+                    if *weight == 0.0 {
+                        continue;
+                    }
+
+                    let activity = traces
+                        .iter()
+                        .map(|trace| (layer_inputs(trace, index)[input] * *weight).abs())
+                        .sum::<f32>()
+                        / traces.len() as f32;
+
+                    if activity < threshold {
+                        *weight = 0.0;
+                        pruned += 1;
+                    }
+                }
+            }
+        }
+
+        pruned
+    }
+
+    /// Removes hidden neurons that can't affect the network's output,
+    /// returning how many were removed:
+    ///
+    /// - neurons with no (non-zero) outgoing weights,
+    /// - neurons whose output is the same for every one of the `probes`,
+    ///   such as ReLU units that never fire; the next layer's biases absorb
+    ///   that constant output.
+    ///
+    /// `propagate` then gives the same results for the probes, and for any
+    /// other input that doesn't wake up a neuron the probes never did. Only
+    /// neurons between two dense layers are removed, and every layer keeps at
+    /// least one neuron.
+    pub fn remove_dead_neurons(&mut self, probes: &[Vec<f32>]) -> usize {
+        let traces = self.probe(probes);
+        let mut removed = 0;
+
+        for index in 0..self.layers.len() - 1 {
+            let (front, back) = self.layers.split_at_mut(index + 1);
+
+            let (Some(layer), Some(next)) = (front[index].as_dense_mut(), back[0].as_dense_mut())
+            else {
+                continue;
+            };
+
+            let outputs = |neuron: usize| {
+                traces
+                    .iter()
+                    .map(move |trace| trace.layers[index].outputs[neuron])
+            };
+
+            let mut dead: Vec<(usize, f32)> = (0..layer.neurons())
+                .filter_map(|neuron| {
+                    let unused = (0..next.neurons()).all(|n| next.row(n)[1 + neuron] == 0.0);
+
+                    let mut outputs = outputs(neuron);
+                    let first = outputs.next();
+                    let constant = first.filter(|&first| outputs.all(|output| output == first));
+
+                    match (unused, constant) {
+                        (true, _) => Some((neuron, 0.0)),
+                        (false, Some(output)) => Some((neuron, output)),
+                        (false, None) => None,
+                    }
+                })
+                .collect();
+
+            if dead.len() == layer.neurons() {
+                dead.remove(0);
+            }
+
+            //Back to front, so that the remaining indices stay valid
+            for &(neuron, output) in dead.iter().rev() {
+                remove_neuron(layer, next, neuron, output);
+            }
+
+            removed += dead.len();
+        }
+
+        removed
+    }
+
+    //Traces every probe from a fresh state
+    fn probe(&self, probes: &[Vec<f32>]) -> Vec<Trace> {
+        let mut network = self.clone();
+
+        probes
+            .iter()
+            .map(|probe| {
+                network.reset_state();
+                network.propagate_traced(probe.clone())
+            })
+            .collect()
+    }
+}
+
+fn layer_inputs(trace: &Trace, layer: usize) -> &[f32] {
+    match layer {
+        0 => &trace.inputs,
+        layer => &trace.layers[layer - 1].outputs,
+    }
+}
+
+//Drops `neuron` from `layer`, and the matching input from `next`, whose
+//biases take over the neuron's constant `output`
+fn remove_neuron(layer: &mut Dense, next: &mut Dense, neuron: usize, output: f32) {
+    let row_len = layer.inputs + 1;
+    layer
+        .weights
+        .drain(neuron * row_len..(neuron + 1) * row_len);
+
+    let weights = next
+        .weights
+        .chunks_exact(next.inputs + 1)
+        .flat_map(|row| {
+            let bias = row[0] + output * row[1 + neuron];
+
+            core::iter::once(bias)
+                .chain(row[1..1 + neuron].iter().copied())
+                .chain(row[2 + neuron..].iter().copied())
+        })
+        .collect();
+
+    next.weights = weights;
+    next.inputs -= 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn network() -> Network {
+        let topology = [
+            LayerTopology::new(2),
+            LayerTopology::new(3),
+            LayerTopology::new(2).with_activation(Activation::Identity),
+        ];
+
+        #[rustfmt::skip]
+        let weights = [
+            //Fires for positive inputs
+            0.0, 1.0, 0.5,
+            //Never fires for inputs in [0, 1]
+            -5.0, 1.0, 1.0,
+            //Fires, but nobody listens
+            0.2, 0.01, 2.0,
+
+            0.1, 1.0, 3.0, 0.0,
+            -0.3, -2.0, 0.5, 0.0,
+        ];
+
+        Network::from_weights(&topology, weights)
+    }
+
+    fn probes() -> Vec<Vec<f32>> {
+        vec![vec![0.0, 1.0], vec![0.5, 0.5], vec![1.0, 0.2]]
+    }
+
+    #[test]
+    fn test_prune_by_magnitude() {
+        let mut network = network();
+
+        assert_eq!(network.prune_by_magnitude(0.6), 3);
+        assert_eq!(network.weights()[1..3], [1.0, 0.0]);
+        //Biases stay
+        assert_eq!(network.weights()[6], 0.2);
+        assert_eq!(network.weights()[8], 2.0);
+    }
+
+    #[test]
+    fn test_prune_by_activity() {
+        let mut network = network();
+
+        //The third neuron's first weight contributes 0.005 on average, and
+        //the second neuron never fires, so nothing it feeds into counts
+        assert_eq!(network.prune_by_activity(&probes(), 0.01), 3);
+
+        let weights = network.weights();
+        assert_eq!([weights[7], weights[11], weights[15]], [0.0; 3]);
+        assert_eq!(weights[8], 2.0);
+    }
+
+    #[test]
+    fn test_remove_dead_neurons() {
+        let mut network = network();
+        let expected: Vec<_> = probes()
+            .into_iter()
+            .map(|probe| network.propagate(probe))
+            .collect();
+
+        assert_eq!(network.remove_dead_neurons(&probes()), 2);
+        assert_eq!(network.topology()[1].neurons, 1);
+
+        for (probe, expected) in probes().into_iter().zip(expected) {
+            assert_relative_eq!(network.propagate(probe).as_slice(), expected.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_constant_outputs_are_folded() {
+        let topology = [
+            LayerTopology::new(1),
+            LayerTopology::new(2).with_activation(Activation::Sigmoid),
+            LayerTopology::new(1).with_activation(Activation::Identity),
+        ];
+
+        //The second hidden neuron ignores its input, so it's always 0.5
+        let mut network = Network::from_weights(&topology, [0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 4.0]);
+
+        assert_eq!(network.remove_dead_neurons(&probes_1d()), 1);
+        assert_relative_eq!(network.weights()[2], 1.0 + 4.0 * 0.5);
+        assert_relative_eq!(
+            network.propagate(vec![0.3])[0],
+            1.0 + (1.0 / (1.0 + (-0.3f32).exp())) + 2.0
+        );
+    }
+
+    fn probes_1d() -> Vec<Vec<f32>> {
+        vec![vec![-1.0], vec![0.3], vec![2.0]]
+    }
+}
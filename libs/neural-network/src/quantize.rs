@@ -0,0 +1,516 @@
+use crate::*;
+
+/// Affine mapping between `f32`s and `i8`s:
+/// `value = scale * (quantized - zero_point)`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Quantization {
+    pub scale: f32,
+    pub zero_point: i8,
+}
+
+impl Quantization {
+    /// Spreads the 256 levels of an `i8` over the range of `values`.
+    ///
+    /// The range is widened to include zero, so that zero - e.g. a ReLU's
+    /// output - is always represented exactly.
+    pub fn fit(values: &[f32]) -> Self {
+        let min = values.iter().copied().fold(0.0, f32::min);
+        let max = values.iter().copied().fold(0.0, f32::max);
+
+        let scale = (max - min) / 255.0;
+
+        //Also covers ranges too narrow or too wide for an `f32` scale, which
+        //a `QuantizedLayer` couldn't be deserialized with
+        if scale == 0.0 || !scale.is_finite() {
+            return Self {
+                scale: 1.0,
+                zero_point: 0,
+            };
+        }
+        let zero_point = (-128.0 - min / scale).round().clamp(-128.0, 127.0) as i8;
+
+        Self { scale, zero_point }
+    }
+
+    pub fn quantize(&self, value: f32) -> i8 {
+        (value / self.scale + self.zero_point as f32)
+            .round()
+            .clamp(-128.0, 127.0) as i8
+    }
+
+    pub fn dequantize(&self, value: i8) -> f32 {
+        self.scale * (value as i32 - self.zero_point as i32) as f32
+    }
+}
+
+/// Dense layer of a `QuantizedNetwork`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedLayer")]
+pub struct QuantizedLayer {
+    inputs: usize,
+    //Row-major `neurons x inputs`; unlike in `Dense`, biases are kept apart
+    weights: Vec<i8>,
+    biases: Vec<f32>,
+    quantization: Quantization,
+    activation: Activation,
+}
+
+impl QuantizedLayer {
+    fn new(layer: &Dense) -> Self {
+        let weights: Vec<_> = (0..layer.neurons())
+            .flat_map(|neuron| layer.row(neuron)[1..].iter().copied())
+            .collect();
+
+        let quantization = Quantization::fit(&weights);
+
+        Self {
+            inputs: layer.inputs,
+            weights: weights.iter().map(|&w| quantization.quantize(w)).collect(),
+            biases: (0..layer.neurons())
+                .map(|neuron| layer.row(neuron)[0])
+                .collect(),
+            quantization,
+            activation: layer.activation,
+        }
+    }
+
+    pub fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    pub fn neurons(&self) -> usize {
+        self.biases.len()
+    }
+
+    /// Every neuron's quantized weights, one row after another.
+    pub fn weights(&self) -> &[i8] {
+        &self.weights
+    }
+
+    pub fn biases(&self) -> &[f32] {
+        &self.biases
+    }
+
+    /// How `weights` map back to `f32`s.
+    pub fn quantization(&self) -> Quantization {
+        self.quantization
+    }
+
+    pub fn activation(&self) -> Activation {
+        self.activation
+    }
+
+    fn propagate(&self, inputs: &[f32]) -> Vec<f32> {
+        assert_eq!(inputs.len(), self.inputs);
+
+        let input = Quantization::fit(inputs);
+        let inputs: Vec<i32> = inputs
+            .iter()
+            .map(|&x| input.quantize(x) as i32 - input.zero_point as i32)
+            .collect();
+
+        let zero_point = self.quantization.zero_point as i32;
+        let scale = self.quantization.scale * input.scale;
+
+        self.weights
+            .chunks_exact(self.inputs)
+            .zip(&self.biases)
+            .map(|(row, &bias)| {
+                //Both factors are within ±255, so this can't overflow for any
+                //sensible number of inputs
+                let sum: i32 = row
+                    .iter()
+                    .zip(&inputs)
+                    .map(|(&w, &x)| (w as i32 - zero_point) * x)
+                    .sum();
+
+                self.activation.apply(bias + scale * sum as f32)
+            })
+            .collect()
+    }
+}
+
+/// `Network` with its weights quantized to `i8`, taking about a quarter of
+/// the memory.
+///
+/// Every layer's weights share one `Quantization`. Inputs to a layer are
+/// quantized the same way on the fly, so weighted sums are computed with
+/// integer arithmetic only; biases and activations stay in `f32`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedNetwork")]
+pub struct QuantizedNetwork {
+    layers: Vec<QuantizedLayer>,
+}
+
+impl QuantizedNetwork {
+    pub fn propagate(&self, inputs: Vec<f32>) -> Vec<f32> {
+        self.layers
+            .iter()
+            .fold(inputs, |inputs, layer| layer.propagate(&inputs))
+    }
+
+    pub fn layers(&self) -> &[QuantizedLayer] {
+        &self.layers
+    }
+
+    pub fn topology(&self) -> Vec<LayerTopology> {
+        core::iter::once(LayerTopology::new(self.layers[0].inputs))
+            .chain(
+                self.layers.iter().map(|layer| {
+                    LayerTopology::new(layer.neurons()).with_activation(layer.activation)
+                }),
+            )
+            .collect()
+    }
+
+    /// Float network with the quantized weights, i.e. with the rounding
+    /// error baked in.
+    pub fn dequantize(&self) -> Network {
+        let weights = self.layers.iter().flat_map(|layer| {
+            layer
+                .weights
+                .chunks_exact(layer.inputs)
+                .zip(&layer.biases)
+                .flat_map(move |(row, &bias)| {
+                    core::iter::once(bias)
+                        .chain(row.iter().map(move |&w| layer.quantization.dequantize(w)))
+                })
+        });
+
+        Network::from_weights(&self.topology(), weights)
+    }
+
+    /// Measures how far this network's outputs drift from the `original`
+    /// one's over the given inputs.
+    pub fn compare(&self, original: &Network, samples: &[Vec<f32>]) -> QuantizationReport {
+        assert!(!samples.is_empty());
+
+        let mut report = QuantizationReport {
+            samples: samples.len(),
+            max_error: 0.0,
+            mean_error: 0.0,
+            agreement: 0.0,
+        };
+
+        let mut original = original.clone();
+        let mut outputs = 0;
+
+        for sample in samples {
+            original.reset_state();
+
+            let expected = original.propagate(sample.clone());
+            let actual = self.propagate(sample.clone());
+
+            for (expected, actual) in expected.iter().zip(&actual) {
+                let error = (expected - actual).abs();
+
+                report.max_error = report.max_error.max(error);
+                report.mean_error += error;
+            }
+
+            outputs += actual.len();
+
+            if argmax(&expected) == argmax(&actual) {
+                report.agreement += 1.0;
+            }
+        }
+
+        report.mean_error /= outputs as f32;
+        report.agreement /= samples.len() as f32;
+        report
+    }
+}
+
+/// How a `QuantizedNetwork` compares to the network it was made from, see
+/// `QuantizedNetwork::compare`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QuantizationReport {
+    pub samples: usize,
+    /// Largest absolute difference of any single output.
+    pub max_error: f32,
+    /// Absolute difference averaged over every output of every sample.
+    pub mean_error: f32,
+    /// Fraction of samples for which both networks' largest output is the
+    /// same neuron's.
+    pub agreement: f32,
+}
+
+impl Network {
+    /// Quantizes every layer's weights to `i8`, see `QuantizedNetwork`.
+    ///
+    /// Only dense layers are supported; any other one is reported as
+    /// `NetworkError::Unquantizable`.
+    pub fn quantize(&self) -> Result<QuantizedNetwork, NetworkError> {
+        let layers = self
+            .layers
+            .iter()
+            .enumerate()
+            .map(|(idx, layer)| {
+                layer
+                    .as_dense()
+                    .map(QuantizedLayer::new)
+                    .ok_or(NetworkError::Unquantizable { layer: idx + 1 })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(QuantizedNetwork { layers })
+    }
+}
+
+//What a `QuantizedLayer` is deserialized from, before it's checked
+#[derive(Deserialize)]
+struct UncheckedLayer {
+    inputs: usize,
+    weights: Vec<i8>,
+    biases: Vec<f32>,
+    quantization: Quantization,
+    activation: Activation,
+}
+
+impl TryFrom<UncheckedLayer> for QuantizedLayer {
+    type Error = NetworkError;
+
+    fn try_from(layer: UncheckedLayer) -> Result<Self, NetworkError> {
+        //Layers are numbered like in the layer's own topology: 0 are its
+        //inputs, 1 its neurons
+        if layer.inputs == 0 {
+            return Err(NetworkError::ZeroWidthLayer { layer: 0 });
+        }
+
+        if layer.biases.is_empty() {
+            return Err(NetworkError::ZeroWidthLayer { layer: 1 });
+        }
+
+        let scale = layer.quantization.scale;
+
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(NetworkError::InvalidQuantization { layer: 1 });
+        }
+
+        let expected = layer
+            .inputs
+            .checked_mul(layer.biases.len())
+            .ok_or(NetworkError::TopologyTooLarge)?;
+        let found = layer.weights.len();
+
+        if found < expected {
+            return Err(NetworkError::TooFewWeights { expected, found });
+        }
+
+        if found > expected {
+            return Err(NetworkError::TooManyWeights { expected, found });
+        }
+
+        //Indexed like `dequantize().weights()`, where every bias starts a row
+        if let Some(neuron) = layer.biases.iter().position(|bias| bias.is_nan()) {
+            return Err(NetworkError::NanWeight {
+                index: neuron * (layer.inputs + 1),
+            });
+        }
+
+        Ok(Self {
+            inputs: layer.inputs,
+            weights: layer.weights,
+            biases: layer.biases,
+            quantization: layer.quantization,
+            activation: layer.activation,
+        })
+    }
+}
+
+//What a `QuantizedNetwork` is deserialized from, before it's checked
+#[derive(Deserialize)]
+struct UncheckedNetwork {
+    layers: Vec<QuantizedLayer>,
+}
+
+impl TryFrom<UncheckedNetwork> for QuantizedNetwork {
+    type Error = NetworkError;
+
+    fn try_from(network: UncheckedNetwork) -> Result<Self, NetworkError> {
+        if network.layers.is_empty() {
+            return Err(NetworkError::EmptyTopology { layers: 1 });
+        }
+
+        for (idx, pair) in network.layers.windows(2).enumerate() {
+            if pair[1].inputs != pair[0].neurons() {
+                return Err(NetworkError::InputMismatch { layer: idx + 2 });
+            }
+        }
+
+        Ok(Self {
+            layers: network.layers,
+        })
+    }
+}
+
+fn argmax(values: &[f32]) -> Option<usize> {
+    values
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_fit() {
+        let quantization = Quantization::fit(&[-1.0, 0.5, 3.0]);
+
+        assert_relative_eq!(quantization.scale, 4.0 / 255.0);
+        assert_eq!(quantization.quantize(-1.0), -128);
+        assert_eq!(quantization.quantize(3.0), 127);
+        assert_eq!(quantization.dequantize(quantization.quantize(0.0)), 0.0);
+
+        //Only positive values still include zero
+        assert_eq!(Quantization::fit(&[2.0, 4.0]).quantize(0.0), -128);
+        assert_eq!(Quantization::fit(&[0.0, 0.0]).scale, 1.0);
+        assert_eq!(Quantization::fit(&[f32::from_bits(1)]).scale, 1.0);
+    }
+
+    #[test]
+    fn test_quantize() {
+        let topology = [
+            LayerTopology::new(2),
+            LayerTopology::new(2),
+            LayerTopology::new(1).with_activation(Activation::Identity),
+        ];
+
+        #[rustfmt::skip]
+        let weights = [
+            0.5, 1.5, -1.05,
+            0.0, 0.25, 0.5,
+            0.1, 2.0, -0.5,
+        ];
+
+        let mut network = Network::from_weights(&topology, weights);
+        let quantized = network.quantize().unwrap();
+
+        assert_eq!(quantized.topology(), topology);
+        assert_eq!(quantized.layers()[0].weights(), [127, -128, 2, 27]);
+        assert_eq!(quantized.layers()[1].biases(), [0.1]);
+
+        //Rounding is off by at most half a step
+        for (layer, range) in quantized.layers().iter().zip([0..6, 6..9]) {
+            assert_relative_eq!(
+                quantized.dequantize().weights()[range.clone()],
+                weights[range],
+                epsilon = layer.quantization().scale / 2.0
+            );
+        }
+
+        assert_relative_eq!(
+            quantized.propagate(vec![1.0, 0.5])[0],
+            network.propagate(vec![1.0, 0.5])[0],
+            epsilon = 0.05
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let topology = [
+            LayerTopology::new(8),
+            LayerTopology::new(16),
+            LayerTopology::new(4).with_activation(Activation::Tanh),
+        ];
+
+        let network = Network::random_with(&mut rng, &topology, Init::default());
+        let samples: Vec<Vec<f32>> = (0..50)
+            .map(|_| (0..8).map(|_| rng.gen_range(-1.0..=1.0)).collect())
+            .collect();
+
+        let report = network.quantize().unwrap().compare(&network, &samples);
+
+        assert_eq!(report.samples, 50);
+        assert!(report.max_error < 0.1, "{report:?}");
+        assert!(report.mean_error < 0.02, "{report:?}");
+        assert!(report.agreement >= 0.9, "{report:?}");
+    }
+
+    #[test]
+    fn test_unquantizable() {
+        let topology = [
+            LayerTopology::new(2),
+            LayerTopology::new(2),
+            LayerTopology::new(2).with_kind(LayerKind::LayerNorm),
+        ];
+
+        assert_eq!(
+            Network::random(&topology).quantize().unwrap_err(),
+            NetworkError::Unquantizable { layer: 2 }
+        );
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let topology = [
+            LayerTopology::new(2),
+            LayerTopology::new(3),
+            LayerTopology::new(1),
+        ];
+
+        let quantized = Network::random(&topology).quantize().unwrap();
+        let json = serde_json::to_string(&quantized).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<QuantizedNetwork>(&json).unwrap(),
+            quantized
+        );
+
+        //Layer with a single neuron
+        let layer = |inputs: usize, weights: &str| {
+            format!(
+                r#"{{"inputs":{inputs},"weights":{weights},"biases":[0.5],"quantization":{{"scale":1.0,"zero_point":0}},"activation":"Identity"}}"#
+            )
+        };
+
+        let err = |json: &str| serde_json::from_str::<QuantizedLayer>(json).unwrap_err();
+        assert!(err(&layer(2, "[1]"))
+            .to_string()
+            .starts_with("got not enough weights"));
+        assert!(err(&layer(2, "[1,2,3]"))
+            .to_string()
+            .starts_with("got too many weights"));
+        assert!(err(&layer(0, "[]"))
+            .to_string()
+            .starts_with("layer 0 has no neurons"));
+
+        //A zero scale would turn every weight into zero
+        let unscaled = layer(1, "[1]").replace(r#""scale":1.0"#, r#""scale":0.0"#);
+        assert!(err(&unscaled)
+            .to_string()
+            .starts_with("quantization scale of layer 1 isn't positive"));
+
+        //Too many weights to count, long before they'd have to be read
+        let huge = format!(
+            r#"{{"inputs":{},"weights":[],"biases":[0.5,0.5],"quantization":{{"scale":1.0,"zero_point":0}},"activation":"Identity"}}"#,
+            usize::MAX
+        );
+        assert!(err(&huge)
+            .to_string()
+            .starts_with("topology has too many weights"));
+
+        let err = |json: &str| serde_json::from_str::<QuantizedNetwork>(json).unwrap_err();
+        assert!(err(r#"{"layers":[]}"#)
+            .to_string()
+            .starts_with("topology has 1 layer(s)"));
+
+        let mismatched = format!(
+            r#"{{"layers":[{},{}]}}"#,
+            layer(2, "[1,2]"),
+            layer(3, "[1,2,3]")
+        );
+
+        assert!(err(&mismatched)
+            .to_string()
+            .starts_with("layer 2 doesn't take as many inputs"));
+    }
+}
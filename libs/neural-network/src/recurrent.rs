@@ -0,0 +1,312 @@
+use crate::*;
+
+/// Elman layer: a dense layer that, besides its inputs, also sees its own
+/// outputs from the previous step.
+///
+/// Every row of the matrix holds a neuron's bias, its input weights and then
+/// its recurrent weights.
+#[derive(Clone, Debug)]
+pub(crate) struct Elman<T = f32> {
+    //Dense over the inputs followed by the previous state
+    cell: Dense<T>,
+    state: Vec<T>,
+    //Inputs and state laid out one after another, reused between steps
+    joined: Vec<T>,
+}
+
+impl<T: Scalar> Elman<T> {
+    pub(crate) fn new(cell: Dense<T>) -> Self {
+        let neurons = cell.neurons();
+        assert!(cell.inputs >= neurons);
+
+        Self {
+            cell,
+            state: vec![T::ZERO; neurons],
+            joined: Vec::new(),
+        }
+    }
+
+    pub(crate) fn inputs(&self) -> usize {
+        self.cell.inputs - self.neurons()
+    }
+
+    pub(crate) fn neurons(&self) -> usize {
+        self.state.len()
+    }
+
+    pub(crate) fn activation(&self) -> Activation {
+        self.cell.activation
+    }
+
+    pub(crate) fn weights(&self) -> &[T] {
+        &self.cell.weights
+    }
+
+    pub(crate) fn row(&self, neuron: usize) -> &[T] {
+        self.cell.row(neuron)
+    }
+
+    pub(crate) fn weights_mut(&mut self) -> &mut [T] {
+        &mut self.cell.weights
+    }
+
+    pub(crate) fn reset_state(&mut self) {
+        self.state.fill(T::ZERO);
+    }
+
+    /// Steps through a batch of inputs one after another, carrying the state
+    /// from each to the next.
+    pub(crate) fn propagate_into(&mut self, inputs: &[T], outputs: &mut Vec<T>) {
+        self.step(inputs, None, outputs);
+    }
+
+    pub(crate) fn trace_into(
+        &mut self,
+        inputs: &[T],
+        pre_activations: &mut Vec<T>,
+        outputs: &mut Vec<T>,
+    ) {
+        pre_activations.clear();
+        self.step(inputs, Some(pre_activations), outputs);
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[T],
+        mut pre_activations: Option<&mut Vec<T>>,
+        outputs: &mut Vec<T>,
+    ) {
+        let input_size = self.inputs();
+        assert_eq!(inputs.len() % input_size, 0);
+
+        outputs.clear();
+
+        for inputs in inputs.chunks_exact(input_size) {
+            self.joined.clear();
+            self.joined.extend_from_slice(inputs);
+            self.joined.extend_from_slice(&self.state);
+
+            for neuron in 0..self.state.len() {
+                let sum = self.cell.weighted_sum(neuron, &self.joined);
+
+                if let Some(pre_activations) = pre_activations.as_deref_mut() {
+                    pre_activations.push(sum);
+                }
+
+                self.state[neuron] = self.cell.activation.apply(sum);
+            }
+
+            outputs.extend_from_slice(&self.state);
+        }
+    }
+}
+
+/// Gated recurrent unit (Cho et al., 2014).
+///
+/// The weights hold three matrices one after another - the update gate, the
+/// reset gate and the candidate state - where every row holds a neuron's
+/// bias, its input weights and then its recurrent weights. Both gates always
+/// use the sigmoid; the layer's activation is applied to the candidate
+/// state (tanh in the original formulation).
+#[derive(Clone, Debug)]
+pub(crate) struct Gru<T = f32> {
+    inputs: usize,
+    weights: Vec<T>,
+    activation: Activation,
+    state: Vec<T>,
+    joined: Vec<T>,
+    update: Vec<T>,
+    reset: Vec<T>,
+}
+
+impl<T: Scalar> Gru<T> {
+    pub(crate) fn new(
+        inputs: usize,
+        neurons: usize,
+        weights: Vec<T>,
+        activation: Activation,
+    ) -> Self {
+        assert_eq!(Some(weights.len()), Self::weights_len(inputs, neurons));
+
+        Self {
+            inputs,
+            weights,
+            activation,
+            state: vec![T::ZERO; neurons],
+            joined: Vec::new(),
+            update: vec![T::ZERO; neurons],
+            reset: vec![T::ZERO; neurons],
+        }
+    }
+
+    /// `None` if the count doesn't fit in a `usize`.
+    pub(crate) fn weights_len(inputs: usize, neurons: usize) -> Option<usize> {
+        //Three gates, every one with a row of bias + inputs + state per neuron
+        inputs
+            .checked_add(neurons)?
+            .checked_add(1)?
+            .checked_mul(neurons)?
+            .checked_mul(3)
+    }
+
+    pub(crate) fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    pub(crate) fn neurons(&self) -> usize {
+        self.state.len()
+    }
+
+    pub(crate) fn activation(&self) -> Activation {
+        self.activation
+    }
+
+    pub(crate) fn weights(&self) -> &[T] {
+        &self.weights
+    }
+
+    pub(crate) fn weights_mut(&mut self) -> &mut [T] {
+        &mut self.weights
+    }
+
+    pub(crate) fn reset_state(&mut self) {
+        self.state.fill(T::ZERO);
+    }
+
+    pub(crate) fn row(&self, gate: usize, neuron: usize) -> &[T] {
+        let columns = 1 + self.inputs + self.neurons();
+
+        &self.weights[(gate * self.neurons() + neuron) * columns..][..columns]
+    }
+
+    pub(crate) fn propagate_into(&mut self, inputs: &[T], outputs: &mut Vec<T>) {
+        self.step(inputs, None, outputs);
+    }
+
+    /// Like `propagate_into`, but also records the candidate state's weighted
+    /// sums; the gates aren't traced.
+    pub(crate) fn trace_into(
+        &mut self,
+        inputs: &[T],
+        pre_activations: &mut Vec<T>,
+        outputs: &mut Vec<T>,
+    ) {
+        pre_activations.clear();
+        self.step(inputs, Some(pre_activations), outputs);
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[T],
+        mut pre_activations: Option<&mut Vec<T>>,
+        outputs: &mut Vec<T>,
+    ) {
+        assert_eq!(inputs.len() % self.inputs, 0);
+
+        outputs.clear();
+
+        for inputs in inputs.chunks_exact(self.inputs) {
+            self.joined.clear();
+            self.joined.extend_from_slice(inputs);
+            self.joined.extend_from_slice(&self.state);
+
+            for neuron in 0..self.neurons() {
+                self.update[neuron] =
+                    Activation::Sigmoid.apply(weighted_sum(self.row(0, neuron), &self.joined));
+                self.reset[neuron] =
+                    Activation::Sigmoid.apply(weighted_sum(self.row(1, neuron), &self.joined));
+            }
+
+            //The candidate only sees the part of the state the reset gate let through
+            for neuron in 0..self.neurons() {
+                self.joined[self.inputs + neuron] = self.reset[neuron] * self.state[neuron];
+            }
+
+            for neuron in 0..self.neurons() {
+                let sum = weighted_sum(self.row(2, neuron), &self.joined);
+
+                if let Some(pre_activations) = pre_activations.as_deref_mut() {
+                    pre_activations.push(sum);
+                }
+
+                let candidate = self.activation.apply(sum);
+                let update = self.update[neuron];
+
+                self.state[neuron] = (T::ONE - update) * candidate + update * self.state[neuron];
+            }
+
+            outputs.extend_from_slice(&self.state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn step(layer: &mut Layer, inputs: &[f32]) -> Vec<f32> {
+        let mut outputs = Vec::new();
+        layer.propagate_into(inputs, &mut outputs);
+        outputs
+    }
+
+    #[test]
+    fn test_elman_remembers() {
+        //out = x + 0.5 * previous out
+        let mut layer = Layer::Elman(Elman::new(Dense::new(
+            2,
+            vec![0.0, 1.0, 0.5],
+            Activation::Identity,
+        )));
+
+        assert_relative_eq!(step(&mut layer, &[1.0])[0], 1.0);
+        assert_relative_eq!(step(&mut layer, &[0.0])[0], 0.5);
+        assert_relative_eq!(step(&mut layer, &[0.0])[0], 0.25);
+
+        layer.reset_state();
+        assert_relative_eq!(step(&mut layer, &[0.0])[0], 0.0);
+    }
+
+    #[test]
+    fn test_gru() {
+        let topology = LayerTopology::new(1)
+            .with_activation(Activation::Tanh)
+            .with_kind(LayerKind::Gru);
+
+        #[rustfmt::skip]
+        let weights = vec![
+            //Update gate: always 0.5
+            0.0, 0.0, 0.0,
+            //Reset gate: whatever
+            0.3, 0.2, 0.1,
+            //Candidate: tanh(x)
+            0.0, 1.0, 0.0,
+        ];
+
+        let mut layer = Layer::from_weights(0, 1, &topology, &mut weights.into_iter());
+
+        let first = 0.5 * 1.0f32.tanh();
+        assert_relative_eq!(step(&mut layer, &[1.0])[0], first);
+        assert_relative_eq!(step(&mut layer, &[0.0])[0], 0.5 * first);
+    }
+
+    #[test]
+    fn test_batch_is_a_sequence() {
+        let topology = LayerTopology::new(3).with_kind(LayerKind::Gru);
+        let mut layer = Layer::from_weights(
+            0,
+            2,
+            &topology,
+            &mut (0..Gru::<f32>::weights_len(2, 3).unwrap()).map(|n| (n % 7) as f32 / 7.0 - 0.4),
+        );
+
+        let batch = step(&mut layer, &[0.1, 0.2, 0.3, 0.4]);
+
+        layer.reset_state();
+        let mut one_by_one = step(&mut layer, &[0.1, 0.2]);
+        one_by_one.extend(step(&mut layer, &[0.3, 0.4]));
+
+        assert_relative_eq!(batch.as_slice(), one_by_one.as_slice());
+    }
+}
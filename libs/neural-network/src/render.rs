@@ -0,0 +1,277 @@
+use crate::*;
+use core::fmt::Write;
+
+const POSITIVE: &str = "#2166ac";
+const NEGATIVE: &str = "#b2182b";
+
+//Edge widths, from the smallest weight to the largest one
+const MIN_WIDTH: f32 = 0.5;
+const MAX_WIDTH: f32 = 4.0;
+
+//SVG layout, in pixels
+const MARGIN: f32 = 30.0;
+const COLUMN: f32 = 160.0;
+const ROW: f32 = 36.0;
+const RADIUS: f32 = 11.0;
+
+impl Network {
+    /// Describes the network in Graphviz's DOT language: one node per neuron
+    /// and one edge per non-zero weight, blue for positive and red for
+    /// negative ones, thicker the larger they are.
+    ///
+    /// Recurrent weights aren't drawn; for GRU layers, edges show the
+    /// candidate state's weights.
+    pub fn to_dot(&self) -> String {
+        let max = self.max_weight();
+        let mut dot = String::from("digraph network {\n");
+
+        dot.push_str("    rankdir=LR;\n");
+        dot.push_str("    node [shape=circle, label=\"\", width=0.3];\n");
+
+        for (layer, neurons) in self.widths().into_iter().enumerate() {
+            dot.push_str("    { rank=same;");
+
+            for neuron in 0..neurons {
+                write!(dot, " n{layer}_{neuron};").unwrap();
+            }
+
+            dot.push_str(" }\n");
+        }
+
+        for (index, layer) in self.layers().enumerate() {
+            for neuron in 0..layer.neurons() {
+                writeln!(
+                    dot,
+                    "    n{}_{neuron} [tooltip=\"bias {}\"];",
+                    index + 1,
+                    layer.bias(neuron)
+                )
+                .unwrap();
+
+                for input in 0..layer.inputs() {
+                    let weight = layer.weight(neuron, input);
+
+                    if weight == 0.0 {
+                        continue;
+                    }
+
+                    writeln!(
+                        dot,
+                        "    n{index}_{input} -> n{}_{neuron} [color=\"{}\", penwidth={:.2}, tooltip=\"{weight}\"];",
+                        index + 1,
+                        edge_color(weight),
+                        edge_width(weight, max),
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Draws the network the same way as `to_dot`, but as a standalone SVG
+    /// that doesn't need Graphviz.
+    ///
+    /// Given a `trace` of this network, neurons are filled according to their
+    /// outputs: bluer the more positive, redder the more negative (saturating
+    /// at ±1).
+    pub fn to_svg(&self, trace: Option<&Trace>) -> String {
+        let widths = self.widths();
+
+        if let Some(trace) = trace {
+            assert_eq!(trace.inputs.len(), widths[0]);
+            assert_eq!(trace.layers.len(), self.layers.len());
+        }
+
+        let tallest = widths.iter().copied().max().unwrap_or(0);
+        let width = 2.0 * MARGIN + (widths.len() - 1) as f32 * COLUMN;
+        let height = 2.0 * MARGIN + tallest.saturating_sub(1) as f32 * ROW;
+
+        //Layers are centered vertically
+        let position = |layer: usize, neuron: usize| {
+            let offset = (tallest - widths[layer]) as f32 * ROW / 2.0;
+
+            (
+                MARGIN + layer as f32 * COLUMN,
+                MARGIN + offset + neuron as f32 * ROW,
+            )
+        };
+
+        let max = self.max_weight();
+        let mut svg = String::new();
+
+        writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">"
+        )
+        .unwrap();
+
+        //Edges first, so that neurons are drawn on top of them
+        for (index, layer) in self.layers().enumerate() {
+            for neuron in 0..layer.neurons() {
+                for input in 0..layer.inputs() {
+                    let weight = layer.weight(neuron, input);
+
+                    if weight == 0.0 {
+                        continue;
+                    }
+
+                    let (x1, y1) = position(index, input);
+                    let (x2, y2) = position(index + 1, neuron);
+
+                    writeln!(
+                        svg,
+                        "  <line x1=\"{x1}\" y1=\"{y1}\" x2=\"{x2}\" y2=\"{y2}\" stroke=\"{}\" stroke-width=\"{:.2}\" stroke-opacity=\"0.8\"><title>{weight}</title></line>",
+                        edge_color(weight),
+                        edge_width(weight, max),
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        for (layer, neurons) in widths.iter().enumerate() {
+            for neuron in 0..*neurons {
+                let (cx, cy) = position(layer, neuron);
+
+                let output = trace.map(|trace| match layer {
+                    0 => trace.inputs[neuron],
+                    layer => trace.layers[layer - 1].outputs[neuron],
+                });
+
+                let fill = output.map_or_else(|| "#ffffff".to_string(), value_color);
+
+                let title = match (layer, output) {
+                    (0, Some(output)) => format!("input {neuron}: {output}"),
+                    (0, None) => format!("input {neuron}"),
+                    (layer, output) => {
+                        let bias = self.layer(layer - 1).bias(neuron);
+
+                        match output {
+                            Some(output) => format!("bias {bias}, output {output}"),
+                            None => format!("bias {bias}"),
+                        }
+                    }
+                };
+
+                writeln!(
+                    svg,
+                    "  <circle cx=\"{cx}\" cy=\"{cy}\" r=\"{RADIUS}\" fill=\"{fill}\" stroke=\"#333333\"><title>{title}</title></circle>"
+                )
+                .unwrap();
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    //Number of neurons in every layer, including the input layer
+    fn widths(&self) -> Vec<usize> {
+        self.topology().iter().map(|layer| layer.neurons).collect()
+    }
+
+    //Largest drawn weight, which gets the thickest edge
+    fn max_weight(&self) -> f32 {
+        self.layers()
+            .flat_map(|layer| {
+                (0..layer.neurons()).flat_map(move |neuron| {
+                    (0..layer.inputs()).map(move |input| layer.weight(neuron, input).abs())
+                })
+            })
+            .fold(0.0, f32::max)
+    }
+}
+
+fn edge_color(weight: f32) -> &'static str {
+    if weight >= 0.0 {
+        POSITIVE
+    } else {
+        NEGATIVE
+    }
+}
+
+fn edge_width(weight: f32, max: f32) -> f32 {
+    if max == 0.0 {
+        return MIN_WIDTH;
+    }
+
+    MIN_WIDTH + (MAX_WIDTH - MIN_WIDTH) * weight.abs() / max
+}
+
+//Blends from white towards the color of the value's sign
+fn value_color(value: f32) -> String {
+    let (r, g, b) = if value >= 0.0 {
+        (0x21, 0x66, 0xac)
+    } else {
+        (0xb2, 0x18, 0x2b)
+    };
+
+    let amount = value.abs().min(1.0);
+    let blend = |channel: u8| (255.0 - (255.0 - channel as f32) * amount).round() as u8;
+
+    format!("#{:02x}{:02x}{:02x}", blend(r), blend(g), blend(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network() -> Network {
+        let topology = [
+            LayerTopology::new(2),
+            LayerTopology::new(2),
+            LayerTopology::new(1),
+        ];
+
+        #[rustfmt::skip]
+        let weights = [
+            0.1, 1.0, 0.0,
+            0.2, -2.0, 0.5,
+            0.3, 0.25, 1.0,
+        ];
+
+        Network::from_weights(&topology, weights)
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let dot = network().to_dot();
+
+        assert!(dot.starts_with("digraph network {"));
+        //The zero weight isn't drawn
+        assert_eq!(dot.matches(" -> ").count(), 5);
+        assert!(dot.contains(&format!(
+            "n0_0 -> n1_1 [color=\"{NEGATIVE}\", penwidth={MAX_WIDTH:.2}"
+        )));
+        assert!(dot.contains("n2_0 [tooltip=\"bias 0.3\"]"));
+    }
+
+    #[test]
+    fn test_to_svg() {
+        let mut network = network();
+        let svg = network.to_svg(None);
+
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<circle").count(), 5);
+        assert_eq!(svg.matches("<line").count(), 5);
+        assert_eq!(svg.matches("fill=\"#ffffff\"").count(), 5);
+
+        let trace = network.propagate_traced(vec![1.0, -1.0]);
+        let svg = network.to_svg(Some(&trace));
+
+        //Inputs of ±1 saturate
+        assert!(svg.contains("fill=\"#2166ac\""));
+        assert!(svg.contains("fill=\"#b2182b\""));
+    }
+
+    #[test]
+    fn test_value_color() {
+        assert_eq!(value_color(0.0), "#ffffff");
+        assert_eq!(value_color(5.0), "#2166ac");
+        assert_eq!(value_color(-0.5), "#d98c95");
+    }
+}
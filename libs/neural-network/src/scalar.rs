@@ -0,0 +1,400 @@
+use crate::*;
+use core::fmt;
+use core::iter::Sum;
+use core::ops::{Add, Div, Mul, Neg, Sub};
+
+/// Number type a `Network` computes with.
+///
+/// Conversions go through `f64`, which represents every `f32` and every
+/// `Fixed` exactly.
+pub trait Scalar:
+    Copy
+    + Default
+    + fmt::Debug
+    + PartialEq
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + Sum
+    + 'static
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+
+    fn from_f32(value: f32) -> Self {
+        Self::from_f64(value as f64)
+    }
+
+    fn exp(self) -> Self;
+    fn tanh(self) -> Self;
+    fn sqrt(self) -> Self;
+
+    fn abs(self) -> Self {
+        if self < Self::ZERO {
+            -self
+        } else {
+            self
+        }
+    }
+
+    fn max(self, other: Self) -> Self {
+        if self >= other {
+            self
+        } else {
+            other
+        }
+    }
+
+    fn is_nan(self) -> bool {
+        false
+    }
+}
+
+macro_rules! impl_float {
+    ($float:ty) => {
+        impl Scalar for $float {
+            const ZERO: Self = 0.0;
+            const ONE: Self = 1.0;
+
+            fn from_f64(value: f64) -> Self {
+                value as _
+            }
+
+            fn to_f64(self) -> f64 {
+                self as _
+            }
+
+            #[cfg(feature = "std")]
+            fn exp(self) -> Self {
+                <$float>::exp(self)
+            }
+
+            #[cfg(not(feature = "std"))]
+            fn exp(self) -> Self {
+                libm::Libm::<$float>::exp(self)
+            }
+
+            #[cfg(feature = "std")]
+            fn tanh(self) -> Self {
+                <$float>::tanh(self)
+            }
+
+            #[cfg(not(feature = "std"))]
+            fn tanh(self) -> Self {
+                libm::Libm::<$float>::tanh(self)
+            }
+
+            #[cfg(feature = "std")]
+            fn sqrt(self) -> Self {
+                <$float>::sqrt(self)
+            }
+
+            #[cfg(not(feature = "std"))]
+            fn sqrt(self) -> Self {
+                libm::Libm::<$float>::sqrt(self)
+            }
+
+            fn abs(self) -> Self {
+                <$float>::abs(self)
+            }
+
+            fn max(self, other: Self) -> Self {
+                <$float>::max(self, other)
+            }
+
+            fn is_nan(self) -> bool {
+                <$float>::is_nan(self)
+            }
+        }
+    };
+}
+
+impl_float!(f32);
+impl_float!(f64);
+
+/// Q16.16 fixed-point number: 16 integer and 16 fractional bits in an `i32`.
+///
+/// Everything, including `exp` and `tanh`, is computed with integer
+/// arithmetic only, so networks of `Fixed` give bit-identical results on
+/// every platform - e.g. in the wasm build and natively. Arithmetic
+/// saturates instead of overflowing, and multiplication and division round
+/// to the nearest representable value.
+///
+/// That only covers the network itself: code feeding it inputs or reading
+/// its outputs with `f32` math, like `Heads` or the bird simulation outside
+/// of its fixed-point `Replay`, can still differ between platforms.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Fixed(i32);
+
+impl Fixed {
+    pub const FRAC_BITS: u32 = 16;
+    pub const MIN: Self = Self(i32::MIN);
+    pub const MAX: Self = Self(i32::MAX);
+
+    pub const fn from_bits(bits: i32) -> Self {
+        Self(bits)
+    }
+
+    pub const fn to_bits(self) -> i32 {
+        self.0
+    }
+
+    fn saturate(bits: i64) -> Self {
+        Self(bits.clamp(i32::MIN as i64, i32::MAX as i64) as i32)
+    }
+}
+
+impl fmt::Debug for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Fixed({})", self.to_f64())
+    }
+}
+
+impl fmt::Display for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_f64())
+    }
+}
+
+impl Add for Fixed {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self(self.0.saturating_add(other.0))
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self(self.0.saturating_sub(other.0))
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let product = self.0 as i64 * other.0 as i64;
+        Self::saturate((product + (1 << (Self::FRAC_BITS - 1))) >> Self::FRAC_BITS)
+    }
+}
+
+impl Div for Fixed {
+    type Output = Self;
+
+    /// Dividing by zero saturates towards the sign of the dividend.
+    fn div(self, other: Self) -> Self {
+        if other.0 == 0 {
+            return if self.0 >= 0 { Self::MAX } else { Self::MIN };
+        }
+
+        let dividend = (self.0 as i64) << Self::FRAC_BITS;
+        let divisor = other.0 as i64;
+
+        //Round half away from zero
+        let half = divisor.abs() / 2;
+        let rounded = if (dividend < 0) == (divisor < 0) {
+            dividend + half * divisor.signum()
+        } else {
+            dividend - half * divisor.signum()
+        };
+
+        Self::saturate(rounded / divisor)
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(self.0.saturating_neg())
+    }
+}
+
+impl Sum for Fixed {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, Add::add)
+    }
+}
+
+//log2(e) and ln(2) in Q32
+const LOG2_E: i128 = 6_196_328_019;
+const LN_2: u128 = 2_977_044_472;
+
+impl Scalar for Fixed {
+    const ZERO: Self = Self(0);
+    const ONE: Self = Self(1 << Self::FRAC_BITS);
+
+    fn from_f64(value: f64) -> Self {
+        if value.is_nan() {
+            return Self::ZERO;
+        }
+
+        //Float to int casts saturate
+        Self((value * (1u32 << Self::FRAC_BITS) as f64).round() as i32)
+    }
+
+    fn to_f64(self) -> f64 {
+        self.0 as f64 / (1u32 << Self::FRAC_BITS) as f64
+    }
+
+    /// e^x = 2^k * 2^f, where k and f are the integer and fractional parts of
+    /// x * log2(e); 2^f comes from a Taylor series.
+    fn exp(self) -> Self {
+        const ONE: u128 = 1 << 32;
+
+        let y = (self.0 as i128 * LOG2_E) >> Self::FRAC_BITS;
+        let k = (y >> 32) as i32;
+        let t = ((y as u128 & (ONE - 1)) * LN_2) >> 32;
+
+        //e^t for t in [0, ln 2); eight terms are accurate to ~1e-6
+        let mut term = ONE;
+        let mut sum = ONE;
+
+        for n in 1..=8 {
+            term = ((term * t) >> 32) / n;
+            sum += term;
+        }
+
+        //sum is in Q32, the result is in Q16
+        let shift = 32 - Self::FRAC_BITS as i32 - k;
+
+        if shift <= 0 {
+            return if -shift >= 32 {
+                Self::MAX
+            } else {
+                Self::saturate((sum << -shift).min(i64::MAX as u128) as i64)
+            };
+        }
+
+        if shift >= 64 {
+            return Self::ZERO;
+        }
+
+        Self::saturate(((sum + (1 << (shift - 1))) >> shift) as i64)
+    }
+
+    fn tanh(self) -> Self {
+        if self < Self::ZERO {
+            return -(-self).tanh();
+        }
+
+        //1 - 2 / (e^2x + 1), which saturates to 1 as e^2x does
+        let two = Self::ONE + Self::ONE;
+        Self::ONE - two / ((self + self).exp() + Self::ONE)
+    }
+
+    /// Rounded down; negative numbers have a square root of zero.
+    fn sqrt(self) -> Self {
+        //sqrt(x / 2^16) * 2^16 = sqrt(x * 2^16)
+        let bits = (self.0.max(0) as u64) << Self::FRAC_BITS;
+
+        Self(bits.isqrt() as i32)
+    }
+}
+
+impl<T: Scalar> Network<T> {
+    /// Converts every weight to another scalar type, keeping the topology.
+    ///
+    /// Recurrent layers start with a fresh state, while batch normalization
+    /// keeps its running statistics.
+    pub fn cast<U: Scalar>(&self) -> Network<U> {
+        let weights = self.weights().into_iter().map(|w| U::from_f64(w.to_f64()));
+        let mut network = Network::from_weights(&self.topology(), weights);
+
+        let statistics: Vec<U> = self
+            .statistics()
+            .into_iter()
+            .map(|value| U::from_f64(value.to_f64()))
+            .collect();
+
+        network.set_statistics(&statistics);
+        network
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn fixed(value: f64) -> Fixed {
+        Fixed::from_f64(value)
+    }
+
+    #[test]
+    fn test_fixed_arithmetic() {
+        assert_eq!(Fixed::ONE.to_bits(), 65536);
+        assert_eq!(fixed(1.5) + fixed(2.25), fixed(3.75));
+        assert_eq!(fixed(1.5) - fixed(2.25), fixed(-0.75));
+        assert_eq!(fixed(1.5) * fixed(-2.25), fixed(-3.375));
+        assert_eq!(fixed(-3.375) / fixed(1.5), fixed(-2.25));
+        assert_eq!(fixed(30000.0) * fixed(30000.0), Fixed::MAX);
+        assert_eq!(fixed(-1.0) / Fixed::ZERO, Fixed::MIN);
+    }
+
+    #[test]
+    fn test_fixed_math() {
+        for x in [-12.0, -5.0, -1.0, -0.3, 0.0, 0.1, 1.0, 2.5, 7.0, 10.0] {
+            assert_relative_eq!(
+                fixed(x).exp().to_f64(),
+                x.exp(),
+                epsilon = 1e-4,
+                max_relative = 1e-4
+            );
+            assert_relative_eq!(fixed(x).tanh().to_f64(), x.tanh(), epsilon = 1e-4);
+
+            if x >= 0.0 {
+                assert_relative_eq!(fixed(x).sqrt().to_f64(), x.sqrt(), epsilon = 1e-4);
+            }
+        }
+
+        assert_eq!(fixed(20.0).exp(), Fixed::MAX);
+        assert_eq!(fixed(-20.0).exp(), Fixed::ZERO);
+    }
+
+    #[test]
+    fn test_cast() {
+        let topology = [
+            LayerTopology::new(3),
+            LayerTopology::new(4).with_activation(Activation::Tanh),
+            LayerTopology::new(2).with_activation(Activation::Sigmoid),
+        ];
+
+        let mut network = Network::random(&topology);
+        let mut wide: Network<f64> = network.cast();
+        let mut fixed: Network<Fixed> = network.cast();
+
+        let inputs = vec![0.5, -0.25, 1.0];
+        let expected = network.propagate(inputs.clone());
+
+        let wide = wide.propagate(inputs.iter().map(|&x| x as f64).collect());
+        let fixed = fixed.propagate(inputs.iter().map(|&x| Fixed::from_f32(x)).collect());
+
+        for ((expected, wide), fixed) in expected.into_iter().zip(wide).zip(fixed) {
+            assert_relative_eq!(expected as f64, wide, epsilon = 1e-6);
+            assert_relative_eq!(expected as f64, fixed.to_f64(), epsilon = 1e-3);
+        }
+
+        //And back, without losing anything
+        assert_eq!(
+            network.cast::<f64>().cast::<f32>().weights(),
+            network.weights()
+        );
+    }
+
+    #[test]
+    fn test_fixed_is_deterministic() {
+        //Pinned bits, which every platform has to reproduce
+        assert_eq!(fixed(1.0).exp().to_bits(), 178145);
+        assert_eq!(fixed(0.5).tanh().to_bits(), 30285);
+    }
+}
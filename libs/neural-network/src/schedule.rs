@@ -0,0 +1,154 @@
+use crate::*;
+use core::f32::consts::PI;
+
+/// How the learning rate changes over the course of training.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schedule {
+    Constant,
+    /// Multiplies the learning rate by `gamma` every `every` steps.
+    Step {
+        every: usize,
+        gamma: f32,
+    },
+    /// Cosine annealing from the base learning rate down to `min` over
+    /// `steps` steps, staying at `min` afterwards.
+    Cosine {
+        steps: usize,
+        min: f32,
+    },
+    /// Ramps the learning rate linearly up from zero over `steps` steps, then
+    /// hands over to `then` (which starts counting from zero).
+    Warmup {
+        steps: usize,
+        then: Box<Schedule>,
+    },
+}
+
+impl Schedule {
+    /// Learning rate to use for the given (zero-based) step.
+    pub fn learning_rate(&self, base: f32, step: usize) -> f32 {
+        match self {
+            Self::Constant => base,
+            Self::Step { every, gamma } => {
+                assert!(*every > 0);
+
+                base * gamma.powi((step / every) as i32)
+            }
+            Self::Cosine { steps, min } => {
+                let progress = (step as f32 / *steps as f32).min(1.0);
+
+                min + (base - min) * (1.0 + (PI * progress).cos()) / 2.0
+            }
+            Self::Warmup { steps, then } => {
+                if step < *steps {
+                    base * (step + 1) as f32 / *steps as f32
+                } else {
+                    then.learning_rate(base, step - steps)
+                }
+            }
+        }
+    }
+}
+
+/// Wraps another optimizer, updating its learning rate before every step.
+#[derive(Clone, Debug)]
+pub struct Scheduled<O> {
+    optimizer: O,
+    schedule: Schedule,
+    base: f32,
+    steps: usize,
+}
+
+impl<O: Optimizer> Scheduled<O> {
+    /// The optimizer's current learning rate becomes the schedule's base.
+    pub fn new(optimizer: O, schedule: Schedule) -> Self {
+        let base = optimizer.learning_rate();
+
+        Self {
+            optimizer,
+            schedule,
+            base,
+            steps: 0,
+        }
+    }
+}
+
+impl<O: Optimizer> Optimizer for Scheduled<O> {
+    fn step(&mut self, params: &mut [f32], grads: &[f32]) {
+        let learning_rate = self.schedule.learning_rate(self.base, self.steps);
+
+        self.optimizer.set_learning_rate(learning_rate);
+        self.optimizer.step(params, grads);
+        self.steps += 1;
+    }
+
+    fn learning_rate(&self) -> f32 {
+        self.optimizer.learning_rate()
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: f32) {
+        self.base = learning_rate;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_step() {
+        let schedule = Schedule::Step {
+            every: 10,
+            gamma: 0.5,
+        };
+
+        assert_relative_eq!(schedule.learning_rate(1.0, 9), 1.0);
+        assert_relative_eq!(schedule.learning_rate(1.0, 10), 0.5);
+        assert_relative_eq!(schedule.learning_rate(1.0, 25), 0.25);
+    }
+
+    #[test]
+    fn test_cosine() {
+        let schedule = Schedule::Cosine {
+            steps: 100,
+            min: 0.1,
+        };
+
+        assert_relative_eq!(schedule.learning_rate(1.0, 0), 1.0);
+        assert_relative_eq!(schedule.learning_rate(1.0, 50), 0.55);
+        assert_relative_eq!(schedule.learning_rate(1.0, 500), 0.1);
+    }
+
+    #[test]
+    fn test_warmup() {
+        let schedule = Schedule::Warmup {
+            steps: 4,
+            then: Box::new(Schedule::Constant),
+        };
+
+        assert_relative_eq!(schedule.learning_rate(1.0, 0), 0.25);
+        assert_relative_eq!(schedule.learning_rate(1.0, 3), 1.0);
+        assert_relative_eq!(schedule.learning_rate(1.0, 7), 1.0);
+    }
+
+    #[test]
+    fn test_scheduled_optimizer() {
+        let mut optimizer = Scheduled::new(
+            Sgd::new(1.0),
+            Schedule::Step {
+                every: 1,
+                gamma: 0.5,
+            },
+        );
+
+        let mut params = [0.0];
+
+        for _ in 0..3 {
+            optimizer.step(&mut params, &[1.0]);
+        }
+
+        //1.0 + 0.5 + 0.25
+        assert_relative_eq!(params[0], -1.75);
+    }
+}
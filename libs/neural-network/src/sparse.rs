@@ -0,0 +1,347 @@
+use crate::*;
+use alloc::collections::BTreeMap;
+use core::ops::Range;
+
+/// Weighted edge of a `SparseNetwork`, between two neurons identified by
+/// arbitrary ids.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Connection {
+    pub from: usize,
+    pub to: usize,
+    pub weight: f32,
+}
+
+/// Hidden or output neuron of a `SparseNetwork`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SparseNeuron {
+    pub id: usize,
+    pub bias: f32,
+    pub activation: Activation,
+}
+
+/// Network wired neuron by neuron instead of layer by layer, such as one
+/// grown by NEAT.
+///
+/// The connections are compiled once into a flat evaluation order, so
+/// propagating is a single pass over them.
+#[derive(Clone, Debug)]
+pub struct SparseNetwork {
+    inputs: usize,
+    //In evaluation order; the value of neuron `n` goes to slot `inputs + n`
+    neurons: Vec<Compiled>,
+    //Slot of the source and weight, grouped by target neuron
+    connections: Vec<(usize, f32)>,
+    //Slots to read the outputs from
+    outputs: Vec<usize>,
+    values: Vec<f32>,
+}
+
+#[derive(Clone, Debug)]
+struct Compiled {
+    bias: f32,
+    activation: Activation,
+    connections: Range<usize>,
+}
+
+impl SparseNetwork {
+    pub fn from_connections(
+        inputs: &[usize],
+        outputs: &[usize],
+        neurons: &[SparseNeuron],
+        connections: &[Connection],
+    ) -> Self {
+        Self::try_from_connections(inputs, outputs, neurons, connections)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Compiles a network from the ids of its inputs, the ids of its outputs
+    /// (in the order `propagate` returns them) and its other neurons.
+    ///
+    /// Every id may only be used once, by either an input or a neuron, and
+    /// connections must not form a cycle. Neurons that don't lead to any
+    /// output are left out, since they can't affect the result.
+    pub fn try_from_connections(
+        inputs: &[usize],
+        outputs: &[usize],
+        neurons: &[SparseNeuron],
+        connections: &[Connection],
+    ) -> Result<Self, NetworkError> {
+        //Inputs first, then the neurons in the given order
+        let mut indices = BTreeMap::new();
+
+        for (index, &id) in inputs
+            .iter()
+            .chain(neurons.iter().map(|neuron| &neuron.id))
+            .enumerate()
+        {
+            if indices.insert(id, index).is_some() {
+                return Err(NetworkError::DuplicateNeuron { id });
+            }
+        }
+
+        let index_of = |id| {
+            indices
+                .get(&id)
+                .copied()
+                .ok_or(NetworkError::UnknownNeuron { id })
+        };
+
+        let mut incoming = vec![Vec::new(); inputs.len() + neurons.len()];
+        let mut outgoing = vec![Vec::new(); inputs.len() + neurons.len()];
+
+        for connection in connections {
+            let (from, to) = (index_of(connection.from)?, index_of(connection.to)?);
+
+            if to < inputs.len() {
+                return Err(NetworkError::ConnectionIntoInput {
+                    from: connection.from,
+                    to: connection.to,
+                });
+            }
+
+            incoming[to].push((from, connection.weight));
+            outgoing[from].push(to);
+        }
+
+        let outputs = outputs
+            .iter()
+            .map(|&id| match index_of(id)? {
+                index if index < inputs.len() => Err(NetworkError::UnknownNeuron { id }),
+                index => Ok(index),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let order = evaluation_order(inputs.len(), &incoming, &outgoing).map_err(|index| {
+            NetworkError::Cycle {
+                id: neurons[index - inputs.len()].id,
+            }
+        })?;
+
+        //Walk back from the outputs to find the neurons that matter
+        let mut needed = vec![false; incoming.len()];
+        let mut pending = outputs.clone();
+
+        while let Some(index) = pending.pop() {
+            if !core::mem::replace(&mut needed[index], true) {
+                pending.extend(incoming[index].iter().map(|&(from, _)| from));
+            }
+        }
+
+        let order: Vec<_> = order.into_iter().filter(|&index| needed[index]).collect();
+
+        //Inputs keep their slots, neurons move to their place in the order
+        let mut slots: Vec<_> = (0..incoming.len()).collect();
+
+        for (position, &index) in order.iter().enumerate() {
+            slots[index] = inputs.len() + position;
+        }
+
+        let mut compiled_connections = Vec::new();
+
+        let compiled = order
+            .iter()
+            .map(|&index| {
+                let neuron = &neurons[index - inputs.len()];
+                let start = compiled_connections.len();
+
+                compiled_connections.extend(
+                    incoming[index]
+                        .iter()
+                        .map(|&(from, weight)| (slots[from], weight)),
+                );
+
+                Compiled {
+                    bias: neuron.bias,
+                    activation: neuron.activation,
+                    connections: start..compiled_connections.len(),
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            inputs: inputs.len(),
+            neurons: compiled,
+            connections: compiled_connections,
+            outputs: outputs.iter().map(|&index| slots[index]).collect(),
+            values: vec![0.0; inputs.len() + order.len()],
+        })
+    }
+
+    pub fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    pub fn outputs(&self) -> usize {
+        self.outputs.len()
+    }
+
+    pub fn propagate(&mut self, inputs: Vec<f32>) -> Vec<f32> {
+        assert_eq!(inputs.len(), self.inputs);
+
+        self.values[..self.inputs].copy_from_slice(&inputs);
+
+        for (position, neuron) in self.neurons.iter().enumerate() {
+            let sum = self.connections[neuron.connections.clone()]
+                .iter()
+                .map(|&(from, weight)| self.values[from] * weight)
+                .sum::<f32>();
+
+            self.values[self.inputs + position] = neuron.activation.apply(neuron.bias + sum);
+        }
+
+        self.outputs.iter().map(|&slot| self.values[slot]).collect()
+    }
+}
+
+/// Orders the non-input neurons so that every one comes after all of its
+/// sources, or returns one of the neurons stuck in a cycle.
+fn evaluation_order(
+    inputs: usize,
+    incoming: &[Vec<(usize, f32)>],
+    outgoing: &[Vec<usize>],
+) -> Result<Vec<usize>, usize> {
+    let mut waiting_for: Vec<_> = incoming.iter().map(Vec::len).collect();
+    let mut ready: Vec<_> = (0..inputs)
+        .chain((inputs..incoming.len()).filter(|&index| waiting_for[index] == 0))
+        .collect();
+
+    let mut order = Vec::with_capacity(incoming.len() - inputs);
+
+    while let Some(index) = ready.pop() {
+        if index >= inputs {
+            order.push(index);
+        }
+
+        for &to in &outgoing[index] {
+            waiting_for[to] -= 1;
+
+            if waiting_for[to] == 0 {
+                ready.push(to);
+            }
+        }
+    }
+
+    match (inputs..incoming.len()).find(|&index| waiting_for[index] > 0) {
+        Some(index) => Err(index),
+        None => Ok(order),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn neuron(id: usize, bias: f32) -> SparseNeuron {
+        SparseNeuron {
+            id,
+            bias,
+            activation: Activation::Identity,
+        }
+    }
+
+    fn connection(from: usize, to: usize, weight: f32) -> Connection {
+        Connection { from, to, weight }
+    }
+
+    #[test]
+    fn test_propagate() {
+        //Ids needn't be contiguous, and hidden neurons may be listed before
+        //the ones they read from
+        let mut network = SparseNetwork::from_connections(
+            &[10, 20],
+            &[30],
+            &[neuron(30, 0.5), neuron(7, 0.0), neuron(8, -1.0)],
+            &[
+                connection(10, 8, 2.0),
+                connection(8, 7, 3.0),
+                connection(20, 7, 1.0),
+                connection(7, 30, 0.5),
+                connection(10, 30, 1.0),
+            ],
+        );
+
+        //n8 = 2 * 1 - 1 = 1, n7 = 3 * 1 + 2 = 5, out = 0.5 + 0.5 * 5 + 1 = 4
+        assert_relative_eq!(
+            network.propagate(vec![1.0, 2.0]).as_slice(),
+            [4.0].as_slice()
+        );
+    }
+
+    #[test]
+    fn test_leaves_out_dead_ends() {
+        let network = SparseNetwork::from_connections(
+            &[0],
+            &[1],
+            &[neuron(1, 0.0), neuron(2, 0.0)],
+            &[connection(0, 1, 1.0), connection(0, 2, 1.0)],
+        );
+
+        assert_eq!(network.neurons.len(), 1);
+    }
+
+    #[test]
+    fn test_cycle() {
+        let err = SparseNetwork::try_from_connections(
+            &[0],
+            &[1],
+            &[neuron(1, 0.0), neuron(2, 0.0)],
+            &[
+                connection(0, 2, 1.0),
+                connection(2, 1, 1.0),
+                connection(1, 2, 1.0),
+            ],
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, NetworkError::Cycle { .. }));
+    }
+
+    #[test]
+    fn test_invalid_connections() {
+        assert_eq!(
+            SparseNetwork::try_from_connections(
+                &[0],
+                &[1],
+                &[neuron(1, 0.0)],
+                &[connection(0, 5, 1.0)]
+            )
+            .unwrap_err(),
+            NetworkError::UnknownNeuron { id: 5 }
+        );
+
+        assert_eq!(
+            SparseNetwork::try_from_connections(
+                &[0],
+                &[1],
+                &[neuron(1, 0.0)],
+                &[connection(1, 0, 1.0)]
+            )
+            .unwrap_err(),
+            NetworkError::ConnectionIntoInput { from: 1, to: 0 }
+        );
+
+        assert_eq!(
+            SparseNetwork::try_from_connections(
+                &[0],
+                &[1],
+                &[neuron(1, 0.0), neuron(1, 0.5)],
+                &[connection(0, 1, 1.0)]
+            )
+            .unwrap_err(),
+            NetworkError::DuplicateNeuron { id: 1 }
+        );
+
+        //Neither may a neuron share its id with an input
+        assert_eq!(
+            SparseNetwork::try_from_connections(
+                &[0, 1],
+                &[1],
+                &[neuron(1, 0.0)],
+                &[connection(0, 1, 1.0)]
+            )
+            .unwrap_err(),
+            NetworkError::DuplicateNeuron { id: 1 }
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use crate::*;
+
+/// Parameters of a layer of leaky integrate-and-fire neurons
+/// (`LayerKind::Spiking`).
+///
+/// Every `propagate` call is one time step. A neuron adds its weighted sum
+/// to its membrane potential, which leaks towards zero between steps; once
+/// the potential reaches `threshold`, the neuron fires a spike, its potential
+/// drops back to zero and it ignores its inputs for `refractory` steps.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Spiking {
+    pub threshold: f32,
+    /// Fraction of the potential lost between steps, in [0, 1]: 0 never
+    /// forgets, 1 only sees the current step.
+    pub leak: f32,
+    pub refractory: u32,
+    pub output: SpikeOutput,
+}
+
+/// What a `Spiking` layer outputs for each neuron, before its activation is
+/// applied.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpikeOutput {
+    /// 1 if the neuron fired this step, 0 if not.
+    #[default]
+    Spikes,
+    /// How many spikes the neuron fired since the last `reset_state`.
+    Counts,
+    /// The neuron's membrane potential after this step, so zero right after
+    /// a spike.
+    Potentials,
+}
+
+impl Spiking {
+    /// Neurons without a refractory period, outputting spikes.
+    pub fn new(threshold: f32, leak: f32) -> Self {
+        Self {
+            threshold,
+            leak,
+            refractory: 0,
+            output: SpikeOutput::default(),
+        }
+    }
+
+    pub fn with_refractory(mut self, steps: u32) -> Self {
+        self.refractory = steps;
+        self
+    }
+
+    pub fn with_output(mut self, output: SpikeOutput) -> Self {
+        self.output = output;
+        self
+    }
+
+    pub(crate) fn is_valid(&self) -> bool {
+        self.threshold > 0.0 && (0.0..=1.0).contains(&self.leak)
+    }
+}
+
+/// Layer of leaky integrate-and-fire neurons, see `Spiking`.
+///
+/// Weights are laid out like a dense layer's; the bias is a constant input
+/// current.
+#[derive(Clone, Debug)]
+pub(crate) struct Lif<T = f32> {
+    cell: Dense<T>,
+    spiking: Spiking,
+    potentials: Vec<T>,
+    //Steps every neuron still has to sit out
+    refractory: Vec<u32>,
+    counts: Vec<u32>,
+}
+
+impl<T: Scalar> Lif<T> {
+    pub(crate) fn new(cell: Dense<T>, spiking: Spiking) -> Self {
+        assert!(spiking.is_valid(), "{spiking:?} is invalid");
+
+        let neurons = cell.neurons();
+
+        Self {
+            cell,
+            spiking,
+            potentials: vec![T::ZERO; neurons],
+            refractory: vec![0; neurons],
+            counts: vec![0; neurons],
+        }
+    }
+
+    pub(crate) fn inputs(&self) -> usize {
+        self.cell.inputs
+    }
+
+    pub(crate) fn neurons(&self) -> usize {
+        self.potentials.len()
+    }
+
+    pub(crate) fn spiking(&self) -> Spiking {
+        self.spiking
+    }
+
+    pub(crate) fn activation(&self) -> Activation {
+        self.cell.activation
+    }
+
+    pub(crate) fn weights(&self) -> &[T] {
+        &self.cell.weights
+    }
+
+    pub(crate) fn weights_mut(&mut self) -> &mut [T] {
+        &mut self.cell.weights
+    }
+
+    pub(crate) fn row(&self, neuron: usize) -> &[T] {
+        self.cell.row(neuron)
+    }
+
+    pub(crate) fn reset_state(&mut self) {
+        self.potentials.fill(T::ZERO);
+        self.refractory.fill(0);
+        self.counts.fill(0);
+    }
+
+    /// Steps through a batch of inputs one after another.
+    pub(crate) fn propagate_into(&mut self, inputs: &[T], outputs: &mut Vec<T>) {
+        self.step(inputs, None, outputs);
+    }
+
+    pub(crate) fn trace_into(
+        &mut self,
+        inputs: &[T],
+        pre_activations: &mut Vec<T>,
+        outputs: &mut Vec<T>,
+    ) {
+        pre_activations.clear();
+        self.step(inputs, Some(pre_activations), outputs);
+    }
+
+    fn step(
+        &mut self,
+        inputs: &[T],
+        mut pre_activations: Option<&mut Vec<T>>,
+        outputs: &mut Vec<T>,
+    ) {
+        assert_eq!(inputs.len() % self.cell.inputs, 0);
+
+        let threshold = T::from_f32(self.spiking.threshold);
+        let retained = T::from_f32(1.0 - self.spiking.leak);
+
+        outputs.clear();
+
+        for inputs in inputs.chunks_exact(self.cell.inputs) {
+            for neuron in 0..self.neurons() {
+                let current = self.cell.weighted_sum(neuron, inputs);
+
+                if let Some(pre_activations) = pre_activations.as_deref_mut() {
+                    pre_activations.push(current);
+                }
+
+                let mut spiked = false;
+
+                if self.refractory[neuron] > 0 {
+                    self.refractory[neuron] -= 1;
+                } else {
+                    let potential = self.potentials[neuron] * retained + current;
+
+                    if potential >= threshold {
+                        spiked = true;
+                        self.potentials[neuron] = T::ZERO;
+                        self.refractory[neuron] = self.spiking.refractory;
+                        self.counts[neuron] += 1;
+                    } else {
+                        self.potentials[neuron] = potential;
+                    }
+                }
+
+                let output = match self.spiking.output {
+                    SpikeOutput::Spikes if spiked => T::ONE,
+                    SpikeOutput::Spikes => T::ZERO,
+                    SpikeOutput::Counts => T::from_f64(self.counts[neuron] as f64),
+                    SpikeOutput::Potentials => self.potentials[neuron],
+                };
+
+                outputs.push(self.cell.activation.apply(output));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn layer(spiking: Spiking) -> Lif {
+        //A single neuron passing its only input through
+        Lif::new(Dense::new(1, vec![0.0, 1.0], Activation::Identity), spiking)
+    }
+
+    fn run(layer: &mut Lif, inputs: &[f32]) -> Vec<f32> {
+        let mut outputs = Vec::new();
+        layer.propagate_into(inputs, &mut outputs);
+        outputs
+    }
+
+    #[test]
+    fn test_integrate_and_fire() {
+        let mut layer = layer(Spiking::new(1.0, 0.5));
+
+        //0.4, 0.6, 0.7, then 1.15 fires; 0.4 again afterwards
+        assert_eq!(
+            run(&mut layer, &[0.4, 0.4, 0.4, 0.8, 0.4]),
+            [0.0, 0.0, 0.0, 1.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn test_refractory() {
+        let mut layer = layer(Spiking::new(1.0, 1.0).with_refractory(2));
+
+        //Every step would fire on its own, but after a spike, two are skipped
+        assert_eq!(
+            run(&mut layer, &[1.0; 7]),
+            [1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn test_outputs() {
+        let mut counts = layer(Spiking::new(1.0, 0.0).with_output(SpikeOutput::Counts));
+        assert_eq!(run(&mut counts, &[0.6; 5]), [0.0, 1.0, 1.0, 2.0, 2.0]);
+
+        counts.reset_state();
+        assert_eq!(run(&mut counts, &[0.6; 2]), [0.0, 1.0]);
+
+        let mut potentials = layer(Spiking::new(1.0, 0.5).with_output(SpikeOutput::Potentials));
+        assert_relative_eq!(
+            run(&mut potentials, &[0.8, 0.8, 0.8]).as_slice(),
+            [0.8, 0.0, 0.8].as_slice()
+        );
+    }
+
+    #[test]
+    fn test_in_network() {
+        let spiking = Spiking::new(0.5, 0.2)
+            .with_refractory(1)
+            .with_output(SpikeOutput::Counts);
+
+        let topology = [
+            LayerTopology::new(3),
+            LayerTopology::new(4)
+                .with_activation(Activation::Identity)
+                .with_kind(LayerKind::Spiking(spiking)),
+            LayerTopology::new(2).with_activation(Activation::Identity),
+        ];
+
+        let mut network = Network::random(&topology);
+        assert_eq!(network.topology(), topology);
+        assert_eq!(network.weights().len(), 4 * 4 + 2 * 5);
+
+        let mut copy = Network::from_weights(&topology, network.weights());
+
+        for inputs in [[1.0, 0.5, -0.5], [0.9, 0.1, 0.0], [-1.0, 1.0, 1.0]] {
+            assert_eq!(
+                copy.propagate(inputs.to_vec()),
+                network.propagate(inputs.to_vec())
+            );
+        }
+
+        let invalid = [
+            LayerTopology::new(3),
+            LayerTopology::new(4).with_kind(LayerKind::Spiking(Spiking::new(0.0, 0.5))),
+        ];
+
+        assert_eq!(
+            Network::try_random(&invalid).unwrap_err(),
+            NetworkError::InvalidSpiking { layer: 1 }
+        );
+    }
+}
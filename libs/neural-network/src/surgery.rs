@@ -0,0 +1,166 @@
+use crate::*;
+
+impl Network {
+    /// Adds a neuron to the hidden `layer` for every entry of `sources`, each
+    /// a copy of the existing neuron at that index (Net2WiderNet).
+    ///
+    /// A neuron and its copies split its outgoing weights evenly, so the
+    /// network computes the same function as before (up to rounding) - only
+    /// its `topology()` and `weights()` grow. Layers are counted like in
+    /// `topology()`, with the input layer being 0; both the widened layer and
+    /// the one after it have to be dense.
+    pub fn widen(&mut self, layer: usize, sources: &[usize]) {
+        assert!(
+            (1..self.layers.len()).contains(&layer),
+            "only hidden layers can be widened"
+        );
+
+        let (front, back) = self.layers.split_at_mut(layer);
+        let (current, next) = match (front[layer - 1].as_dense_mut(), back[0].as_dense_mut()) {
+            (Some(current), Some(next)) => (current, next),
+            _ => panic!("only dense layers can be widened"),
+        };
+
+        let neurons = current.neurons();
+        let mut copies = vec![1.0f32; neurons];
+
+        for &source in sources {
+            assert!(source < neurons, "neuron {source} doesn't exist");
+            copies[source] += 1.0;
+        }
+
+        for &source in sources {
+            let row = current.row(source).to_vec();
+            current.weights.extend(row);
+        }
+
+        let weights = next
+            .weights
+            .chunks_exact(next.inputs + 1)
+            .flat_map(|row| {
+                let split = |input: usize| row[1 + input] / copies[input];
+
+                core::iter::once(row[0])
+                    .chain((0..neurons).map(split))
+                    .chain(sources.iter().map(move |&source| split(source)))
+            })
+            .collect();
+
+        next.weights = weights;
+        next.inputs += sources.len();
+    }
+
+    /// Inserts a new dense layer right after `layer` (counted like in
+    /// `topology()`), initialized to pass its inputs through unchanged
+    /// (Net2DeeperNet).
+    ///
+    /// The new layer has as many neurons as `layer` has. It uses a ReLU if
+    /// `layer` does, since applying a ReLU twice changes nothing, and the
+    /// identity otherwise.
+    pub fn deepen(&mut self, layer: usize) {
+        let topology = self.topology();
+        assert!(layer < topology.len(), "layer {layer} doesn't exist");
+
+        let neurons = topology[layer].neurons;
+
+        let activation = match topology[layer].activation {
+            Activation::Relu if layer > 0 => Activation::Relu,
+            _ => Activation::Identity,
+        };
+
+        let mut weights = (0..neurons).flat_map(|neuron| {
+            core::iter::once(0.0)
+                .chain((0..neurons).map(move |input| if input == neuron { 1.0 } else { 0.0 }))
+        });
+
+        self.layers.insert(
+            layer,
+            Layer::Dense(Dense::from_weights(
+                neurons,
+                neurons,
+                activation,
+                &mut weights,
+            )),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    fn network(rng: &mut ChaCha8Rng) -> Network {
+        let topology = [
+            LayerTopology::new(3),
+            LayerTopology::new(4),
+            LayerTopology::new(2).with_activation(Activation::Tanh),
+        ];
+
+        Network::random_with(rng, &topology, Init::default())
+    }
+
+    fn assert_same_outputs(rng: &mut ChaCha8Rng, a: &mut Network, b: &mut Network) {
+        for _ in 0..20 {
+            let inputs: Vec<f32> = (0..3).map(|_| rng.gen_range(-1.0..=1.0)).collect();
+
+            assert_relative_eq!(
+                a.propagate(inputs.clone()).as_slice(),
+                b.propagate(inputs).as_slice(),
+                epsilon = 1e-6
+            );
+        }
+    }
+
+    #[test]
+    fn test_widen() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut network = network(&mut rng);
+        let mut wider = network.clone();
+
+        wider.widen(1, &[0, 2, 2]);
+
+        assert_eq!(wider.topology()[1].neurons, 7);
+        assert_eq!(wider.weights().len(), 7 * 4 + 2 * 8);
+        //The copies are the same neurons
+        assert_eq!(
+            wider.layer(0).weights()[16..20],
+            wider.layer(0).weights()[0..4]
+        );
+
+        assert_same_outputs(&mut rng, &mut network, &mut wider);
+    }
+
+    #[test]
+    fn test_deepen() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut network = network(&mut rng);
+        let mut deeper = network.clone();
+
+        deeper.deepen(1);
+        deeper.deepen(3);
+
+        let topology = deeper.topology();
+        assert_eq!(topology.len(), 5);
+        assert_eq!(topology[2].neurons, 4);
+        assert_eq!(topology[2].activation, Activation::Relu);
+        //Tanh isn't idempotent, so the new output layer is linear
+        assert_eq!(topology[4].activation, Activation::Identity);
+
+        assert_same_outputs(&mut rng, &mut network, &mut deeper);
+
+        //And both operations compose
+        deeper.widen(2, &[1]);
+        assert_same_outputs(&mut rng, &mut network, &mut deeper);
+    }
+
+    #[test]
+    #[should_panic(expected = "only hidden layers can be widened")]
+    fn test_widen_output() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        network(&mut rng).widen(2, &[0]);
+    }
+}
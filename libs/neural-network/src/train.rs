@@ -0,0 +1,765 @@
+use crate::*;
+
+//Keeps the logarithms in the cross-entropy finite
+const CROSS_ENTROPY_EPSILON: f32 = 1e-7;
+
+/// How far a network's outputs are from the expected targets.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Loss {
+    /// Mean of the squared differences over every output.
+    MeanSquaredError,
+    /// Binary cross-entropy averaged over every output; expects outputs in
+    /// (0, 1), e.g. from a sigmoid output layer.
+    CrossEntropy,
+}
+
+impl Loss {
+    pub fn loss(&self, outputs: &[f32], targets: &[f32]) -> f32 {
+        assert_eq!(outputs.len(), targets.len());
+
+        let sum: f32 = outputs
+            .iter()
+            .zip(targets)
+            .map(|(&output, &target)| match self {
+                Self::MeanSquaredError => (output - target).powi(2),
+                Self::CrossEntropy => {
+                    let output = output.clamp(CROSS_ENTROPY_EPSILON, 1.0 - CROSS_ENTROPY_EPSILON);
+
+                    -(target * output.ln() + (1.0 - target) * (1.0 - output).ln())
+                }
+            })
+            .sum();
+
+        sum / outputs.len() as f32
+    }
+
+    /// Derivative of `loss` with respect to every output.
+    pub fn gradient(&self, outputs: &[f32], targets: &[f32]) -> Vec<f32> {
+        assert_eq!(outputs.len(), targets.len());
+
+        let n = outputs.len() as f32;
+
+        outputs
+            .iter()
+            .zip(targets)
+            .map(|(&output, &target)| match self {
+                Self::MeanSquaredError => 2.0 * (output - target) / n,
+                Self::CrossEntropy => {
+                    let output = output.clamp(CROSS_ENTROPY_EPSILON, 1.0 - CROSS_ENTROPY_EPSILON);
+
+                    (output - target) / (output * (1.0 - output)) / n
+                }
+            })
+            .collect()
+    }
+}
+
+/// Everything `Network::forward` computed on the way through the network,
+/// kept around so that `Network::backward` doesn't have to recompute it.
+#[derive(Clone, Debug)]
+pub struct ForwardPass {
+    //What every layer received; inputs[0] is the network's input
+    inputs: Vec<Vec<f32>>,
+    //Every layer's weighted sums, before the activation
+    pre_activations: Vec<Vec<f32>>,
+    //Which inputs dropout layers kept, and how much they were scaled up
+    masks: Vec<Vec<f32>>,
+    output: Vec<f32>,
+}
+
+impl ForwardPass {
+    pub fn output(&self) -> &[f32] {
+        &self.output
+    }
+}
+
+/// Outcome of `Network::check_gradient`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GradientCheck {
+    /// Largest absolute difference between the two gradients.
+    pub max_error: f32,
+    /// Index (in `weights()`) of the weight with that difference.
+    pub weight: usize,
+}
+
+impl Network {
+    /// Like `propagate`, but remembers every intermediate value.
+    ///
+    /// Only networks made of dense, normalization and dropout layers can be
+    /// trained by backpropagation; the other kinds are meant to be evolved,
+    /// and make this return `NetworkError::Untrainable`. Here, dropout layers
+    /// keep every input and batch normalization uses its running statistics,
+    /// whatever the `Mode`.
+    pub fn forward(&self, inputs: Vec<f32>) -> Result<ForwardPass, NetworkError> {
+        self.check_trainable()?;
+
+        Ok(self.forward_unchecked(inputs))
+    }
+
+    fn forward_unchecked(&self, inputs: Vec<f32>) -> ForwardPass {
+        let mut layer_inputs = Vec::with_capacity(self.layers.len());
+        let mut pre_activations = Vec::with_capacity(self.layers.len());
+
+        let output = self.layers.iter().fold(inputs, |inputs, layer| {
+            let sums = forward_layer(layer, &inputs, None);
+            let activation = layer.topology().activation;
+            let outputs = sums.iter().map(|&sum| activation.apply(sum)).collect();
+
+            layer_inputs.push(inputs);
+            pre_activations.push(sums);
+            outputs
+        });
+
+        ForwardPass {
+            inputs: layer_inputs,
+            pre_activations,
+            masks: Vec::new(),
+            output,
+        }
+    }
+
+    /// Backpropagates `output_gradient` (the loss' derivative with respect to
+    /// the network's output) through `pass`.
+    ///
+    /// Returns gradients in the same layout as `weights()`.
+    pub fn backward(
+        &self,
+        pass: &ForwardPass,
+        output_gradient: &[f32],
+    ) -> Result<Vec<f32>, NetworkError> {
+        self.check_trainable()?;
+
+        Ok(self.backward_unchecked(pass, output_gradient))
+    }
+
+    fn backward_unchecked(&self, pass: &ForwardPass, output_gradient: &[f32]) -> Vec<f32> {
+        assert_eq!(output_gradient.len(), pass.output.len());
+
+        self.backward_batch(core::slice::from_ref(pass), vec![output_gradient.to_vec()])
+    }
+
+    //Like `forward` for every example of a batch, each with dropout masks of
+    //its own, going through the layers one at a time so that batch
+    //normalization layers in `Mode::Training` can normalize with the batch's
+    //statistics (updating their running ones)
+    fn forward_batch(&mut self, inputs: &[Vec<f32>]) -> Vec<ForwardPass> {
+        let examples = inputs.len();
+
+        let mut passes: Vec<ForwardPass> = inputs
+            .iter()
+            .map(|input| ForwardPass {
+                inputs: Vec::new(),
+                pre_activations: Vec::new(),
+                masks: self.dropout_masks(),
+                output: input.clone(),
+            })
+            .collect();
+
+        for (layer_idx, layer) in self.layers.iter_mut().enumerate() {
+            let activation = layer.topology().activation;
+
+            match layer {
+                Layer::BatchNorm(layer) if layer.normalizes_batch(examples) => {
+                    let inputs: Vec<f32> = passes
+                        .iter()
+                        .flat_map(|pass| pass.output())
+                        .copied()
+                        .collect();
+                    let (mut sums, mut outputs) = (Vec::new(), Vec::new());
+
+                    layer.trace_into(&inputs, &mut sums, &mut outputs);
+
+                    let neurons = layer.neurons();
+
+                    for ((pass, sums), outputs) in passes
+                        .iter_mut()
+                        .zip(sums.chunks_exact(neurons))
+                        .zip(outputs.chunks_exact(neurons))
+                    {
+                        let inputs = core::mem::replace(&mut pass.output, outputs.to_vec());
+
+                        pass.inputs.push(inputs);
+                        pass.pre_activations.push(sums.to_vec());
+                    }
+                }
+                layer => {
+                    for pass in &mut passes {
+                        let sums = forward_layer(layer, &pass.output, pass.masks.get(layer_idx));
+                        let outputs = sums.iter().map(|&sum| activation.apply(sum)).collect();
+                        let inputs = core::mem::replace(&mut pass.output, outputs);
+
+                        pass.inputs.push(inputs);
+                        pass.pre_activations.push(sums);
+                    }
+                }
+            }
+        }
+
+        passes
+    }
+
+    //Backpropagates every pass of a batch, given the loss' derivative with
+    //respect to each output, and sums up the gradients; batch normalization
+    //layers that normalized the batch with its own statistics backpropagate
+    //through them, since every input affected them
+    fn backward_batch(&self, passes: &[ForwardPass], output_gradients: Vec<Vec<f32>>) -> Vec<f32> {
+        let examples = passes.len();
+
+        let mut gradients = vec![0.0; self.weights_len()];
+        let mut offset = gradients.len();
+        let mut deltas = output_gradients;
+
+        for (layer_idx, layer) in self.layers.iter().enumerate().rev() {
+            let activation = layer.topology().activation;
+
+            offset -= layer.weights().len();
+
+            let gradients = &mut gradients[offset..][..layer.weights().len()];
+
+            //Derivatives with respect to the sums, before the activation
+            let sum_deltas = deltas.iter().zip(passes).map(|(deltas, pass)| {
+                deltas
+                    .iter()
+                    .zip(&pass.pre_activations[layer_idx])
+                    .map(|(delta, &sum)| delta * activation.derivative(sum))
+                    .collect::<Vec<f32>>()
+            });
+
+            deltas = match layer {
+                Layer::BatchNorm(layer) if layer.normalizes_batch(examples) => {
+                    let inputs: Vec<f32> = passes
+                        .iter()
+                        .flat_map(|pass| &pass.inputs[layer_idx])
+                        .copied()
+                        .collect();
+                    let sum_deltas: Vec<f32> = sum_deltas.flatten().collect();
+
+                    layer
+                        .backward_batch(&inputs, &sum_deltas, gradients)
+                        .chunks_exact(layer.neurons())
+                        .map(<[f32]>::to_vec)
+                        .collect()
+                }
+                layer => {
+                    let mut example_gradients = vec![0.0; gradients.len()];
+
+                    sum_deltas
+                        .zip(passes)
+                        .map(|(sum_deltas, pass)| {
+                            let input_deltas = backward_layer(
+                                layer,
+                                &pass.inputs[layer_idx],
+                                sum_deltas,
+                                pass.masks.get(layer_idx),
+                                &mut example_gradients,
+                            );
+
+                            for (gradient, example) in gradients.iter_mut().zip(&example_gradients)
+                            {
+                                *gradient += example;
+                            }
+
+                            input_deltas
+                        })
+                        .collect()
+                }
+            };
+        }
+
+        gradients
+    }
+
+    /// Runs one step of gradient descent over a batch of examples, returning
+    /// the batch's mean loss (measured before the update).
+    ///
+    /// In `Mode::Training`, dropout layers drop a different set of inputs
+    /// for every example, and batch normalization layers normalize the batch
+    /// with its own statistics, which then update their running ones. Fails without touching the network if it has
+    /// layers that can't be trained, see `forward`.
+    pub fn train_batch(
+        &mut self,
+        inputs: &[Vec<f32>],
+        targets: &[Vec<f32>],
+        loss: Loss,
+        optimizer: &mut dyn Optimizer,
+    ) -> Result<f32, NetworkError> {
+        self.train_batch_with_penalty(inputs, targets, loss, Penalty::default(), optimizer)
+    }
+
+    /// Like `train_batch`, but also minimizes the `penalty` on the weights;
+    /// the returned loss includes it.
+    pub fn train_batch_with_penalty(
+        &mut self,
+        inputs: &[Vec<f32>],
+        targets: &[Vec<f32>],
+        loss: Loss,
+        penalty: Penalty,
+        optimizer: &mut dyn Optimizer,
+    ) -> Result<f32, NetworkError> {
+        assert!(!inputs.is_empty());
+        assert_eq!(inputs.len(), targets.len());
+
+        self.check_trainable()?;
+
+        let passes = self.forward_batch(inputs);
+        let mut total_loss = 0.0;
+
+        let output_gradients = passes
+            .iter()
+            .zip(targets)
+            .map(|(pass, target)| {
+                total_loss += loss.loss(pass.output(), target);
+                loss.gradient(pass.output(), target)
+            })
+            .collect();
+
+        let mut gradients = self.backward_batch(&passes, output_gradients);
+
+        let batch_size = inputs.len() as f32;
+
+        for (gradient, penalty) in gradients.iter_mut().zip(penalty.gradient(self)) {
+            *gradient = *gradient / batch_size + penalty;
+        }
+
+        let penalty = penalty.penalty(self);
+
+        let mut params = self.weights();
+        optimizer.step(&mut params, &gradients);
+        self.set_weights(&params);
+
+        Ok(total_loss / batch_size + penalty)
+    }
+
+    /// Estimates the loss' gradient for one example by central finite
+    /// differences, nudging every weight by `step` in both directions.
+    ///
+    /// Returns gradients in the same layout as `weights()`. Unlike
+    /// `backward`, this only needs `propagate`, so it works for every layer
+    /// kind; every evaluation starts from a fresh state.
+    pub fn numerical_gradient(
+        &self,
+        inputs: &[f32],
+        targets: &[f32],
+        loss: Loss,
+        step: f32,
+    ) -> Vec<f32> {
+        let mut network = self.clone();
+        let mut weights = self.weights();
+        let mut scratch = Scratch::new();
+
+        let mut evaluate = |weights: &[f32]| {
+            network.set_weights(weights);
+            network.reset_state();
+            loss.loss(network.propagate_into(inputs, &mut scratch), targets)
+        };
+
+        (0..weights.len())
+            .map(|idx| {
+                let weight = weights[idx];
+
+                weights[idx] = weight + step;
+                let above = evaluate(&weights);
+
+                weights[idx] = weight - step;
+                let below = evaluate(&weights);
+
+                weights[idx] = weight;
+                (above - below) / (2.0 * step)
+            })
+            .collect()
+    }
+
+    /// Compares `backward`'s gradient for one example with
+    /// `numerical_gradient`'s, to catch mistakes in the derivatives.
+    pub fn check_gradient(
+        &self,
+        inputs: &[f32],
+        targets: &[f32],
+        loss: Loss,
+        step: f32,
+    ) -> Result<GradientCheck, NetworkError> {
+        let pass = self.forward(inputs.to_vec())?;
+        let analytic = self.backward_unchecked(&pass, &loss.gradient(pass.output(), targets));
+        let numerical = self.numerical_gradient(inputs, targets, loss, step);
+
+        let check = analytic
+            .iter()
+            .zip(&numerical)
+            .map(|(analytic, numerical)| (analytic - numerical).abs())
+            .enumerate()
+            .fold(GradientCheck::default(), |check, (weight, error)| {
+                if error > check.max_error {
+                    GradientCheck {
+                        max_error: error,
+                        weight,
+                    }
+                } else {
+                    check
+                }
+            });
+
+        Ok(check)
+    }
+
+    fn check_trainable(&self) -> Result<(), NetworkError> {
+        let untrainable = self.layers.iter().position(|layer| {
+            !matches!(
+                layer,
+                Layer::Dense(_) | Layer::LayerNorm(_) | Layer::BatchNorm(_) | Layer::Dropout(_)
+            )
+        });
+
+        match untrainable {
+            Some(idx) => Err(NetworkError::Untrainable { layer: idx + 1 }),
+            None => Ok(()),
+        }
+    }
+
+    //One per layer, empty for layers other than dropout ones
+    fn dropout_masks(&mut self) -> Vec<Vec<f32>> {
+        self.layers
+            .iter_mut()
+            .map(|layer| match layer {
+                Layer::Dropout(layer) => layer.mask(),
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+
+    fn weights_len(&self) -> usize {
+        self.layers.iter().map(|layer| layer.weights().len()).sum()
+    }
+
+    //Overwrites the parameters in place, following the layout of weights()
+    fn set_weights(&mut self, params: &[f32]) {
+        assert_eq!(params.len(), self.weights_len());
+
+        let mut params = params;
+
+        for layer in &mut self.layers {
+            let weights = layer.weights_mut();
+            let (layer_params, rest) = params.split_at(weights.len());
+
+            weights.copy_from_slice(layer_params);
+            params = rest;
+        }
+    }
+}
+
+//What `layer` computes for one example, before the activation; `mask` is
+//the layer's dropout mask, if there is one
+fn forward_layer(layer: &Layer, inputs: &[f32], mask: Option<&Vec<f32>>) -> Vec<f32> {
+    let mut sums = Vec::new();
+
+    match layer {
+        Layer::Dense(layer) => {
+            sums.extend((0..layer.neurons()).map(|neuron| layer.weighted_sum(neuron, inputs)))
+        }
+        Layer::LayerNorm(layer) => layer.normalize(inputs, &mut sums),
+        Layer::BatchNorm(layer) => layer.normalize(inputs, &mut sums),
+        Layer::Dropout(_) => match mask {
+            Some(mask) => sums.extend(inputs.iter().zip(mask).map(|(x, m)| x * m)),
+            None => sums.extend_from_slice(inputs),
+        },
+        _ => unreachable!("untrainable layers are rejected by check_trainable"),
+    }
+
+    sums
+}
+
+//Writes the gradients of `layer`'s weights for one example into
+//`gradients`, given the derivatives with respect to its sums, and returns
+//the derivatives with respect to its inputs
+fn backward_layer(
+    layer: &Layer,
+    inputs: &[f32],
+    sum_deltas: Vec<f32>,
+    mask: Option<&Vec<f32>>,
+    gradients: &mut [f32],
+) -> Vec<f32> {
+    match layer {
+        Layer::Dense(layer) => {
+            let mut input_deltas = vec![0.0; inputs.len()];
+
+            for (neuron, &delta) in sum_deltas.iter().enumerate() {
+                let at = neuron * (inputs.len() + 1);
+
+                gradients[at] = delta;
+
+                for (input_idx, (input, weight)) in
+                    inputs.iter().zip(&layer.row(neuron)[1..]).enumerate()
+                {
+                    gradients[at + 1 + input_idx] = delta * input;
+                    input_deltas[input_idx] += delta * weight;
+                }
+            }
+
+            input_deltas
+        }
+        Layer::LayerNorm(layer) => layer.backward(inputs, &sum_deltas, gradients),
+        Layer::BatchNorm(layer) => layer.backward(inputs, &sum_deltas, gradients),
+        Layer::Dropout(_) => match mask {
+            Some(mask) => sum_deltas.iter().zip(mask).map(|(d, m)| d * m).collect(),
+            None => sum_deltas,
+        },
+        _ => unreachable!("untrainable layers are rejected by check_trainable"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    fn topology() -> [LayerTopology; 3] {
+        [
+            LayerTopology::new(2).with_activation(Activation::Identity),
+            LayerTopology::new(3).with_activation(Activation::Tanh),
+            LayerTopology::new(1).with_activation(Activation::Sigmoid),
+        ]
+    }
+
+    fn network() -> Network {
+        let weights = (0..13).map(|n| ((n * 7 % 11) as f32 - 5.0) / 10.0);
+
+        Network::from_weights(&topology(), weights)
+    }
+
+    #[test]
+    fn test_forward_matches_propagate() {
+        let mut network = network();
+
+        let pass = network.forward(vec![0.3, -0.8]).unwrap();
+
+        assert_relative_eq!(pass.output(), network.propagate(vec![0.3, -0.8]).as_slice());
+    }
+
+    #[test]
+    fn test_backward_matches_finite_differences() {
+        let network = network();
+        let (input, target) = (vec![0.3, -0.8], vec![1.0]);
+
+        for loss in [Loss::MeanSquaredError, Loss::CrossEntropy] {
+            let pass = network.forward(input.clone()).unwrap();
+            let gradients = network
+                .backward(&pass, &loss.gradient(pass.output(), &target))
+                .unwrap();
+
+            let weights = network.weights();
+            let topology = topology();
+
+            for (idx, &gradient) in gradients.iter().enumerate() {
+                let h = 1e-2;
+                let mut nudged = weights.clone();
+
+                nudged[idx] += h;
+                let mut above = Network::from_weights(&topology, nudged.clone());
+
+                nudged[idx] -= 2.0 * h;
+                let mut below = Network::from_weights(&topology, nudged);
+
+                let numeric = (loss.loss(&above.propagate(input.clone()), &target)
+                    - loss.loss(&below.propagate(input.clone()), &target))
+                    / (2.0 * h);
+
+                assert_relative_eq!(gradient, numeric, epsilon = 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_train_batch_reduces_loss() {
+        let mut network = network();
+        let mut optimizer = Sgd::new(0.5);
+
+        let inputs = vec![vec![0.0, 1.0], vec![1.0, 0.0], vec![1.0, 1.0]];
+        let targets = vec![vec![1.0], vec![1.0], vec![0.0]];
+
+        let mut train = |network: &mut Network| {
+            network
+                .train_batch(&inputs, &targets, Loss::CrossEntropy, &mut optimizer)
+                .unwrap()
+        };
+
+        let before = train(&mut network);
+
+        for _ in 0..200 {
+            train(&mut network);
+        }
+
+        let after = train(&mut network);
+
+        assert!(after < before / 2.0, "{after} is not below {before} / 2");
+    }
+
+    #[test]
+    fn test_train_batch_with_batch_norm() {
+        let topology = [
+            LayerTopology::new(1).with_activation(Activation::Identity),
+            LayerTopology::new(1)
+                .with_activation(Activation::Identity)
+                .with_kind(LayerKind::BatchNorm),
+            LayerTopology::new(1).with_activation(Activation::Sigmoid),
+        ];
+
+        let mut network = Network::from_weights(&topology, [0.0, 1.0, 0.0, 0.0]);
+        let mut optimizer = Sgd::new(0.5);
+
+        //Far too large for the sigmoid without normalization
+        let inputs = vec![vec![4000.0], vec![4500.0], vec![5500.0], vec![6000.0]];
+        let targets = vec![vec![0.0], vec![0.0], vec![1.0], vec![1.0]];
+
+        network.set_mode(Mode::Training);
+
+        let mut train = |network: &mut Network| {
+            network
+                .train_batch(&inputs, &targets, Loss::CrossEntropy, &mut optimizer)
+                .unwrap()
+        };
+
+        let before = train(&mut network);
+
+        for _ in 0..200 {
+            train(&mut network);
+        }
+
+        let after = train(&mut network);
+
+        assert!(after < before / 2.0, "{after} is not below {before} / 2");
+
+        //Every batch is the same, so the running statistics end up as its own
+        let statistics = network.statistics();
+        assert_relative_eq!(statistics[0], 5000.0, max_relative = 1e-3);
+        assert_relative_eq!(statistics[1], 625000.0, max_relative = 1e-3);
+
+        //Which carries over to inference
+        network.set_mode(Mode::Inference);
+
+        assert!(network.propagate(vec![4200.0])[0] < 0.5);
+        assert!(network.propagate(vec![5800.0])[0] > 0.5);
+    }
+
+    #[test]
+    fn test_untrainable_layers() {
+        let topology = [
+            LayerTopology::new(2),
+            LayerTopology::new(3),
+            LayerTopology::new(1).with_kind(LayerKind::Elman),
+        ];
+
+        let mut network = Network::random(&topology);
+        let weights = network.weights();
+        let error = NetworkError::Untrainable { layer: 2 };
+
+        assert_eq!(network.forward(vec![0.3, -0.8]).unwrap_err(), error);
+
+        let trained = network.train_batch(
+            &[vec![0.3, -0.8]],
+            &[vec![1.0]],
+            Loss::MeanSquaredError,
+            &mut Sgd::new(0.5),
+        );
+
+        assert_eq!(trained.unwrap_err(), error);
+        assert_eq!(network.weights(), weights);
+    }
+
+    #[test]
+    fn test_check_gradient() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let activations = [
+            Activation::Tanh,
+            Activation::Sigmoid,
+            Activation::Softsign,
+            Activation::Identity,
+        ];
+
+        //Random dense networks with smooth activations, where finite
+        //differences are accurate
+        for _ in 0..20 {
+            let inputs = rng.gen_range(1..5);
+            let mut topology = vec![LayerTopology::new(inputs)];
+
+            for _ in 0..rng.gen_range(1..4) {
+                let activation = activations[rng.gen_range(0..activations.len())];
+                topology.push(LayerTopology::new(rng.gen_range(1..5)).with_activation(activation));
+            }
+
+            let network = Network::random_with(&mut rng, &topology, Init::default());
+            let outputs = topology.last().unwrap().neurons;
+
+            let input: Vec<f32> = (0..inputs).map(|_| rng.gen_range(-1.0..=1.0)).collect();
+            let target: Vec<f32> = (0..outputs).map(|_| rng.gen_range(0.0..=1.0)).collect();
+
+            let check = network
+                .check_gradient(&input, &target, Loss::MeanSquaredError, 1e-2)
+                .unwrap();
+
+            assert!(
+                check.max_error < 1e-3,
+                "{check:?} for {topology:?}, input {input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_gradient_through_normalization() {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+
+        let topology = [
+            LayerTopology::new(3),
+            LayerTopology::new(4).with_activation(Activation::Tanh),
+            LayerTopology::new(4)
+                .with_activation(Activation::Identity)
+                .with_kind(LayerKind::LayerNorm),
+            LayerTopology::new(4)
+                .with_activation(Activation::Identity)
+                .with_kind(LayerKind::Dropout(0.5)),
+            LayerTopology::new(2).with_activation(Activation::Sigmoid),
+        ];
+
+        let mut network = Network::random_with(&mut rng, &topology, Init::default());
+
+        //Moves β and γ away from where they start
+        let weights: Vec<f32> = network
+            .weights()
+            .iter()
+            .map(|weight| weight + rng.gen_range(-0.5..=0.5))
+            .collect();
+
+        network.set_weights(&weights);
+
+        let check = network
+            .check_gradient(&[0.5, -0.3, 0.8], &[0.2, 0.9], Loss::MeanSquaredError, 1e-2)
+            .unwrap();
+        assert!(check.max_error < 1e-3, "{check:?}");
+    }
+
+    #[test]
+    fn test_numerical_gradient_of_any_layer() {
+        let topology = [
+            LayerTopology::new(4),
+            LayerTopology::new(3).with_kind(LayerKind::Gru),
+            LayerTopology::conv1d(3, Convolution::new(2, 2)),
+            LayerTopology::new(2).with_activation(Activation::Identity),
+        ];
+
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut network = Network::random_with(&mut rng, &topology, Init::default());
+        let (input, target) = ([0.5, -0.2, 0.1, 0.9], [1.0, -1.0]);
+
+        let gradient = network.numerical_gradient(&input, &target, Loss::MeanSquaredError, 1e-2);
+        assert_eq!(gradient.len(), network.weights().len());
+
+        //The output layer is linear, so its biases get the loss' gradient
+        network.reset_state();
+        let outputs = network.propagate(input.to_vec());
+        let expected = Loss::MeanSquaredError.gradient(&outputs, &target);
+        let biases = gradient.len() - 2 * 5;
+
+        assert_relative_eq!(gradient[biases], expected[0], epsilon = 1e-3);
+        assert_relative_eq!(gradient[biases + 5], expected[1], epsilon = 1e-3);
+    }
+}
@@ -79,6 +79,7 @@ impl Simulation {
         Self { rng, sim }
     }
 
+    #[allow(deprecated)]
     pub fn world(&self) -> JsValue {
         let world = World::from(self.sim.get_world());
         JsValue::from_serde(&world).unwrap()
@@ -92,21 +93,30 @@ impl Simulation {
         let stats = self.sim.train(&mut self.rng);
 
         format!(
-            "min={:.2}, max={:.2}, avg={:.2}",
+            "min={:.2}, max={:.2}, avg={:.2}, diversity={:.2}",
             stats.min_fitness(),
             stats.max_fitness(),
-            stats.avg_fitness()
+            stats.avg_fitness(),
+            self.sim.get_diversity().unwrap_or_default()
         )
     }
+
+    /// SVG drawing of the last generation's best brain, if a generation has
+    /// finished yet.
+    pub fn champion_svg(&self) -> Option<String> {
+        self.sim.get_champion().map(|brain| brain.to_svg(None))
+    }
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl From<&sim::World> for World {
     fn from(world: &sim::World) -> Self {
-        let animals = world
-            .get_animals()
-            .iter()
-            .map(|e| Animal::from(e))
-            .collect();
+        let animals = world.get_animals().iter().map(Animal::from).collect();
 
         let foods = world.get_foods().iter().map(Food::from).collect();
 
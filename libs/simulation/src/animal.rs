@@ -13,7 +13,7 @@ pub struct Animal {
 impl Animal {
     pub fn random(rng: &mut dyn RngCore) -> Self {
         let eye = Eye::default();
-        let brain = Brain::random(&eye);
+        let brain = Brain::random(rng, &eye);
 
         Self::new(eye, brain, rng)
     }
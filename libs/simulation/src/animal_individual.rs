@@ -8,7 +8,7 @@ pub struct AnimalIndividual {
 impl AnimalIndividual {
     pub fn from_animal(animal: &Animal) -> Self {
         Self {
-            fitness: animal.satiation as f32,
+            fitness: (animal.satiation as f32 - animal.brain.penalty()).max(0.0),
             chromosome: animal.as_chromsome(),
         }
     }
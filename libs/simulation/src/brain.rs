@@ -1,37 +1,68 @@
 use crate::eye::Eye;
 use crate::*;
 
+/// Kind of the layer between the eye and the output.
+const HIDDEN_LAYER: nn::LayerKind = nn::LayerKind::Dense;
+
+/// Subtracted from a bird's fitness, so that out of two birds eating as much,
+/// the one with the smaller weights wins. Off by default; an L1 penalty of
+/// around 0.01 evolves sparser brains.
+const WEIGHT_PENALTY: nn::Penalty = nn::Penalty { l1: 0.0, l2: 0.0 };
+
+/// Network a bird thinks with. The simulation evolves `f32` brains, whose
+/// math - like the rest of the simulation's - can differ between platforms;
+/// `Replay` casts them to `nn::Fixed` to run bit-identically everywhere.
 #[derive(Debug)]
-pub struct Brain {
-    pub(crate) nn: nn::Network,
+pub struct Brain<T = f32> {
+    pub(crate) nn: nn::Network<T>,
 }
 
-impl Brain {
-    pub fn random(eye: &Eye) -> Self {
+impl<T: nn::Scalar> Brain<T> {
+    pub(crate) fn as_chromosome(&self) -> ga::Chromosome<T> {
+        ga::Chromosome::new(self.nn.weights())
+    }
+
+    pub(crate) fn from_chromosome(chromosome: ga::Chromosome<T>, eye: &Eye) -> Self {
         Self {
-            nn: nn::Network::random(&Self::topology(eye)),
+            nn: nn::Network::from_weights(&Brain::topology(eye), chromosome),
         }
     }
 
-    pub(crate) fn as_chromosome(&self) -> ga::Chromosome {
-        ga::Chromosome::new(self.nn.weights())
+    /// The same brain, computing with `U`.
+    pub fn cast<U: nn::Scalar>(&self) -> Brain<U> {
+        Brain { nn: self.nn.cast() }
     }
+}
 
-    pub(crate) fn from_chromosome(chromosome: ga::Chromosome, eye: &Eye) -> Self {
+impl Brain {
+    pub fn random(rng: &mut dyn RngCore, eye: &Eye) -> Self {
         Self {
-            nn: nn::Network::from_weights(&Self::topology(eye), chromosome),
+            nn: nn::Network::random_with(rng, &Self::topology(eye), nn::Init::default()),
         }
     }
 
-    fn topology(eye: &Eye) -> [nn::LayerTopology; 3] {
+    /// How much `WEIGHT_PENALTY` takes off the bird's fitness.
+    pub(crate) fn penalty(&self) -> f32 {
+        WEIGHT_PENALTY.penalty(&self.nn)
+    }
+
+    /// How the brain's outputs are read: one value for the speed and one
+    /// for the rotation, each added to the bird's own.
+    pub(crate) fn heads() -> nn::Heads {
+        nn::Heads::new()
+            .with_head("speed", 1, nn::HeadKind::Linear)
+            .with_head("rotation", 1, nn::HeadKind::Linear)
+    }
+
+    pub(crate) fn topology(eye: &Eye) -> [nn::LayerTopology; 3] {
         [
-            nn::LayerTopology {
-                neurons: eye.cells(),
-            },
-            nn::LayerTopology {
-                neurons: 2 * eye.cells(),
-            },
-            nn::LayerTopology { neurons: 2 },
+            nn::LayerTopology::new(eye.cells()),
+            nn::LayerTopology::new(2 * eye.cells()).with_kind(HIDDEN_LAYER),
+            //ReLU keeps the outputs non-negative, so birds only ever speed up
+            //and turn one way, as they always have
+            Self::heads()
+                .topology()
+                .with_activation(nn::Activation::Relu),
         ]
     }
 }
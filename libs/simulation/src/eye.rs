@@ -1,4 +1,5 @@
 use crate::*;
+use nn::Scalar;
 use std::{f32::consts::*, f64::consts::PI};
 
 /// How far our eye can see:
@@ -123,7 +124,22 @@ impl Eye {
         rotation: na::Rotation2<f32>,
         foods: &[Food],
     ) -> Vec<f32> {
-        let mut cells = vec![0.0; self.cells];
+        let mut cells = Vec::new();
+        self.process_vision_into(position, rotation, foods, &mut cells);
+        cells
+    }
+
+    //Same as process_vision, but reuses the given buffer for the cells
+    pub fn process_vision_into(
+        &self,
+        position: na::Point2<f32>,
+        rotation: na::Rotation2<f32>,
+        foods: &[Food],
+        cells: &mut Vec<f32>,
+    ) {
+        cells.clear();
+        cells.resize(self.cells, 0.0);
+
         for food in foods {
             let vec = food.position - position;
             let dist = vec.norm();
@@ -157,7 +173,45 @@ impl Eye {
             let energy = (self.fov_range - dist) / self.fov_range;
             cells[cell] += energy;
         }
-        cells
+    }
+
+    //Same as process_vision_into, in fixed-point for `Replay`; `angle` is the
+    //bird's rotation, and positions are (x, y)
+    pub(crate) fn process_fixed_vision_into(
+        &self,
+        position: [nn::Fixed; 2],
+        angle: nn::Fixed,
+        foods: &[[nn::Fixed; 2]],
+        cells: &mut Vec<nn::Fixed>,
+    ) {
+        let fov_range = nn::Fixed::from_f32(self.fov_range);
+        let fov_angle = nn::Fixed::from_f32(self.fov_angle);
+        let half_fov_angle = nn::Fixed::from_f32(self.fov_angle / 2.0);
+
+        cells.clear();
+        cells.resize(self.cells, nn::Fixed::ZERO);
+
+        for food in foods {
+            let (x, y) = (food[0] - position[0], food[1] - position[1]);
+            let dist = (x * x + y * y).sqrt();
+
+            if dist >= fov_range {
+                continue;
+            }
+
+            //Angle between the y axis and the food, like Rotation2::rotation_between
+            let angle = fixed::wrap_angle(fixed::atan2(-x, y) - angle);
+
+            if angle < -half_fov_angle || angle > half_fov_angle {
+                continue;
+            }
+
+            let cell =
+                (angle + half_fov_angle) / fov_angle * nn::Fixed::from_f64(self.cells as f64);
+            let cell = ((cell.to_bits() >> nn::Fixed::FRAC_BITS) as usize).min(cells.len() - 1);
+
+            cells[cell] = cells[cell] + (fov_range - dist) / fov_range;
+        }
     }
 }
 
@@ -0,0 +1,112 @@
+use crate::*;
+use nn::{Fixed, Scalar};
+
+//π, π / 2 and 2π, rounded to the nearest Q16.16 value
+pub(crate) const PI: Fixed = Fixed::from_bits(205_887);
+pub(crate) const FRAC_PI_2: Fixed = Fixed::from_bits(102_944);
+pub(crate) const TAU: Fixed = Fixed::from_bits(411_775);
+
+//Trigonometry for `Replay`, with `Fixed` arithmetic only, so that it gives
+//the same results on every platform (unlike `f32::sin` & co., which come
+//from the platform's math library)
+
+/// Wraps an angle into [-π, π).
+pub(crate) fn wrap_angle(mut angle: Fixed) -> Fixed {
+    while angle >= PI {
+        angle = angle - TAU;
+    }
+
+    while angle < -PI {
+        angle = angle + TAU;
+    }
+
+    angle
+}
+
+pub(crate) fn sin(angle: Fixed) -> Fixed {
+    //sin(π - x) = sin(x) brings the angle into [-π/2, π/2]
+    let x = match wrap_angle(angle) {
+        x if x > FRAC_PI_2 => PI - x,
+        x if x < -FRAC_PI_2 => -PI - x,
+        x => x,
+    };
+
+    //Taylor series up to x^9, accurate to ~4e-6 in that range:
+    //x (1 - x²/6 (1 - x²/20 (1 - x²/42 (1 - x²/72))))
+    let x2 = x * x;
+
+    [72, 42, 20, 6]
+        .into_iter()
+        .fold(Fixed::ONE, |sum, divisor| {
+            Fixed::ONE - x2 / Fixed::from_bits(divisor << Fixed::FRAC_BITS) * sum
+        })
+        * x
+}
+
+pub(crate) fn cos(angle: Fixed) -> Fixed {
+    sin(angle + FRAC_PI_2)
+}
+
+/// Angle of the vector (x, y), in [-π, π].
+pub(crate) fn atan2(y: Fixed, x: Fixed) -> Fixed {
+    if x == Fixed::ZERO && y == Fixed::ZERO {
+        return Fixed::ZERO;
+    }
+
+    if x.abs() >= y.abs() {
+        let angle = atan(y / x);
+
+        match (x < Fixed::ZERO, y < Fixed::ZERO) {
+            (false, _) => angle,
+            (true, false) => angle + PI,
+            (true, true) => angle - PI,
+        }
+    } else if y > Fixed::ZERO {
+        FRAC_PI_2 - atan(x / y)
+    } else {
+        -FRAC_PI_2 - atan(x / y)
+    }
+}
+
+//Arctangent of a value in [-1, 1]; the polynomial from Abramowitz & Stegun
+//(4.4.47) is accurate to ~1e-5
+fn atan(x: Fixed) -> Fixed {
+    let x2 = x * x;
+
+    [-0.0851330, 0.1801410, -0.3302995, 0.9998660]
+        .into_iter()
+        .fold(Fixed::from_f64(0.0208351), |sum, coefficient| {
+            sum * x2 + Fixed::from_f64(coefficient)
+        })
+        * x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trigonometry() {
+        for step in -80..=80 {
+            let angle = step as f64 / 10.0;
+            let fixed = Fixed::from_f64(angle);
+
+            assert!(
+                (sin(fixed).to_f64() - angle.sin()).abs() < 1e-4,
+                "sin({angle})"
+            );
+            assert!(
+                (cos(fixed).to_f64() - angle.cos()).abs() < 1e-4,
+                "cos({angle})"
+            );
+
+            let (y, x) = (angle.sin() * 0.3, angle.cos() * 0.3);
+            let expected = y.atan2(x);
+            let found = atan2(Fixed::from_f64(y), Fixed::from_f64(x)).to_f64();
+
+            //Both ends of [-π, π] are the same direction
+            let error = (found - expected).abs();
+            assert!(error.min(TAU.to_f64() - error) < 1e-3, "atan2({y}, {x})");
+        }
+    }
+}
@@ -1,13 +1,14 @@
-pub use self::{animal::*, brain::*, food::*, world::*};
+pub use self::{animal::*, brain::*, food::*, replay::*, world::*};
 mod animal;
 mod animal_individual;
 mod brain;
 mod eye;
+mod fixed;
 mod food;
+mod replay;
 mod world;
 use self::animal_individual::*;
 use genetic_algorithm as ga;
-use na::distance;
 use nalgebra as na;
 use neural_network as nn;
 use rand::{Rng, RngCore};
@@ -25,6 +26,15 @@ pub struct Simulation {
     world: World,
     ga: ga::GeneticAlgorithm<ga::RouletteWheelSelection>,
     age: usize,
+    //Buffers reused by every brain on every step, so that thinking doesn't allocate
+    vision: Vec<f32>,
+    scratch: nn::Scratch,
+    heads: nn::Heads,
+    response: Vec<f32>,
+    //Brain of the best bird of the last finished generation
+    champion: Option<nn::Network>,
+    //Mean weight distance between the last finished generation's brains
+    diversity: Option<f32>,
 }
 
 impl Simulation {
@@ -35,7 +45,17 @@ impl Simulation {
             ga::UniformCrossover::new(),
             ga::GaussianMutation::new(0.01, 0.3),
         );
-        Self { world, ga, age: 0 }
+        Self {
+            world,
+            ga,
+            age: 0,
+            vision: Vec::new(),
+            scratch: nn::Scratch::new(),
+            heads: Brain::heads(),
+            response: Vec::new(),
+            champion: None,
+            diversity: None,
+        }
     }
 
     pub fn step(&mut self, rng: &mut dyn RngCore) -> Option<ga::Statistics> {
@@ -75,14 +95,25 @@ impl Simulation {
 
     fn process_brains(&mut self) {
         for animal in &mut self.world.animals {
-            let vision =
-                animal
-                    .eye
-                    .process_vision(animal.position, animal.rotation, &self.world.foods);
-            let response = animal.brain.nn.propagate(vision);
-
-            let speed = response[0].clamp(-SPEED_ACCEL, SPEED_ACCEL);
-            let rotation = response[1].clamp(-ROTATION_ACCEL, ROTATION_ACCEL);
+            animal.eye.process_vision_into(
+                animal.position,
+                animal.rotation,
+                &self.world.foods,
+                &mut self.vision,
+            );
+
+            //Every bird has its own weights, so each brain runs separately, but
+            //they all share the same buffers
+            let outputs = animal
+                .brain
+                .nn
+                .propagate_into(&self.vision, &mut self.scratch);
+
+            let response = self.heads.apply_into(outputs, &mut self.response);
+            let speed = response.value("speed").clamp(-SPEED_ACCEL, SPEED_ACCEL);
+            let rotation = response
+                .value("rotation")
+                .clamp(-ROTATION_ACCEL, ROTATION_ACCEL);
 
             animal.speed = (animal.speed + speed).clamp(SPEED_MIN, SPEED_MAX);
             animal.rotation = na::Rotation2::new(animal.rotation.angle() + rotation);
@@ -105,9 +136,21 @@ impl Simulation {
             .world
             .animals
             .iter()
-            .map(|animal| AnimalIndividual::from_animal(animal))
+            .map(AnimalIndividual::from_animal)
             .collect();
 
+        //Kept around for reports, since the birds are about to be replaced
+        self.champion = self
+            .world
+            .animals
+            .iter()
+            .max_by_key(|animal| animal.satiation)
+            .map(|animal| animal.brain.nn.clone());
+
+        self.diversity = Some(nn::Network::diversity(
+            self.world.animals.iter().map(|animal| &animal.brain.nn),
+        ));
+
         //Step 2: Evolve Birdies
         let (evolved_population, stats) = self.ga.evolve(rng, &current_population);
 
@@ -131,4 +174,17 @@ impl Simulation {
     pub fn get_world(&self) -> &World {
         &self.world
     }
+
+    /// Brain of the bird that ate the most during the last finished
+    /// generation, e.g. to export it with `to_dot` or `to_svg`.
+    pub fn get_champion(&self) -> Option<&nn::Network> {
+        self.champion.as_ref()
+    }
+
+    /// How different the last finished generation's brains were from each
+    /// other, see `nn::Network::diversity`; close to zero, the population
+    /// has collapsed to clones and mutation is all that's left to explore.
+    pub fn get_diversity(&self) -> Option<f32> {
+        self.diversity
+    }
 }
@@ -0,0 +1,177 @@
+use crate::{eye::Eye, *};
+use nn::{Fixed, Scalar};
+
+/// As many as in a `World`.
+const FOODS: usize = 60;
+
+/// A single bird, thinking with a brain cast to `nn::Fixed`, in a world of
+/// its own.
+///
+/// Unlike `Simulation`, whose `f32` math relies on the platform's
+/// trigonometry, everything here - vision, steering and movement - is
+/// computed in Q16.16 fixed-point. The same brain, stepped with generators
+/// seeded the same way, follows bit-identical trajectories on every platform,
+/// e.g. natively and in wasm; they're close to, but not the same as, what the
+/// `f32` bird would do.
+#[derive(Debug)]
+pub struct Replay {
+    eye: Eye,
+    brain: Brain<Fixed>,
+    //(x, y), like the positions of the foods
+    position: [Fixed; 2],
+    rotation: Fixed,
+    speed: Fixed,
+    foods: Vec<[Fixed; 2]>,
+    satiation: usize,
+    //Buffers reused on every step, so that replaying doesn't allocate
+    vision: Vec<Fixed>,
+    scratch: nn::Scratch<Fixed>,
+}
+
+impl Replay {
+    /// Places a bird thinking with `network`, e.g. `Simulation::get_champion`,
+    /// and the foods at random.
+    pub fn new(rng: &mut dyn RngCore, network: &nn::Network) -> Self {
+        let eye = Eye::default();
+        let brain = Brain::from_chromosome(ga::Chromosome::new(network.weights()), &eye);
+
+        Self {
+            position: random_point(rng),
+            rotation: Fixed::from_bits(rng.gen_range(-fixed::PI.to_bits()..fixed::PI.to_bits())),
+            speed: Fixed::from_f32(0.002),
+            foods: (0..FOODS).map(|_| random_point(rng)).collect(),
+            satiation: 0,
+            vision: Vec::new(),
+            scratch: nn::Scratch::new(),
+            brain: brain.cast(),
+            eye,
+        }
+    }
+
+    /// Moves the bird like `Simulation::step` does.
+    pub fn step(&mut self, rng: &mut dyn RngCore) {
+        let reach = Fixed::from_f64(0.01);
+
+        for food in &mut self.foods {
+            let (x, y) = (food[0] - self.position[0], food[1] - self.position[1]);
+
+            if (x * x + y * y).sqrt() <= reach {
+                self.satiation += 1;
+                *food = random_point(rng);
+            }
+        }
+
+        self.eye.process_fixed_vision_into(
+            self.position,
+            self.rotation,
+            &self.foods,
+            &mut self.vision,
+        );
+
+        //`Brain::heads` are linear, so the outputs are the speed and rotation
+        //as they are
+        let outputs = self
+            .brain
+            .nn
+            .propagate_into(&self.vision, &mut self.scratch);
+
+        let speed_accel = Fixed::from_f32(SPEED_ACCEL);
+        let rotation_accel = Fixed::from_f32(ROTATION_ACCEL);
+        let speed = outputs[0].clamp(-speed_accel, speed_accel);
+        let rotation = outputs[1].clamp(-rotation_accel, rotation_accel);
+
+        self.speed =
+            (self.speed + speed).clamp(Fixed::from_f32(SPEED_MIN), Fixed::from_f32(SPEED_MAX));
+        self.rotation = fixed::wrap_angle(self.rotation + rotation);
+
+        //Rotating (0, speed), like the f32 birds move
+        let movement = [
+            -self.speed * fixed::sin(self.rotation),
+            self.speed * fixed::cos(self.rotation),
+        ];
+
+        for (coordinate, movement) in self.position.iter_mut().zip(movement) {
+            *coordinate = wrap_unit(*coordinate + movement);
+        }
+    }
+
+    /// The bird's (x, y) position.
+    pub fn position(&self) -> [Fixed; 2] {
+        self.position
+    }
+
+    pub fn rotation(&self) -> Fixed {
+        self.rotation
+    }
+
+    /// How many foods the bird has eaten.
+    pub fn satiation(&self) -> usize {
+        self.satiation
+    }
+}
+
+//Random point of the [0, 1) square, drawn as integers
+fn random_point(rng: &mut dyn RngCore) -> [Fixed; 2] {
+    [(); 2].map(|_| Fixed::from_bits(rng.gen_range(0..Fixed::ONE.to_bits())))
+}
+
+//Wraps a coordinate into [0, 1), like na::wrap does for the f32 birds
+fn wrap_unit(value: Fixed) -> Fixed {
+    if value < Fixed::ZERO {
+        value + Fixed::ONE
+    } else if value >= Fixed::ONE {
+        value - Fixed::ONE
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    //Weights that don't depend on any platform's math, unlike random ones
+    fn network() -> nn::Network {
+        let topology = Brain::topology(&Eye::default());
+
+        //Every layer is dense, with a bias and a weight per input
+        let weights_len: usize = topology
+            .windows(2)
+            .map(|layers| (layers[0].neurons + 1) * layers[1].neurons)
+            .sum();
+
+        let weights = (0..weights_len).map(|n| ((n * 7 % 11) as f32 - 5.0) / 10.0);
+
+        nn::Network::from_weights(&topology, weights)
+    }
+
+    fn trajectory() -> (Vec<[Fixed; 2]>, usize) {
+        let mut rng = ChaCha8Rng::from_seed(Default::default());
+        let mut replay = Replay::new(&mut rng, &network());
+
+        let positions = (0..2000)
+            .map(|_| {
+                replay.step(&mut rng);
+                replay.position()
+            })
+            .collect();
+
+        (positions, replay.satiation())
+    }
+
+    #[test]
+    fn test_replay_is_exact() {
+        let (positions, satiation) = trajectory();
+
+        assert_eq!(trajectory(), (positions.clone(), satiation));
+
+        //Pinned, so that a platform computing anything differently fails
+        assert_eq!(
+            positions.last().unwrap().map(Fixed::to_bits),
+            [20045, 56908]
+        );
+        assert_eq!(satiation, 2);
+    }
+}